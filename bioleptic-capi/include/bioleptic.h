@@ -0,0 +1,103 @@
+#ifndef BIOLEPTIC_H
+#define BIOLEPTIC_H
+
+/* Generated with cbindgen, regenerate with `cbindgen --config cbindgen.toml --output include/bioleptic.h` */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define BIOLP_OK 0
+
+#define BIOLP_ERROR_NULL_POINTER -1
+
+#define BIOLP_ERROR_PANIC -2
+
+#define BIOLP_ERROR_INVALID_COMPRESSION_METHOD -3
+
+#define BIOLP_ERROR_INVALID_MAGIC -4
+
+#define BIOLP_ERROR_INVALID_DATA_TYPE -5
+
+#define BIOLP_ERROR_INVALID_VERSION -6
+
+#define BIOLP_ERROR_DWT -7
+
+#define BIOLP_ERROR_COMPRESSOR -8
+
+#define BIOLP_ERROR_OUT_OF_MEMORY -9
+
+#define BIOLP_ERROR_INVALID_HEADER -10
+
+#define BIOLP_ERROR_UNSUPPORTED_CONFIGURATION -11
+
+#define BIOLP_ERROR_DECOMPRESSION -12
+
+#define BIOLP_ERROR_INVALID_QUANTIZATION_SCALE -13
+
+#define BIOLP_ERROR_OUT_OF_BOUNDS -14
+
+#define BIOLP_ERROR_INVALID_CONFIG_CODE -15
+
+#define BIOLP_ERROR_INVALID_HEADER_EXTENSION -16
+
+#define BIOLP_ERROR_INVALID_BATCH_INDEX -17
+
+#define BIOLP_ERROR_CHECKSUM_MISMATCH -18
+
+#define BIOLP_ERROR_UNSUPPORTED_VERSION -19
+
+// Encoder settings, laid out as the fields of `CompressionOptions::to_code`.
+typedef struct BiolpOptions {
+  // 0 Cdf53, 1 Cdf97, 2 Db4, 3 Sym4, 4 Db2, 5 Db6, 6 Db8, 7 Coif1, 8 Coif2, 9 Coif3,
+  // 10 Sym6, 11 Sym8, 12 Bior22, 13 Bior44, 14 Bior68.
+  uint32_t method;
+  // Quantization scale shift in `6..=12`.
+  uint32_t scale;
+  // 0 Low, 1 Medium, 2 High, 3 None.
+  uint32_t cutoff;
+} BiolpOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Compresses `len` samples at `data` into a buffer stored to `out` and `out_len`.
+//
+// The buffer must be released with [`biolp_free`]. Returns [`BIOLP_OK`] or a negative
+// error code, `out` is left untouched on error.
+//
+// # Safety
+//
+// `data` must point to `len` readable floats, `out` and `out_len` must be writable.
+int32_t biolp_compress(const float *data,
+                       size_t len,
+                       struct BiolpOptions options,
+                       uint8_t **out,
+                       size_t *out_len);
+
+// Decompresses `len` bytes at `data` into a buffer of samples stored to `out` and
+// `out_len`.
+//
+// The buffer must be released with [`biolp_free`]. Returns [`BIOLP_OK`] or a negative
+// error code, `out` is left untouched on error.
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes, `out` and `out_len` must be writable.
+int32_t biolp_decompress(const uint8_t *data, size_t len, float **out, size_t *out_len);
+
+// Releases a buffer returned by [`biolp_compress`] or [`biolp_decompress`], null is
+// ignored.
+//
+// # Safety
+//
+// `ptr` must be null or a buffer returned by this library that wasn't freed yet.
+void biolp_free(void *ptr);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BIOLEPTIC_H */
@@ -0,0 +1,282 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use bioleptic::{BiolepticError, CompressionOptions, compress, decompress};
+use std::alloc::{Layout, alloc, dealloc, handle_alloc_error};
+use std::ffi::c_void;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+pub const BIOLP_OK: i32 = 0;
+pub const BIOLP_ERROR_NULL_POINTER: i32 = -1;
+pub const BIOLP_ERROR_PANIC: i32 = -2;
+pub const BIOLP_ERROR_INVALID_COMPRESSION_METHOD: i32 = -3;
+pub const BIOLP_ERROR_INVALID_MAGIC: i32 = -4;
+pub const BIOLP_ERROR_INVALID_DATA_TYPE: i32 = -5;
+pub const BIOLP_ERROR_INVALID_VERSION: i32 = -6;
+pub const BIOLP_ERROR_DWT: i32 = -7;
+pub const BIOLP_ERROR_COMPRESSOR: i32 = -8;
+pub const BIOLP_ERROR_OUT_OF_MEMORY: i32 = -9;
+pub const BIOLP_ERROR_INVALID_HEADER: i32 = -10;
+pub const BIOLP_ERROR_UNSUPPORTED_CONFIGURATION: i32 = -11;
+pub const BIOLP_ERROR_DECOMPRESSION: i32 = -12;
+pub const BIOLP_ERROR_INVALID_QUANTIZATION_SCALE: i32 = -13;
+pub const BIOLP_ERROR_OUT_OF_BOUNDS: i32 = -14;
+pub const BIOLP_ERROR_INVALID_CONFIG_CODE: i32 = -15;
+pub const BIOLP_ERROR_INVALID_HEADER_EXTENSION: i32 = -16;
+pub const BIOLP_ERROR_INVALID_BATCH_INDEX: i32 = -17;
+pub const BIOLP_ERROR_CHECKSUM_MISMATCH: i32 = -18;
+pub const BIOLP_ERROR_UNSUPPORTED_VERSION: i32 = -19;
+
+/// Encoder settings, laid out as the fields of `CompressionOptions::to_code`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct BiolpOptions {
+    /// 0 Cdf53, 1 Cdf97, 2 Db4, 3 Sym4, 4 Db2, 5 Db6, 6 Db8, 7 Coif1, 8 Coif2, 9 Coif3,
+    /// 10 Sym6, 11 Sym8, 12 Bior22, 13 Bior44, 14 Bior68.
+    pub method: u32,
+    /// Quantization scale shift in `6..=12`.
+    pub scale: u32,
+    /// 0 Low, 1 Medium, 2 High, 3 None.
+    pub cutoff: u32,
+}
+
+impl BiolpOptions {
+    fn to_options(self) -> Result<CompressionOptions, BiolepticError> {
+        if self.method > 0xff || self.scale > 0x1f || self.cutoff > 0x3 {
+            return Err(BiolepticError::InvalidConfigCode(self.method));
+        }
+        CompressionOptions::from_code(self.method | (self.scale << 8) | (self.cutoff << 13))
+    }
+}
+
+fn error_code(error: &BiolepticError) -> i32 {
+    match error {
+        BiolepticError::InvalidCompressionMethod(_) => BIOLP_ERROR_INVALID_COMPRESSION_METHOD,
+        BiolepticError::InvalidMagic(_) => BIOLP_ERROR_INVALID_MAGIC,
+        BiolepticError::InvalidDataType(_) => BIOLP_ERROR_INVALID_DATA_TYPE,
+        BiolepticError::InvalidVersion(_) => BIOLP_ERROR_INVALID_VERSION,
+        BiolepticError::UnsupportedVersion { .. } => BIOLP_ERROR_UNSUPPORTED_VERSION,
+        BiolepticError::UnderlyingDwtError(_) => BIOLP_ERROR_DWT,
+        BiolepticError::UnderlyingCompressorError(_) => BIOLP_ERROR_COMPRESSOR,
+        BiolepticError::OutOfMemoryError(_) => BIOLP_ERROR_OUT_OF_MEMORY,
+        BiolepticError::InvalidHeader => BIOLP_ERROR_INVALID_HEADER,
+        BiolepticError::UnsupportedCompressorConfiguration(_) => {
+            BIOLP_ERROR_UNSUPPORTED_CONFIGURATION
+        }
+        BiolepticError::DecompressionError(_) => BIOLP_ERROR_DECOMPRESSION,
+        BiolepticError::InvalidQuantizationScale(_) => BIOLP_ERROR_INVALID_QUANTIZATION_SCALE,
+        BiolepticError::OutOfBounds(..) => BIOLP_ERROR_OUT_OF_BOUNDS,
+        BiolepticError::InvalidConfigCode(_) => BIOLP_ERROR_INVALID_CONFIG_CODE,
+        BiolepticError::InvalidHeaderExtension => BIOLP_ERROR_INVALID_HEADER_EXTENSION,
+        BiolepticError::InvalidBatchIndex(_) => BIOLP_ERROR_INVALID_BATCH_INDEX,
+        BiolepticError::ChecksumMismatch(..) => BIOLP_ERROR_CHECKSUM_MISMATCH,
+    }
+}
+
+/// Returned buffers carry their allocation size in a prefix, so a single
+/// [`biolp_free`] releases buffers of any element type.
+const BUFFER_PREFIX: usize = 16;
+
+fn export_buffer<T: Copy>(data: &[T]) -> *mut T {
+    let size = BUFFER_PREFIX + size_of_val(data);
+    let layout = Layout::from_size_align(size, BUFFER_PREFIX).unwrap();
+    unsafe {
+        let base = alloc(layout);
+        if base.is_null() {
+            handle_alloc_error(layout);
+        }
+        (base as *mut usize).write(size);
+        let ptr = base.add(BUFFER_PREFIX) as *mut T;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+        ptr
+    }
+}
+
+/// Runs `f` and converts its outcome to an error code, a panic must not unwind into C.
+fn guarded(f: impl FnOnce() -> Result<(), BiolepticError>) -> i32 {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => BIOLP_OK,
+        Ok(Err(error)) => error_code(&error),
+        Err(_) => BIOLP_ERROR_PANIC,
+    }
+}
+
+/// Compresses `len` samples at `data` into a buffer stored to `out` and `out_len`.
+///
+/// The buffer must be released with [`biolp_free`]. Returns [`BIOLP_OK`] or a negative
+/// error code, `out` is left untouched on error.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable floats, `out` and `out_len` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn biolp_compress(
+    data: *const f32,
+    len: usize,
+    options: BiolpOptions,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    if data.is_null() || out.is_null() || out_len.is_null() {
+        return BIOLP_ERROR_NULL_POINTER;
+    }
+    guarded(|| {
+        let samples = unsafe { std::slice::from_raw_parts(data, len) };
+        let compressed = compress(samples, options.to_options()?)?;
+        unsafe {
+            *out = export_buffer(&compressed);
+            *out_len = compressed.len();
+        }
+        Ok(())
+    })
+}
+
+/// Decompresses `len` bytes at `data` into a buffer of samples stored to `out` and
+/// `out_len`.
+///
+/// The buffer must be released with [`biolp_free`]. Returns [`BIOLP_OK`] or a negative
+/// error code, `out` is left untouched on error.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, `out` and `out_len` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn biolp_decompress(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut f32,
+    out_len: *mut usize,
+) -> i32 {
+    if data.is_null() || out.is_null() || out_len.is_null() {
+        return BIOLP_ERROR_NULL_POINTER;
+    }
+    guarded(|| {
+        let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+        let samples = decompress(bytes)?;
+        unsafe {
+            *out = export_buffer(&samples);
+            *out_len = samples.len();
+        }
+        Ok(())
+    })
+}
+
+/// Releases a buffer returned by [`biolp_compress`] or [`biolp_decompress`], null is
+/// ignored.
+///
+/// # Safety
+///
+/// `ptr` must be null or a buffer returned by this library that wasn't freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn biolp_free(ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    unsafe {
+        let base = (ptr as *mut u8).sub(BUFFER_PREFIX);
+        let size = (base as *const usize).read();
+        dealloc(base, Layout::from_size_align_unchecked(size, BUFFER_PREFIX));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let signal = (0..4000)
+            .map(|i| (i as f32 * 0.05).sin() * 100.)
+            .collect::<Vec<f32>>();
+        let options = BiolpOptions {
+            method: 1,
+            scale: 11,
+            cutoff: 0,
+        };
+        let mut compressed = std::ptr::null_mut();
+        let mut compressed_len = 0usize;
+        let mut decompressed = std::ptr::null_mut();
+        let mut decompressed_len = 0usize;
+        unsafe {
+            assert_eq!(
+                biolp_compress(
+                    signal.as_ptr(),
+                    signal.len(),
+                    options,
+                    &mut compressed,
+                    &mut compressed_len,
+                ),
+                BIOLP_OK
+            );
+            assert_eq!(
+                biolp_decompress(
+                    compressed,
+                    compressed_len,
+                    &mut decompressed,
+                    &mut decompressed_len,
+                ),
+                BIOLP_OK
+            );
+            let restored = std::slice::from_raw_parts(decompressed, decompressed_len);
+            assert_eq!(
+                restored,
+                decompress(std::slice::from_raw_parts(compressed, compressed_len)).unwrap()
+            );
+            assert_eq!(
+                biolp_decompress(compressed, 8, &mut decompressed, &mut decompressed_len),
+                BIOLP_ERROR_INVALID_HEADER
+            );
+            biolp_free(compressed as *mut c_void);
+            biolp_free(decompressed as *mut c_void);
+        }
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let mut out = std::ptr::null_mut();
+        let mut samples = std::ptr::null_mut();
+        let mut out_len = 0usize;
+        let options = BiolpOptions {
+            method: 99,
+            scale: 11,
+            cutoff: 0,
+        };
+        let signal = [1f32; 100];
+        unsafe {
+            assert_eq!(
+                biolp_compress(signal.as_ptr(), 100, options, &mut out, &mut out_len),
+                BIOLP_ERROR_INVALID_CONFIG_CODE
+            );
+            assert_eq!(
+                biolp_decompress(std::ptr::null(), 0, &mut samples, &mut out_len),
+                BIOLP_ERROR_NULL_POINTER
+            );
+        }
+        assert!(out.is_null() && samples.is_null());
+    }
+}
@@ -0,0 +1,46 @@
+/*
+ * Round trip through the C API.
+ *
+ * cargo build -p bioleptic-capi --release
+ * cc tests/roundtrip.c -Iinclude -L../target/release -lbioleptic_capi -lm -lpthread -ldl -o roundtrip
+ * ./roundtrip
+ */
+#include <math.h>
+#include <stdio.h>
+
+#include "bioleptic.h"
+
+int main(void) {
+  enum { SAMPLES = 4000 };
+  static float signal[SAMPLES];
+  for (size_t i = 0; i < SAMPLES; i++) {
+    signal[i] = sinf((float)i * 0.05f) * 100.f;
+  }
+
+  BiolpOptions options = {.method = 1, .scale = 11, .cutoff = 0};
+  uint8_t *compressed = NULL;
+  size_t compressed_len = 0;
+  int32_t status = biolp_compress(signal, SAMPLES, options, &compressed, &compressed_len);
+  if (status != BIOLP_OK) {
+    fprintf(stderr, "biolp_compress failed with %d\n", status);
+    return 1;
+  }
+
+  float *restored = NULL;
+  size_t restored_len = 0;
+  status = biolp_decompress(compressed, compressed_len, &restored, &restored_len);
+  if (status != BIOLP_OK || restored_len != SAMPLES) {
+    fprintf(stderr, "biolp_decompress failed with %d\n", status);
+    return 1;
+  }
+
+  float max_error = 0.f;
+  for (size_t i = 0; i < SAMPLES; i++) {
+    max_error = fmaxf(max_error, fabsf(signal[i] - restored[i]));
+  }
+  printf("%zu bytes, max error %f\n", compressed_len, max_error);
+
+  biolp_free(compressed);
+  biolp_free(restored);
+  return max_error < 1.f ? 0 : 1;
+}
@@ -27,7 +27,8 @@
  * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
 use bioleptic::{
-    CompressionMethod, CompressionOptions, CutoffLevel, QuantizationScale, compress, decompress,
+    CompressionMethod, CompressionOptions, CutoffLevel, QuantizationScale, StreamingCompressor,
+    StreamingDecompressor, compress, decompress, prd, rmse,
 };
 use wasm_bindgen::prelude::*;
 
@@ -43,6 +44,17 @@ pub enum BiolpCompressionMethod {
     Cdf53,
     Sym4,
     Db4,
+    Db2,
+    Db6,
+    Db8,
+    Sym6,
+    Sym8,
+    Coif1,
+    Coif2,
+    Coif3,
+    Bior22,
+    Bior44,
+    Bior68,
 }
 
 #[wasm_bindgen]
@@ -51,6 +63,7 @@ pub enum BiolpCutoffLevel {
     Low,
     Medium,
     High,
+    None,
 }
 
 #[wasm_bindgen]
@@ -92,11 +105,23 @@ impl BiolpCompressionOptions {
             BiolpCompressionMethod::Cdf53 => CompressionMethod::Cdf53,
             BiolpCompressionMethod::Sym4 => CompressionMethod::Sym4,
             BiolpCompressionMethod::Db4 => CompressionMethod::Db4,
+            BiolpCompressionMethod::Db2 => CompressionMethod::Db2,
+            BiolpCompressionMethod::Db6 => CompressionMethod::Db6,
+            BiolpCompressionMethod::Db8 => CompressionMethod::Db8,
+            BiolpCompressionMethod::Sym6 => CompressionMethod::Sym6,
+            BiolpCompressionMethod::Sym8 => CompressionMethod::Sym8,
+            BiolpCompressionMethod::Coif1 => CompressionMethod::Coif1,
+            BiolpCompressionMethod::Coif2 => CompressionMethod::Coif2,
+            BiolpCompressionMethod::Coif3 => CompressionMethod::Coif3,
+            BiolpCompressionMethod::Bior22 => CompressionMethod::Bior22,
+            BiolpCompressionMethod::Bior44 => CompressionMethod::Bior44,
+            BiolpCompressionMethod::Bior68 => CompressionMethod::Bior68,
         };
         let cutoff = match cutoff {
             BiolpCutoffLevel::Low => CutoffLevel::Low,
             BiolpCutoffLevel::Medium => CutoffLevel::Medium,
             BiolpCutoffLevel::High => CutoffLevel::High,
+            BiolpCutoffLevel::None => CutoffLevel::None,
         };
         let scale = QuantizationScale::from(scale);
         Ok(Self {
@@ -104,6 +129,7 @@ impl BiolpCompressionOptions {
                 method,
                 scale,
                 cutoff_level: cutoff,
+                ..Default::default()
             },
         })
     }
@@ -124,3 +150,99 @@ pub fn compress_signal(
 pub fn decompress_signal(data: &[u8]) -> Result<Vec<f32>, JsError> {
     decompress(data).map_err(|e| JsError::new(&e.to_string()))
 }
+
+fn check_lengths(original: &[f32], reconstructed: &[f32]) -> Result<(), JsError> {
+    if original.len() != reconstructed.len() {
+        return Err(JsError::new(&format!(
+            "Signals must have equal length, but they had {} and {}",
+            original.len(),
+            reconstructed.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Percentage root-mean-square difference between two Float32Arrays.
+#[wasm_bindgen]
+pub fn compute_prd(original: &[f32], reconstructed: &[f32]) -> Result<f64, JsError> {
+    check_lengths(original, reconstructed)?;
+    Ok(prd(original, reconstructed))
+}
+
+/// Root-mean-square error between two Float32Arrays, in signal units.
+#[wasm_bindgen]
+pub fn compute_rmse(original: &[f32], reconstructed: &[f32]) -> Result<f64, JsError> {
+    check_lengths(original, reconstructed)?;
+    Ok(rmse(original, reconstructed))
+}
+
+/// Compresses a signal arriving in chunks, e.g. WebSocket sample frames, into a stream of
+/// independent records of `frame_len` samples each.
+///
+/// Call `free()` to release the encoder state early, `finish()` releases it as well.
+#[wasm_bindgen]
+pub struct BiolpStreamingCompressor {
+    inner: StreamingCompressor,
+}
+
+#[wasm_bindgen]
+impl BiolpStreamingCompressor {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        frame_len: usize,
+        options: Option<BiolpCompressionOptions>,
+    ) -> Result<BiolpStreamingCompressor, JsError> {
+        let opts = options.map(|o| o.inner).unwrap_or_default();
+        let inner =
+            StreamingCompressor::new(opts, frame_len).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    /// Buffers a Float32Array, returning the Uint8Array records of every frame it completes
+    /// or `undefined` while the current frame is still filling up.
+    pub fn push(&mut self, data: &[f32]) -> Result<Option<Vec<u8>>, JsError> {
+        self.inner
+            .push(data)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Compresses the last, partial frame into a Uint8Array, empty when the signal ended
+    /// on a frame boundary.
+    pub fn finish(self) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .finish()
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// Decodes the records of a [`BiolpStreamingCompressor`] from arbitrarily split chunks.
+///
+/// Call `free()` to release the decoder state early, `finish()` releases it as well.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct BiolpStreamingDecompressor {
+    inner: StreamingDecompressor,
+}
+
+#[wasm_bindgen]
+impl BiolpStreamingDecompressor {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> BiolpStreamingDecompressor {
+        Self::default()
+    }
+
+    /// Appends a Uint8Array chunk, returning the Float32Array samples of every record it
+    /// completes.
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<f32>, JsError> {
+        self.inner
+            .push(data)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Ends the stream, throwing if it stopped in the middle of a record.
+    pub fn finish(self) -> Result<(), JsError> {
+        self.inner
+            .finish()
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+}
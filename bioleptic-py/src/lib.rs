@@ -30,6 +30,7 @@
 use ::bioleptic::{
     CompressionMethod, CompressionOptions, CutoffLevel, QuantizationScale, compress, decompress,
 };
+use ::bioleptic::{prd as compute_prd, rmse as compute_rmse};
 use numpy::{IntoPyArray, Ix1, PyArray, PyReadonlyArray1};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
@@ -44,36 +45,57 @@ pub struct BiolpCompressionOptions {
 #[pymethods]
 impl BiolpCompressionOptions {
     #[new]
-    #[pyo3(signature = (method = "cdf97", scale = 11, cutoff = "low"))]
-    fn new(method: &str, scale: u8, cutoff: &str) -> PyResult<Self> {
+    #[pyo3(signature = (method = "cdf97", scale = 11, cutoff = "low", deflate_level = 6))]
+    fn new(method: &str, scale: u8, cutoff: &str, deflate_level: u32) -> PyResult<Self> {
         let method = match method {
             "cdf97" => CompressionMethod::Cdf97,
             "cdf53" => CompressionMethod::Cdf53,
             "sym4" => CompressionMethod::Sym4,
             "db4" => CompressionMethod::Db4,
+            "db2" => CompressionMethod::Db2,
+            "db6" => CompressionMethod::Db6,
+            "db8" => CompressionMethod::Db8,
+            "sym6" => CompressionMethod::Sym6,
+            "sym8" => CompressionMethod::Sym8,
+            "coif1" => CompressionMethod::Coif1,
+            "coif2" => CompressionMethod::Coif2,
+            "coif3" => CompressionMethod::Coif3,
+            "bior22" => CompressionMethod::Bior22,
+            "bior44" => CompressionMethod::Bior44,
+            "bior68" => CompressionMethod::Bior68,
             other => {
                 return Err(PyValueError::new_err(format!(
-                    "Unknown method {other:?}, expected 'cdf97' or 'cdf53' or 'db4' or 'sym4'"
+                    "Unknown method {other:?}, expected 'cdf97', 'cdf53', 'db2', 'db4', 'db6', \
+                     'db8', 'sym4', 'sym6', 'sym8', 'coif1', 'coif2', 'coif3', 'bior22', \
+                     'bior44' or 'bior68'"
                 )));
             }
         };
         let cutoff: CutoffLevel = match cutoff {
+            "none" => CutoffLevel::None,
             "low" => CutoffLevel::Low,
             "medium" => CutoffLevel::Medium,
             "high" => CutoffLevel::High,
             other => {
                 return Err(PyValueError::new_err(format!(
-                    "Unknown cutoff level {other:?}, expected 'low' or 'medium' or 'high'"
+                    "Unknown cutoff level {other:?}, expected 'none', 'low', 'medium' or 'high'"
                 )));
             }
         };
         let scale =
             QuantizationScale::try_from(scale).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        if deflate_level > 9 {
+            return Err(PyValueError::new_err(format!(
+                "Deflate level must be in 0..=9, but it was {deflate_level}"
+            )));
+        }
         Ok(Self {
             inner: CompressionOptions {
                 method,
                 scale,
                 cutoff_level: cutoff,
+                deflate_level,
+                ..Default::default()
             },
         })
     }
@@ -101,10 +123,48 @@ fn decompress_signal<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, P
     Ok(pyarray)
 }
 
+fn metric_inputs<'a>(
+    original: &'a PyReadonlyArray1<'_, f32>,
+    reconstructed: &'a PyReadonlyArray1<'_, f32>,
+) -> PyResult<(&'a [f32], &'a [f32])> {
+    let original = original.as_slice()?;
+    let reconstructed = reconstructed.as_slice()?;
+    if original.len() != reconstructed.len() {
+        return Err(PyValueError::new_err(format!(
+            "Signals must have equal length, but they had {} and {}",
+            original.len(),
+            reconstructed.len()
+        )));
+    }
+    Ok((original, reconstructed))
+}
+
+/// Percentage root-mean-square difference between two 1-D float32 NumPy arrays.
+#[pyfunction]
+fn prd(
+    original: PyReadonlyArray1<'_, f32>,
+    reconstructed: PyReadonlyArray1<'_, f32>,
+) -> PyResult<f64> {
+    let (original, reconstructed) = metric_inputs(&original, &reconstructed)?;
+    Ok(compute_prd(original, reconstructed))
+}
+
+/// Root-mean-square error between two 1-D float32 NumPy arrays, in signal units.
+#[pyfunction]
+fn rmse(
+    original: PyReadonlyArray1<'_, f32>,
+    reconstructed: PyReadonlyArray1<'_, f32>,
+) -> PyResult<f64> {
+    let (original, reconstructed) = metric_inputs(&original, &reconstructed)?;
+    Ok(compute_rmse(original, reconstructed))
+}
+
 #[pymodule]
 fn bioleptic(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<BiolpCompressionOptions>()?;
     m.add_function(wrap_pyfunction!(compress_signal, m)?)?;
     m.add_function(wrap_pyfunction!(decompress_signal, m)?)?;
+    m.add_function(wrap_pyfunction!(prd, m)?)?;
+    m.add_function(wrap_pyfunction!(rmse, m)?)?;
     Ok(())
 }
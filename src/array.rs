@@ -0,0 +1,80 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::{BiolepticError, CompressionOptions, compress, decompress};
+use ndarray::{Array1, ArrayView1};
+
+/// Compresses a 1-D `ndarray` view, see [`compress`].
+///
+/// Contiguous views are compressed in place, non-contiguous views (e.g. strided slices
+/// or reversed axes) are copied into a temporary buffer in logical order first.
+pub fn compress_array(
+    data: ArrayView1<f32>,
+    options: CompressionOptions,
+) -> Result<Vec<u8>, BiolepticError> {
+    match data.as_slice() {
+        Some(slice) => compress(slice, options),
+        None => compress(&data.to_vec(), options),
+    }
+}
+
+/// Decompresses a stream produced by [`compress`] or [`compress_array`] into an `Array1`.
+pub fn decompress_array(bytes: &[u8]) -> Result<Array1<f32>, BiolepticError> {
+    decompress(bytes).map(Array1::from_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use ndarray::s;
+
+    #[test]
+    fn test_array_round_trip() {
+        let signal = Array1::from_vec(generate_ppg(5000, 120., 75.));
+        let options = CompressionOptions::default();
+        let compressed = compress_array(signal.view(), options).unwrap();
+        assert_eq!(
+            compressed,
+            compress(signal.as_slice().unwrap(), options).unwrap()
+        );
+        let decoded = decompress_array(&compressed).unwrap();
+        assert_eq!(decoded.len(), signal.len());
+        assert_eq!(decoded.to_vec(), decompress(&compressed).unwrap());
+    }
+
+    #[test]
+    fn test_array_non_contiguous_view_is_copied() {
+        let signal = Array1::from_vec(generate_ppg(5000, 120., 75.));
+        let strided = signal.slice(s![..;2]);
+        assert!(strided.as_slice().is_none());
+        let options = CompressionOptions::default();
+        let compressed = compress_array(strided, options).unwrap();
+        assert_eq!(compressed, compress(&strided.to_vec(), options).unwrap());
+    }
+}
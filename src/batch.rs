@@ -0,0 +1,214 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::decompressor::decompress;
+use crate::extension::record_length;
+use crate::{BiolepticError, BiolepticHeader};
+
+/// Magic closing a batch, directly after the record count.
+const BATCH_INDEX_MAGIC: [u8; 4] = *b"BIDX";
+
+/// Offset table of a batch: consecutive records followed by an index trailer.
+///
+/// The trailer is each record's `u64` LE start offset, then the `u32` LE record count and
+/// `b"BIDX"`, so it is found from the end of the buffer and any record is reached without
+/// walking the ones before it. The trailer is not a record, so plain record walkers such as
+/// [`crate::decode_concatenated`] must be given only the records part.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchIndex {
+    offsets: Vec<u64>,
+}
+
+impl BatchIndex {
+    /// Indexes a buffer of consecutive records by walking their headers once.
+    pub fn build(records: &[u8]) -> Result<Self, BiolepticError> {
+        let mut offsets = Vec::new();
+        let mut offset = 0usize;
+        while offset < records.len() {
+            let record = &records[offset..];
+            let header = BiolepticHeader::from_bytes(record)?;
+            offsets.push(offset as u64);
+            offset += record_length(record, &header)?;
+        }
+        Ok(Self { offsets })
+    }
+
+    /// Appends the index trailer to `dst`, which must hold exactly the indexed records.
+    pub fn write(&self, dst: &mut Vec<u8>) {
+        dst.reserve(self.offsets.len() * 8 + 8);
+        for &offset in self.offsets.iter() {
+            dst.extend_from_slice(&offset.to_le_bytes());
+        }
+        dst.extend_from_slice(&(self.offsets.len() as u32).to_le_bytes());
+        dst.extend_from_slice(&BATCH_INDEX_MAGIC);
+    }
+
+    /// Reads the index trailer from the end of a batch.
+    pub fn read(bytes: &[u8]) -> Result<Self, BiolepticError> {
+        let (count, table_start) = Self::trailer(bytes)?;
+        let offsets = bytes[table_start..table_start + count * 8]
+            .chunks_exact(8)
+            .map(|x| u64::from_le_bytes(x.try_into().unwrap()))
+            .collect::<Vec<u64>>();
+        Ok(Self { offsets })
+    }
+
+    /// Returns the record count and where the offset table starts.
+    fn trailer(bytes: &[u8]) -> Result<(usize, usize), BiolepticError> {
+        if bytes.len() < 8 || bytes[bytes.len() - 4..] != BATCH_INDEX_MAGIC {
+            return Err(BiolepticError::InvalidBatchIndex(
+                "batch index trailer is missing".to_string(),
+            ));
+        }
+        let count_start = bytes.len() - 8;
+        let count =
+            u32::from_le_bytes(bytes[count_start..count_start + 4].try_into().unwrap()) as usize;
+        let table_start = count
+            .checked_mul(8)
+            .and_then(|table| count_start.checked_sub(table))
+            .ok_or_else(|| {
+                BiolepticError::InvalidBatchIndex(format!(
+                    "batch index of {count} records doesn't fit in {} bytes",
+                    bytes.len()
+                ))
+            })?;
+        Ok((count, table_start))
+    }
+
+    /// Number of indexed records.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns `true` if the batch holds no records.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Start offset of every record.
+    pub fn offsets(&self) -> &[u64] {
+        &self.offsets
+    }
+}
+
+/// Builds a batch from individually compressed records, appending the index trailer.
+pub fn write_batch<R: AsRef<[u8]>>(records: &[R]) -> Result<Vec<u8>, BiolepticError> {
+    let mut batch = Vec::with_capacity(records.iter().map(|x| x.as_ref().len()).sum());
+    for record in records {
+        batch.extend_from_slice(record.as_ref());
+    }
+    let index = BatchIndex::build(&batch)?;
+    if index.len() != records.len() {
+        return Err(BiolepticError::InvalidBatchIndex(format!(
+            "expected {} records but found {}",
+            records.len(),
+            index.len()
+        )));
+    }
+    index.write(&mut batch);
+    Ok(batch)
+}
+
+/// Decompresses the `index`-th record of a batch using its offset table.
+///
+/// Only the trailer and the requested record are read. Offsets that don't land on a
+/// complete record before the trailer are reported as [`BiolepticError::InvalidBatchIndex`].
+/// The record is decoded with [`decompress`], so `Int16` records are rejected.
+pub fn decompress_nth(bytes: &[u8], index: usize) -> Result<Vec<f32>, BiolepticError> {
+    let (count, table_start) = BatchIndex::trailer(bytes)?;
+    if index >= count {
+        return Err(BiolepticError::OutOfBounds(index, 1, count));
+    }
+    let entry = table_start + index * 8;
+    let offset = u64::from_le_bytes(bytes[entry..entry + 8].try_into().unwrap());
+    let records = &bytes[..table_start];
+    let record = usize::try_from(offset)
+        .ok()
+        .and_then(|offset| records.get(offset..))
+        .filter(|x| !x.is_empty())
+        .ok_or_else(|| {
+            BiolepticError::InvalidBatchIndex(format!(
+                "record {index} offset {offset} is outside of the batch"
+            ))
+        })?;
+    let header = BiolepticHeader::from_bytes(record).map_err(|_| {
+        BiolepticError::InvalidBatchIndex(format!(
+            "record {index} offset {offset} doesn't point at a record"
+        ))
+    })?;
+    let length = record_length(record, &header)?;
+    decompress(&record[..length])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::{CompressionOptions, compress};
+
+    #[test]
+    fn test_random_access() {
+        let records = (0..1000)
+            .map(|i| {
+                let signal = generate_ppg(64 + i % 7, 120., 60. + (i % 40) as f32);
+                compress(&signal, CompressionOptions::default()).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let batch = write_batch(&records).unwrap();
+        let index = BatchIndex::read(&batch).unwrap();
+        assert_eq!(index.len(), 1000);
+
+        for step in 0..1000 {
+            let i = (step * 389) % 1000;
+            assert_eq!(
+                decompress_nth(&batch, i).unwrap(),
+                decompress(&records[i]).unwrap()
+            );
+        }
+        assert!(matches!(
+            decompress_nth(&batch, 1000),
+            Err(BiolepticError::OutOfBounds(..))
+        ));
+
+        // Point record 5 into the middle of record 4.
+        let mut corrupted = batch.clone();
+        let table_start = batch.len() - 8 - 1000 * 8;
+        let entry = table_start + 5 * 8;
+        let shifted = index.offsets()[4] + 3;
+        corrupted[entry..entry + 8].copy_from_slice(&shifted.to_le_bytes());
+        assert!(matches!(
+            decompress_nth(&corrupted, 5),
+            Err(BiolepticError::InvalidBatchIndex(_))
+        ));
+
+        assert!(matches!(
+            decompress_nth(&records[0], 0),
+            Err(BiolepticError::InvalidBatchIndex(_))
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::decompressor::decompress;
+use crate::{BiolepticError, BiolepticHeader};
+
+/// Serves windows of a Bioleptic-encoded signal without re-decoding the whole
+/// stream on every request.
+///
+/// The full signal is decoded once on the first [`CachedDecoder::window`] call and
+/// kept in memory, so the memory cost equals the full decoded signal
+/// (`signal_length * size_of::<f32>()` bytes). Call [`CachedDecoder::evict`] to
+/// release it; the next window request decodes again. Windows are `f32`, so `Int16`
+/// streams are rejected like by [`decompress`], decode those with
+/// [`crate::decompress_i16`].
+pub struct CachedDecoder<'a> {
+    bytes: &'a [u8],
+    signal_length: usize,
+    decoded: Option<Vec<f32>>,
+}
+
+impl<'a> CachedDecoder<'a> {
+    /// Creates a decoder over an encoded stream. Only the header is parsed here.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, BiolepticError> {
+        let header = BiolepticHeader::from_bytes(bytes)?;
+        Ok(Self {
+            bytes,
+            signal_length: header.signal_length as usize,
+            decoded: None,
+        })
+    }
+
+    /// Number of samples in the encoded signal.
+    pub fn signal_length(&self) -> usize {
+        self.signal_length
+    }
+
+    /// Returns `true` if the decoded signal is currently held in memory.
+    pub fn is_cached(&self) -> bool {
+        self.decoded.is_some()
+    }
+
+    /// Returns `len` samples starting at `start`, decoding the full signal on first access.
+    pub fn window(&mut self, start: usize, len: usize) -> Result<&[f32], BiolepticError> {
+        let end = start
+            .checked_add(len)
+            .filter(|&end| end <= self.signal_length)
+            .ok_or(BiolepticError::OutOfBounds(start, len, self.signal_length))?;
+        let decoded = match self.decoded {
+            Some(ref decoded) => decoded,
+            None => self.decoded.insert(decompress(self.bytes)?),
+        };
+        Ok(&decoded[start..end])
+    }
+
+    /// Drops the cached signal, freeing its memory.
+    pub fn evict(&mut self) {
+        self.decoded = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::{CompressionOptions, compress};
+
+    #[test]
+    fn test_windows_match_decompress() {
+        let signal = generate_ppg(10000, 120., 75.);
+        let encoded = compress(&signal, CompressionOptions::default()).unwrap();
+        let reference = decompress(&encoded).unwrap();
+        let mut decoder = CachedDecoder::new(&encoded).unwrap();
+        assert!(!decoder.is_cached());
+        for &(start, len) in &[(0usize, 512usize), (256, 512), (9000, 1000), (256, 512)] {
+            let window = decoder.window(start, len).unwrap();
+            assert_eq!(window, &reference[start..start + len]);
+        }
+        assert!(decoder.is_cached());
+        decoder.evict();
+        assert!(!decoder.is_cached());
+        assert_eq!(decoder.window(100, 10).unwrap(), &reference[100..110]);
+        assert!(decoder.window(9999, 2).is_err());
+    }
+}
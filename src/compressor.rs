@@ -26,23 +26,54 @@
  * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
  * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
-use crate::{BiolepticError, BiolepticHeader, CompressionMethod, DataType};
-use flate2::Compression;
-use flate2::write::DeflateEncoder;
-use osclet::{BorderMode, DaubechiesFamily, Osclet, SymletFamily};
-use std::io::Write;
+use crate::entropy::{EntropyCoder, entropy_encode, max_encoded_size};
+use crate::extension::{
+    ENVELOPE_TAG, ExtensionWriter, FLOAT64_OFFSET_TAG, LEVEL_SCALES_TAG, METADATA_TAG,
+    NON_FINITE_MAP_TAG, NON_FINITE_TAG, RESERVED_TAG, encode_envelope, encode_non_finite_counts,
+    encode_non_finite_map,
+};
+use crate::header::{
+    FLAG_DIFFERENCE, FLAG_EXTENDED, FLAG_LOSSLESS, FLAG_MIDPOINT_DEQUANT, FLAG_SOFT_THRESHOLD,
+    FLAG_WIDE_COEFFICIENTS, FLAG_ZERO_SIGNAL, payload_crc,
+};
+use crate::lossless::{LOSSLESS_MAX_MAGNITUDE, forward_lossless, lossless_layout};
+use crate::payload::{max_payload_size, serialize_coefficients};
+use crate::pretransform::{
+    DIFFERENCE_TAG, LOG_TAG, POLY_TAG, TAPER_TAG, add_polynomial, apply_taper, difference,
+    encode_polynomial, encode_taper, fit_polynomial, log_compand,
+};
+use crate::simd::{normalize, quantize_slice};
+use crate::worker::make_dwt_worker;
+use crate::{BIOLEPTIC_HEADER_SIZE, BiolepticError, BiolepticHeader, CompressionMethod, DataType};
+use osclet::DwtExecutor;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::hash::{Hash, Hasher};
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum CutoffLevel {
     #[default]
     Low,
     Medium,
     High,
+    /// No thresholding, only quantization discards detail, e.g. to tell its loss apart
+    /// from denoising. Applies to [`ThresholdStrategy::Fixed`].
+    None,
 }
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
 #[repr(u8)]
 #[derive(Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum QuantizationScale {
     S6 = 6,
     S7 = 7,
@@ -52,6 +83,14 @@ pub enum QuantizationScale {
     #[default]
     S11 = 11,
     S12 = 12,
+    S13 = 13,
+    S14 = 14,
+    S15 = 15,
+    S16 = 16,
+    S17 = 17,
+    S18 = 18,
+    S19 = 19,
+    S20 = 20,
 }
 
 impl QuantizationScale {
@@ -64,6 +103,44 @@ impl QuantizationScale {
     pub fn multiplier(self) -> f32 {
         (1u32 << self.as_u8()) as f32
     }
+
+    /// Returns the multiplier `1 << scale` as `f64`, exact for any shift below 53.
+    ///
+    /// The codec quantizes and dequantizes in `f64` with this pair, since `f32` stops
+    /// representing every product exactly once the scale passes 23.
+    pub fn multiplier_f64(self) -> f64 {
+        (1u64 << self.as_u8()) as f64
+    }
+
+    /// Returns `1 / (1 << scale)` as `f64`, the exact inverse of [`Self::multiplier_f64`].
+    pub fn reciprocal_f64(self) -> f64 {
+        1. / self.multiplier_f64()
+    }
+
+    /// Returns the nominal PRD in percent this scale yields on PPG-like signals.
+    ///
+    /// Measured on a synthetic 120 Hz PPG with default CDF 9/7 options. This is rough
+    /// guidance for labeling quality presets, real recordings typically land within a
+    /// factor of 3 of the tabulated value depending on noise and sample rate.
+    pub fn typical_prd_ppg(self) -> f64 {
+        match self {
+            QuantizationScale::S6 => 2.9,
+            QuantizationScale::S7 => 1.5,
+            QuantizationScale::S8 => 0.76,
+            QuantizationScale::S9 => 0.38,
+            QuantizationScale::S10 => 0.23,
+            QuantizationScale::S11 => 0.13,
+            QuantizationScale::S12 => 0.079,
+            QuantizationScale::S13 => 0.044,
+            QuantizationScale::S14 => 0.019,
+            QuantizationScale::S15 => 0.0081,
+            QuantizationScale::S16 => 0.0037,
+            QuantizationScale::S17 => 0.0018,
+            QuantizationScale::S18 => 0.00089,
+            QuantizationScale::S19 => 0.00045,
+            QuantizationScale::S20 => 0.00022,
+        }
+    }
 }
 
 impl TryFrom<u8> for QuantizationScale {
@@ -78,16 +155,383 @@ impl TryFrom<u8> for QuantizationScale {
             10 => Ok(Self::S10),
             11 => Ok(Self::S11),
             12 => Ok(Self::S12),
+            13 => Ok(Self::S13),
+            14 => Ok(Self::S14),
+            15 => Ok(Self::S15),
+            16 => Ok(Self::S16),
+            17 => Ok(Self::S17),
+            18 => Ok(Self::S18),
+            19 => Ok(Self::S19),
+            20 => Ok(Self::S20),
             _ => Err(BiolepticError::InvalidQuantizationScale(value)),
         }
     }
 }
 
-#[derive(Copy, Clone, Hash, Debug)]
+/// Integer width of the stored DWT coefficients.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum CoefficientWidth {
+    /// Coefficients saturate at `i16`, supports scales up to `S12`.
+    #[default]
+    I16,
+    /// Coefficients are stored as `i32`, roughly doubling the payload but allowing
+    /// scales up to `S20` without saturation for near-lossless archival.
+    I32,
+}
+
+/// Layout of the coefficient stream before entropy coding.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum PayloadEncoding {
+    /// Every coefficient is stored at full width.
+    #[default]
+    Dense,
+    /// Detail levels that are mostly zero after thresholding are stored as
+    /// `(index gap, value)` lists, the rest stay dense. Chosen per level.
+    SparseDetails,
+    /// Experimental embedded stream: magnitudes are sent bitplane by bitplane from the
+    /// most significant one, coarse levels first. A stream cut at any byte still decodes
+    /// to a coarser approximation, at the cost of a larger payload than `Dense`.
+    Bitplane,
+    /// Every coefficient is zigzag mapped and written as an LEB128 varint, so the many
+    /// near-zero values left after thresholding take a single byte instead of the full
+    /// width. Usually smaller than `Dense` for sparse, low-scale payloads.
+    Varint,
+    /// Coefficients are stored at full width, except that every run of zeros collapses
+    /// into a single zero followed by the LEB128 run length. Suits the long zero runs a
+    /// `High` cutoff leaves in the detail levels.
+    ZeroRuns,
+}
+
+/// How quantized coefficients are mapped back to real values on decode.
+///
+/// Quantization truncates toward zero, so every non-zero level covers the bin
+/// `[|q|, |q| + 1)` scaled by `1 / (1 << scale)`. `Midpoint` reconstructs at the
+/// center of that bin instead of its lower edge. Zero stays zero since its bin is
+/// symmetric. Midpoint is only meaningful for truncating quantization, a rounding
+/// quantizer already centers its bins and should be decoded with `Truncate`.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DequantMode {
+    /// Reconstruct at the lower edge of the bin, `q / (1 << scale)`.
+    #[default]
+    Truncate,
+    /// Reconstruct at the bin center, `(q + 0.5 * sign(q)) / (1 << scale)`.
+    Midpoint,
+}
+
+impl DequantMode {
+    /// Returns the mapping from a quantized level and `1 / (1 << scale)` to a coefficient.
+    pub(crate) fn dequantizer(self) -> fn(i32, f64) -> f32 {
+        match self {
+            DequantMode::Truncate => |x, rcp_scale| (x as f64 * rcp_scale) as f32,
+            DequantMode::Midpoint => |x, rcp_scale| {
+                if x == 0 {
+                    0.
+                } else {
+                    ((x as f64 + 0.5f64.copysign(x as f64)) * rcp_scale) as f32
+                }
+            },
+        }
+    }
+}
+
+/// How the orthogonal wavelets extend the signal past its edges.
+///
+/// Only Daubechies, Symlet and Coiflet methods take a border mode, the CDF lifting
+/// schemes have a fixed one.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum BorderMode {
+    /// Treats the signal as periodic. Leaves artifacts at both edges when the first and
+    /// last samples differ a lot.
+    #[default]
+    Wrap,
+    /// Mirrors the signal at its edges, suits non-periodic signals such as ramps.
+    Reflect,
+    /// Repeats the edge samples.
+    Clamp,
+    /// Pads with zeros.
+    Zeros,
+}
+
+/// How detail coefficients below the cutoff threshold are treated.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ThresholdMode {
+    /// Coefficients below the threshold are zeroed, larger ones are kept as is.
+    #[default]
+    Hard,
+    /// Every coefficient is shrunk toward zero by the threshold, so small ones still
+    /// vanish but the kept ones don't jump. Smoother for denoising, at the cost of a
+    /// slight amplitude bias. Needs no reversal on decode.
+    Soft,
+}
+
+/// How the magnitude below which detail coefficients count as noise is chosen.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ThresholdStrategy {
+    /// A fixed number of quantization steps per scale, raised by the [`CutoffLevel`].
+    /// Every level gets the same threshold, though details shrink at coarser levels.
+    #[default]
+    Fixed,
+    /// The universal (VisuShrink) threshold `sigma * sqrt(2 ln N)` of every level, with
+    /// the noise level `sigma` estimated from the median absolute deviation of that level's
+    /// `N` details, capped at the estimate of the finest level. The cutoff level is ignored.
+    ///
+    /// Tracks the actual noise floor per level: on noisy recordings most of the fine
+    /// details vanish, giving a much better ratio and a smoother signal, but a larger
+    /// distance to the noisy input. On clean signals the estimated noise is small and the
+    /// result stays close to [`ThresholdStrategy::Fixed`].
+    Adaptive,
+}
+
+/// How `NaN` and infinite samples are replaced before the transform.
+///
+/// Whatever the policy, their counts are recorded in the header extension, see
+/// [`BiolepticHeader::non_finite_counts`], and
+/// [`CompressionOptions::preserve_nonfinite`] can restore them on decode.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum NonFinitePolicy {
+    /// `NaN` and `-inf` become `0.0`, `+inf` becomes `1.0`.
+    #[default]
+    Substitute,
+    /// Every non-finite sample becomes `0.0`.
+    Zero,
+    /// `+inf` and `-inf` become the largest and smallest finite sample, `NaN` the middle
+    /// of that range, so the substitutes never widen the normalization range.
+    ClampToRange,
+    /// Runs of non-finite samples are linearly interpolated between the finite samples
+    /// around them, leading and trailing runs repeat the nearest finite sample. Suits
+    /// sensor dropouts, where a jump to a constant adds spurious spectral content.
+    Interpolate,
+    /// Signals with any non-finite sample are rejected.
+    Error,
+}
+
+/// Transform applied to the raw signal before the DWT.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum PreTransform {
+    /// The signal is transformed as is.
+    #[default]
+    None,
+    /// The first difference is compressed and integrated back on decode, starting from
+    /// the stored initial sample. Pays off for clean ramps and drifts, whose difference is
+    /// nearly constant. Quantization error accumulates along the signal, so once noise
+    /// dominates the difference the direct path is smaller at the same PRD.
+    Difference,
+    /// A least-squares polynomial of the given degree (at most 3) is subtracted and added
+    /// back on decode, so the DWT spends its bits on content instead of a slow trend such
+    /// as ECG baseline wander. The fit costs 8 bytes per coefficient.
+    PolyDetrend(u8),
+    /// Companding with `sign(x) * ln(1 + |x| * k)`, `k = 2^exponent`, expanded back on
+    /// decode. Spends resolution evenly across orders of magnitude for wide-dynamic-range
+    /// signals such as impedance pneumography, at the cost of coarser steps on large
+    /// amplitudes. Pick `k` near the reciprocal of the smallest amplitude that matters,
+    /// the exponent must be in `-32..=32`.
+    Log(i8),
+}
+
+/// How many DWT levels the signal is decomposed into, see [`CompressionOptions::levels`].
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DecompositionLevels {
+    /// Decomposes one level at a time and stops at the first level whose details hold more
+    /// than a quarter of the energy left in its approximation, as further levels would
+    /// split content that is no longer smooth. Oscillatory signals stop early, slow trends
+    /// go as deep as the length-based choice. Lossless streams use the length-based choice.
+    Auto,
+    /// Exactly this many levels, in `1..=10`.
+    Fixed(u8),
+}
+
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CompressionOptions {
     pub method: CompressionMethod,
     pub scale: QuantizationScale,
+    /// Overrides `scale` per coefficient band: entry 0 quantizes the approximation and
+    /// entry `i` the `i`-th detail level counted from the finest. Entries past the level
+    /// count are ignored.
+    ///
+    /// Coarse bands carry most of the energy and tolerate less error than the finest ones,
+    /// so they usually want a larger scale. Costs one byte per band in the extension section.
+    pub level_scales: Option<[QuantizationScale; 11]>,
     pub cutoff_level: CutoffLevel,
+    /// Number of DWT levels, chosen from the signal length when `None`. The chosen count
+    /// is stored in the header.
+    ///
+    /// A fixed count must be in `1..=10`, and the signal must still span the wavelet filter
+    /// at the deepest level, i.e. `len >> (levels - 1)` samples at least the filter length.
+    pub levels: Option<DecompositionLevels>,
+    /// Samples per bucket of the optional min/max envelope, see
+    /// [`CompressionOptions::with_envelope`].
+    pub envelope: Option<u32>,
+    pub coefficient_width: CoefficientWidth,
+    pub payload_encoding: PayloadEncoding,
+    pub dequant_mode: DequantMode,
+    pub threshold_mode: ThresholdMode,
+    pub threshold_strategy: ThresholdStrategy,
+    pub border_mode: BorderMode,
+    pub pre_transform: PreTransform,
+    /// Percent of the signal covered by the optional Tukey taper, see
+    /// [`CompressionOptions::with_taper`].
+    pub taper: Option<u8>,
+    /// Opaque quality score stored in the header, see [`CompressionOptions::with_quality_tag`].
+    pub quality_tag: u8,
+    /// Bytes stored verbatim in the extension section, e.g. to prototype format extensions,
+    /// read them back with [`crate::read_reserved`]. The codec never interprets them.
+    ///
+    /// The fixed header only has the 4 bytes of [`BiolepticHeader::reserved1`] left after
+    /// the channel count, quality tag, sample rate and checksums, so these 16 bytes live
+    /// in an extension entry costing 24 bytes instead.
+    pub reserved: Option<[u8; 16]>,
+    /// Sample rate in Hz, see [`CompressionOptions::with_sample_rate`]. Must be a normal,
+    /// positive `f32`.
+    pub sample_rate: Option<f32>,
+    /// Deflate level of the payload, `0..=9`, defaults to `6`.
+    ///
+    /// Only trades encode time for ratio, decoding is the same at every level. Use `1`
+    /// for realtime capture and `9` for archival.
+    pub deflate_level: u32,
+    /// Backend coding the payload, deflate by default.
+    pub entropy: EntropyCoder,
+    /// Stores integer lifting coefficients instead of quantized ones, so the samples are
+    /// reconstructed bit-exactly.
+    ///
+    /// Requires [`CompressionMethod::Cdf53`], integer-valued samples with a magnitude up to
+    /// `2^24`, no pre-transform and no taper. Scale, cutoff and coefficient width are ignored.
+    pub lossless: bool,
+    /// Records where `NaN`, `+inf` and `-inf` samples were, so [`crate::decompress`] puts
+    /// them back instead of the `0.0`/`1.0` substitutes.
+    ///
+    /// Costs 2 bits per sample before deflate, only written when the signal has any
+    /// non-finite samples.
+    pub preserve_nonfinite: bool,
+    /// Replacement of non-finite samples before the transform.
+    pub non_finite: NonFinitePolicy,
+    /// Takes the samples as already normalized and skips the min/max/mean passes, storing
+    /// `min = 0`, `max = 1` and `mean = 0` so the decoder maps them back unchanged.
+    ///
+    /// Meant for pipelines that already center and scale to roughly `[0, 1]`: the scale
+    /// assumes that range, so samples far outside it saturate. Can't be combined with
+    /// `lossless`.
+    pub skip_normalization: bool,
+}
+
+impl CompressionOptions {
+    /// Every field by value, the sample rate as its bits so the options stay `Eq` and
+    /// hashable.
+    fn key(&self) -> impl Eq + Hash {
+        let Self {
+            method,
+            scale,
+            level_scales,
+            cutoff_level,
+            levels,
+            envelope,
+            coefficient_width,
+            payload_encoding,
+            dequant_mode,
+            threshold_mode,
+            threshold_strategy,
+            border_mode,
+            pre_transform,
+            taper,
+            quality_tag,
+            reserved,
+            sample_rate,
+            deflate_level,
+            entropy,
+            lossless,
+            preserve_nonfinite,
+            non_finite,
+            skip_normalization,
+        } = *self;
+        (
+            (
+                method,
+                scale,
+                level_scales,
+                cutoff_level,
+                levels,
+                envelope,
+                coefficient_width,
+                payload_encoding,
+                dequant_mode,
+                threshold_mode,
+                threshold_strategy,
+                border_mode,
+            ),
+            (
+                pre_transform,
+                taper,
+                quality_tag,
+                reserved,
+                sample_rate.map(f32::to_bits),
+                deflate_level,
+                entropy,
+                lossless,
+                preserve_nonfinite,
+                non_finite,
+                skip_normalization,
+            ),
+        )
+    }
+}
+
+impl PartialEq for CompressionOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for CompressionOptions {}
+
+impl Hash for CompressionOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
 }
 
 impl Default for CompressionOptions {
@@ -95,355 +539,2810 @@ impl Default for CompressionOptions {
         CompressionOptions {
             method: CompressionMethod::Cdf97,
             scale: QuantizationScale::S11,
+            level_scales: None,
             cutoff_level: CutoffLevel::default(),
+            levels: None,
+            envelope: None,
+            coefficient_width: CoefficientWidth::I16,
+            payload_encoding: PayloadEncoding::Dense,
+            dequant_mode: DequantMode::Truncate,
+            threshold_mode: ThresholdMode::Hard,
+            threshold_strategy: ThresholdStrategy::Fixed,
+            border_mode: BorderMode::Wrap,
+            pre_transform: PreTransform::None,
+            taper: None,
+            quality_tag: 0,
+            reserved: None,
+            sample_rate: None,
+            deflate_level: 6,
+            entropy: EntropyCoder::Deflate,
+            lossless: false,
+            preserve_nonfinite: false,
+            non_finite: NonFinitePolicy::Substitute,
+            skip_normalization: false,
         }
     }
 }
 
 impl CompressionOptions {
+    /// Starts an [`OptionsBuilder`] from the defaults.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+
     pub fn from_method(method: CompressionMethod) -> Self {
         CompressionOptions {
             method,
             ..Default::default()
         }
     }
-}
 
-fn threshold(details: &mut [i16], scale: QuantizationScale, cutoff_level: CutoffLevel) {
-    let mut threshold = match scale {
-        QuantizationScale::S6 => 0,
-        QuantizationScale::S7 => 0,
-        QuantizationScale::S8 => 1,
-        QuantizationScale::S9 => 1,
-        QuantizationScale::S10 => 2,
-        QuantizationScale::S11 => 2,
-        QuantizationScale::S12 => 3,
-    };
-    match cutoff_level {
-        CutoffLevel::Low => {}
-        CutoffLevel::Medium => {
-            threshold *= 3;
-        }
-        CutoffLevel::High => {
-            threshold *= 7;
-        }
+    /// Stores a decimated min/max envelope, one `(min, max)` pair per `decimation` samples,
+    /// so viewers can draw a zoomed-out outline without decompressing the signal.
+    ///
+    /// Costs 8 bytes per bucket plus 16 bytes of framing, e.g. ~0.4% of the raw
+    /// signal size with a decimation of 256. Read it back with [`BiolepticHeader::envelope`].
+    pub fn with_envelope(mut self, decimation: u32) -> Self {
+        self.envelope = Some(decimation);
+        self
     }
-    for det in details.iter_mut() {
-        if det.unsigned_abs() < threshold {
-            *det = 0;
-        }
-    }
-}
 
-fn compute_max_levels(signal_len: usize, filter_length: usize) -> usize {
-    if signal_len < filter_length {
-        return 1;
+    /// Applies a Tukey (cosine) taper covering `percent` of the signal, half at each edge,
+    /// to suppress boundary ringing on short snippets. The decoder adds the taper back.
+    ///
+    /// Both edges are blended onto the signal mean, so a snippet whose first and last
+    /// samples differ no longer shows a jump to the [`BorderMode::Wrap`] transform. The
+    /// blend is additive and stores only the two edge offsets, so it doesn't amplify the
+    /// reconstruction error. Percent must be in `1..=100`.
+    pub fn with_taper(mut self, percent: u8) -> Self {
+        self.taper = Some(percent);
+        self
     }
-    // floor(log2(signal_len / filter_length))
-    let ratio = signal_len / filter_length;
-    let max = usize::BITS as usize - ratio.leading_zeros() as usize - 1;
-    max.clamp(1, 8)
-}
 
-/// Compresses a slice of `f32` samples into a Bioleptic-encoded byte vector.
-///
-/// Non-finite values (`NaN`, `±inf`) are substituted before processing:
-/// `NaN` and `-inf` become `0.0`, `+inf` becomes `1.0`. The signal is then
-/// mean-centered and range-normalized, transformed with a multi-level DWT,
-/// quantized to `i16`, thresholded, and entropy-coded with deflate.
-pub fn compress(data: &[f32], options: CompressionOptions) -> Result<Vec<u8>, BiolepticError> {
-    if data.is_empty() {
-        return Err(BiolepticError::UnsupportedCompressorConfiguration(
-            "Can't compress empty data".to_string(),
-        ));
+    /// Annotates the file with a producer-defined quality score, e.g. 0–100 from an upstream
+    /// signal-quality index, so downstream systems can triage recordings without decoding.
+    ///
+    /// The codec stores the value as-is and never interprets it. Read it back with
+    /// [`BiolepticHeader::quality_tag`].
+    pub fn with_quality_tag(mut self, tag: u8) -> Self {
+        self.quality_tag = tag;
+        self
     }
-    if data.len() > i32::MAX as usize {
-        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
-            "Can't compress data bigger than {}, but data was {}",
-            i32::MAX,
-            data.len()
-        )));
+
+    /// Records the sample rate of the signal in the header, read it back with
+    /// [`BiolepticHeader::sample_rate`]. Enables [`crate::decompress_to_rate`].
+    ///
+    /// The rate must be a normal, positive `f32`.
+    pub fn with_sample_rate(mut self, hz: f32) -> Self {
+        self.sample_rate = Some(hz);
+        self
     }
-    let original_length = data.len();
-    let mut v_min = f32::INFINITY;
-    let mut v_max = f32::NEG_INFINITY;
-    let mut working_data = vec![0.; data.len()];
-    for (dst, &src) in working_data.iter_mut().zip(data.iter()) {
-        #[allow(clippy::if_same_then_else)]
-        let val = if src.is_finite() {
-            src
-        } else if src.is_nan() {
-            0.
-        } else if src.is_sign_negative() {
-            0.
-        } else {
-            1.
+
+    /// Packs the options into a stable `u32` code, e.g. for sharing settings in a URL.
+    ///
+    /// Bit layout, least significant first:
+    ///
+    /// | bits   | field                                           |
+    /// |--------|-------------------------------------------------|
+    /// | 0..8   | method: 0 Cdf53, 1 Cdf97, 2 Db4, 3 Sym4, 4 Db2, |
+    /// |        | 5 Db6, 6 Db8, 7 Coif1, 8 Coif2, 9 Coif3,        |
+    /// |        | 10 Sym6, 11 Sym8, 12 Bior22, 13 Bior44,         |
+    /// |        | 14 Bior68                                       |
+    /// | 8..13  | quantization scale shift (e.g. 11 for `S11`)    |
+    /// | 13..15 | cutoff level: 0 Low, 1 Medium, 2 High, 3 None   |
+    /// | 15     | `i32` coefficients                              |
+    /// | 16..32 | reserved for future flags, must be zero         |
+    ///
+    /// Only `method`, `scale`, `cutoff_level` and `coefficient_width` are encoded, every
+    /// other field must be at its default, e.g. no pre-transform, taper, level scales,
+    /// payload encoding, sample rate or reserved bytes. Fails with
+    /// [`BiolepticError::UnsupportedCompressorConfiguration`] otherwise, rather than
+    /// dropping a setting the code can't carry.
+    pub fn to_code(&self) -> Result<u32, BiolepticError> {
+        let representable = CompressionOptions {
+            method: self.method,
+            scale: self.scale,
+            cutoff_level: self.cutoff_level,
+            coefficient_width: self.coefficient_width,
+            ..Default::default()
         };
-        v_min = val.min(v_min);
-        v_max = val.max(v_max);
-        *dst = val;
-    }
-    let mut v_sum = 0.;
-    let range = v_max - v_min;
-    let mut v_mean = 0.;
-    if range > 1e-5 {
-        let range_scale = 1. / range;
-        let diff = v_min;
-        for dst in working_data.iter_mut() {
-            let q = (*dst - diff) * range_scale;
-            v_sum += q;
-            *dst = q;
-        }
-        v_mean = v_sum / data.len() as f32;
-        for dst in working_data.iter_mut() {
-            *dst -= v_mean;
+        if representable != *self {
+            return Err(BiolepticError::UnsupportedCompressorConfiguration(
+                "Only method, scale, cutoff level and coefficient width fit in a config code"
+                    .to_string(),
+            ));
         }
-    } else {
-        working_data.fill(0.);
+        let method: u32 = match self.method {
+            CompressionMethod::Cdf53 => 0,
+            CompressionMethod::Cdf97 => 1,
+            CompressionMethod::Db4 => 2,
+            CompressionMethod::Sym4 => 3,
+            CompressionMethod::Db2 => 4,
+            CompressionMethod::Db6 => 5,
+            CompressionMethod::Db8 => 6,
+            CompressionMethod::Coif1 => 7,
+            CompressionMethod::Coif2 => 8,
+            CompressionMethod::Coif3 => 9,
+            CompressionMethod::Sym6 => 10,
+            CompressionMethod::Sym8 => 11,
+            CompressionMethod::Bior22 => 12,
+            CompressionMethod::Bior44 => 13,
+            CompressionMethod::Bior68 => 14,
+        };
+        let cutoff: u32 = match self.cutoff_level {
+            CutoffLevel::Low => 0,
+            CutoffLevel::Medium => 1,
+            CutoffLevel::High => 2,
+            CutoffLevel::None => 3,
+        };
+        let wide: u32 = match self.coefficient_width {
+            CoefficientWidth::I16 => 0,
+            CoefficientWidth::I32 => 1,
+        };
+        Ok(method | ((self.scale.as_u8() as u32) << 8) | (cutoff << 13) | (wide << 15))
     }
 
-    let dwt_worker = match options.method {
-        CompressionMethod::Cdf53 => Osclet::make_cdf53_f32(),
-        CompressionMethod::Cdf97 => Osclet::make_cdf97_f32(),
-        CompressionMethod::Db4 => {
-            Osclet::make_daubechies_f32(DaubechiesFamily::Db4, BorderMode::Wrap)
+    /// Unpacks options produced by [`CompressionOptions::to_code`], rejecting unknown codes.
+    pub fn from_code(code: u32) -> Result<Self, BiolepticError> {
+        if code >> 16 != 0 {
+            return Err(BiolepticError::InvalidConfigCode(code));
         }
-        CompressionMethod::Sym4 => Osclet::make_symlet_f32(SymletFamily::Sym4, BorderMode::Wrap),
-    };
+        let method = match code & 0xff {
+            0 => CompressionMethod::Cdf53,
+            1 => CompressionMethod::Cdf97,
+            2 => CompressionMethod::Db4,
+            3 => CompressionMethod::Sym4,
+            4 => CompressionMethod::Db2,
+            5 => CompressionMethod::Db6,
+            6 => CompressionMethod::Db8,
+            7 => CompressionMethod::Coif1,
+            8 => CompressionMethod::Coif2,
+            9 => CompressionMethod::Coif3,
+            10 => CompressionMethod::Sym6,
+            11 => CompressionMethod::Sym8,
+            12 => CompressionMethod::Bior22,
+            13 => CompressionMethod::Bior44,
+            14 => CompressionMethod::Bior68,
+            _ => return Err(BiolepticError::InvalidConfigCode(code)),
+        };
+        let scale = QuantizationScale::try_from(((code >> 8) & 0x1f) as u8)
+            .map_err(|_| BiolepticError::InvalidConfigCode(code))?;
+        let cutoff_level = match (code >> 13) & 0x3 {
+            0 => CutoffLevel::Low,
+            1 => CutoffLevel::Medium,
+            2 => CutoffLevel::High,
+            _ => CutoffLevel::None,
+        };
+        let coefficient_width = if (code >> 15) & 1 != 0 {
+            CoefficientWidth::I32
+        } else {
+            CoefficientWidth::I16
+        };
+        Ok(CompressionOptions {
+            method,
+            scale,
+            cutoff_level,
+            coefficient_width,
+            ..Default::default()
+        })
+    }
+}
 
-    if working_data.len() < dwt_worker.filter_length() {
-        let target_len = dwt_worker.filter_length();
-        let current_len = working_data.len();
-        let needed = target_len - current_len;
-        // Wrap-extend: mirror the existing samples cyclically to avoid
-        // zero-padding artifacts at the filter boundary.
-        let extension = (0..needed)
-            .map(|i| working_data[i % current_len])
-            .collect::<Vec<f32>>();
-        working_data.extend_from_slice(&extension);
+/// Chainable construction of [`CompressionOptions`], started with
+/// [`CompressionOptions::builder`].
+///
+/// Every setter mirrors the field of the same name, fields left unset keep their defaults.
+/// Nothing is validated here, [`compress`] rejects unsupported combinations as usual.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct OptionsBuilder {
+    options: CompressionOptions,
+}
+
+impl OptionsBuilder {
+    pub fn method(mut self, method: CompressionMethod) -> Self {
+        self.options.method = method;
+        self
     }
 
-    let level = if data.len() < 20 {
-        1
-    } else if data.len() < 40 {
-        2
-    } else if data.len() < 60 {
-        3
-    } else if data.len() < 80 {
-        4
-    } else {
-        compute_max_levels(data.len(), dwt_worker.filter_length())
-    };
+    pub fn scale(mut self, scale: QuantizationScale) -> Self {
+        self.options.scale = scale;
+        self
+    }
 
-    let dwt = dwt_worker
-        .multi_dwt(&working_data, level)
-        .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?;
+    pub fn level_scales(mut self, level_scales: [QuantizationScale; 11]) -> Self {
+        self.options.level_scales = Some(level_scales);
+        self
+    }
 
-    if dwt.levels.is_empty() {
-        return Err(BiolepticError::UnderlyingDwtError(
-            "Internal DWT returned zero levels, what shouldn't happen".to_string(),
-        ));
+    pub fn cutoff_level(mut self, cutoff_level: CutoffLevel) -> Self {
+        self.options.cutoff_level = cutoff_level;
+        self
     }
 
-    let last_dwt_level = match dwt.levels.last() {
-        None => {
-            return Err(BiolepticError::UnderlyingDwtError(
-                "Internal DWT returned zero levels, what shouldn't happen".to_string(),
-            ));
-        }
-        Some(v) => v,
-    };
+    pub fn levels(mut self, levels: DecompositionLevels) -> Self {
+        self.options.levels = Some(levels);
+        self
+    }
 
-    let scale_multiplier = options.scale.multiplier();
+    pub fn envelope(mut self, envelope: u32) -> Self {
+        self.options.envelope = Some(envelope);
+        self
+    }
 
-    let mut approximation = last_dwt_level
-        .approximations
-        .iter()
-        .map(|&x| {
-            (x * scale_multiplier)
-                .min(i16::MAX as f32)
-                .max(i16::MIN as f32) as i16
-        })
-        .collect::<Vec<i16>>();
+    pub fn coefficient_width(mut self, coefficient_width: CoefficientWidth) -> Self {
+        self.options.coefficient_width = coefficient_width;
+        self
+    }
 
-    let mut details = dwt
-        .levels
-        .iter()
-        .map(|x| {
-            x.details
-                .iter()
-                .map(|&x| {
-                    (x * scale_multiplier)
-                        .min(i16::MAX as f32)
-                        .max(i16::MIN as f32) as i16
-                })
-                .collect::<Vec<i16>>()
-        })
-        .collect::<Vec<Vec<i16>>>();
+    pub fn payload_encoding(mut self, payload_encoding: PayloadEncoding) -> Self {
+        self.options.payload_encoding = payload_encoding;
+        self
+    }
 
-    let mut total_details_length = 0usize;
+    pub fn dequant_mode(mut self, dequant_mode: DequantMode) -> Self {
+        self.options.dequant_mode = dequant_mode;
+        self
+    }
 
-    for level_details in details.iter_mut() {
-        threshold(level_details, options.scale, options.cutoff_level);
-        total_details_length += level_details.len();
+    pub fn threshold_mode(mut self, threshold_mode: ThresholdMode) -> Self {
+        self.options.threshold_mode = threshold_mode;
+        self
     }
 
-    approximation
-        .try_reserve_exact(total_details_length)
-        .map_err(|_| BiolepticError::OutOfMemoryError(total_details_length))?;
+    pub fn threshold_strategy(mut self, threshold_strategy: ThresholdStrategy) -> Self {
+        self.options.threshold_strategy = threshold_strategy;
+        self
+    }
 
-    for level_details in details.iter() {
-        approximation.extend_from_slice(level_details);
+    pub fn border_mode(mut self, border_mode: BorderMode) -> Self {
+        self.options.border_mode = border_mode;
+        self
     }
 
-    let approximation_bytes = approximation
-        .into_iter()
-        .flat_map(|x| x.to_le_bytes())
-        .collect::<Vec<_>>();
+    pub fn pre_transform(mut self, pre_transform: PreTransform) -> Self {
+        self.options.pre_transform = pre_transform;
+        self
+    }
 
-    let mut e = DeflateEncoder::new(Vec::new(), Compression::default());
-    e.write_all(&approximation_bytes)
-        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
-    let compressed_data = e
-        .finish()
-        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+    pub fn taper(mut self, taper: u8) -> Self {
+        self.options.taper = Some(taper);
+        self
+    }
 
-    let header = BiolepticHeader::new(
-        DataType::Float32,
-        options.method,
-        level as u8,
-        options.scale,
-        original_length as u32,
-        v_min,
-        v_max,
-        v_mean,
-        compressed_data.len() as u32,
-    );
+    pub fn quality_tag(mut self, quality_tag: u8) -> Self {
+        self.options.quality_tag = quality_tag;
+        self
+    }
 
-    let mut header_bytes = header.to_bytes().to_vec();
-    header_bytes.extend_from_slice(&compressed_data);
+    pub fn reserved(mut self, reserved: [u8; 16]) -> Self {
+        self.options.reserved = Some(reserved);
+        self
+    }
 
-    Ok(header_bytes)
-}
+    /// Sample rate in Hz, see [`CompressionOptions::with_sample_rate`].
+    pub fn sample_rate(mut self, hz: f32) -> Self {
+        self.options = self.options.with_sample_rate(hz);
+        self
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::decompressor::decompress;
+    pub fn deflate_level(mut self, deflate_level: u32) -> Self {
+        self.options.deflate_level = deflate_level;
+        self
+    }
 
-    /// Generates a synthetic PPG-like signal.
-    /// Models the systolic peak, dicrotic notch, and diastolic peak.
-    pub fn generate_ppg(samples: usize, sample_rate: f32, heart_rate_bpm: f32) -> Vec<f32> {
-        let rr_interval = 60.0 / heart_rate_bpm;
-        let mut signal = vec![0.0f32; samples];
+    pub fn entropy(mut self, entropy: EntropyCoder) -> Self {
+        self.options.entropy = entropy;
+        self
+    }
+
+    pub fn lossless(mut self, lossless: bool) -> Self {
+        self.options.lossless = lossless;
+        self
+    }
 
-        for i in 0..samples {
-            let t = i as f32 / sample_rate;
-            let phase = (t / rr_interval).fract();
+    pub fn preserve_nonfinite(mut self, preserve_nonfinite: bool) -> Self {
+        self.options.preserve_nonfinite = preserve_nonfinite;
+        self
+    }
 
-            // systolic rise — fast gaussian peak at ~25% of cycle
-            let systolic = 1.0 * gaussian(phase, 0.25, 0.06);
+    pub fn non_finite(mut self, non_finite: NonFinitePolicy) -> Self {
+        self.options.non_finite = non_finite;
+        self
+    }
 
-            // dicrotic notch — small dip at ~45% of cycle
-            let notch = -0.08 * gaussian(phase, 0.45, 0.02);
+    pub fn skip_normalization(mut self, skip_normalization: bool) -> Self {
+        self.options.skip_normalization = skip_normalization;
+        self
+    }
 
-            // diastolic peak — smaller secondary bump at ~55% of cycle
-            let diastolic = 0.15 * gaussian(phase, 0.55, 0.04);
+    pub fn build(self) -> CompressionOptions {
+        self.options
+    }
+}
 
-            // slow baseline variation simulating respiration (~0.3 Hz)
-            let baseline = 0.03 * (2.0 * std::f32::consts::PI * 0.3 * t).sin();
+/// Quantizes a normalized coefficient by truncation toward zero, saturating to `width`.
+#[inline]
+pub(crate) fn quantize(x: f32, multiplier: f64, width: CoefficientWidth) -> i32 {
+    // Float to int `as` casts saturate, so only the narrow width needs explicit clamping.
+    let (q_min, q_max) = match width {
+        CoefficientWidth::I16 => (i16::MIN as f64, i16::MAX as f64),
+        CoefficientWidth::I32 => (f64::MIN, f64::MAX),
+    };
+    (x as f64 * multiplier).min(q_max).max(q_min) as i32
+}
 
-            // noise
-            let noise = 0.005 * pseudo_noise(i);
+/// Quantizes and thresholds one detail level, returning it with the number of zeroed
+/// coefficients.
+fn quantize_band(
+    details: &[f32],
+    scale: QuantizationScale,
+    magnitude: u32,
+    options: CompressionOptions,
+) -> (Vec<i32>, usize) {
+    let multiplier = scale.multiplier_f64();
+    let mut quantized = quantize_slice(details, multiplier, options.coefficient_width);
+    let zeroed = threshold(&mut quantized, magnitude, options.threshold_mode);
+    (quantized, zeroed)
+}
 
-            signal[i] = (systolic + notch + diastolic + baseline + noise) * 3500.0;
-        }
+#[cfg(not(feature = "rayon"))]
+#[inline]
+fn quantize_details(
+    details: &[f32],
+    scale: QuantizationScale,
+    magnitude: u32,
+    options: CompressionOptions,
+) -> (Vec<i32>, usize) {
+    quantize_band(details, scale, magnitude, options)
+}
 
-        signal
+/// Same as [`quantize_band`], split into chunks across the rayon pool. Every coefficient
+/// is treated on its own, so the output matches the sequential pass exactly.
+#[cfg(feature = "rayon")]
+fn quantize_details(
+    details: &[f32],
+    scale: QuantizationScale,
+    magnitude: u32,
+    options: CompressionOptions,
+) -> (Vec<i32>, usize) {
+    const CHUNK_SIZE: usize = 16384;
+    if details.len() <= CHUNK_SIZE {
+        return quantize_band(details, scale, magnitude, options);
+    }
+    let chunks = details
+        .par_chunks(CHUNK_SIZE)
+        .map(|chunk| quantize_band(chunk, scale, magnitude, options))
+        .collect::<Vec<(Vec<i32>, usize)>>();
+    let mut quantized = Vec::with_capacity(details.len());
+    let mut zeroed = 0usize;
+    for (chunk, chunk_zeroed) in chunks {
+        quantized.extend_from_slice(&chunk);
+        zeroed += chunk_zeroed;
     }
+    (quantized, zeroed)
+}
 
-    #[inline]
-    fn gaussian(x: f32, mean: f32, std: f32) -> f32 {
-        (-(x - mean).powi(2) / (2.0 * std.powi(2))).exp()
+/// Magnitude below which quantized detail coefficients are treated as noise, in
+/// quantization steps of `scale`.
+pub(crate) fn threshold_magnitude(scale: QuantizationScale, cutoff_level: CutoffLevel) -> u32 {
+    let threshold = match scale {
+        QuantizationScale::S6 => 0,
+        QuantizationScale::S7 => 0,
+        QuantizationScale::S8 => 1,
+        QuantizationScale::S9 => 1,
+        QuantizationScale::S10 => 2,
+        QuantizationScale::S11 => 2,
+        QuantizationScale::S12 => 3,
+        QuantizationScale::S13
+        | QuantizationScale::S14
+        | QuantizationScale::S15
+        | QuantizationScale::S16
+        | QuantizationScale::S17
+        | QuantizationScale::S18
+        | QuantizationScale::S19
+        | QuantizationScale::S20 => 3,
+    };
+    match cutoff_level {
+        CutoffLevel::Low => threshold,
+        CutoffLevel::Medium => threshold * 3,
+        CutoffLevel::High => threshold * 7,
+        CutoffLevel::None => 0,
     }
+}
 
-    /// Deterministic pseudo-noise via LCG, avoids rand dependency
-    #[inline]
-    fn pseudo_noise(i: usize) -> f32 {
-        let x = (i as u32).wrapping_mul(1664525).wrapping_add(1013904223);
-        // map to [-1, 1]
-        (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+/// Threshold of one detail band before quantization, in quantization steps of `scale`,
+/// as picked by [`CompressionOptions::threshold_strategy`]. `max_sigma` caps the noise
+/// level estimated for the band, see [`universal_threshold`].
+pub(crate) fn band_threshold(
+    details: &[f32],
+    scale: QuantizationScale,
+    max_sigma: f32,
+    options: CompressionOptions,
+) -> u32 {
+    match options.threshold_strategy {
+        ThresholdStrategy::Fixed => threshold_magnitude(scale, options.cutoff_level),
+        ThresholdStrategy::Adaptive => {
+            (universal_threshold(details, max_sigma) as f64 * scale.multiplier_f64()).ceil() as u32
+        }
     }
+}
 
-    pub fn prd(original: &[f32], reconstructed: &[f32]) -> f64 {
-        assert_eq!(original.len(), reconstructed.len());
-        let n = original.len() as f64;
+/// Noise level of a detail band estimated from its median absolute deviation,
+/// `median(|d|) / 0.6745`.
+pub(crate) fn noise_sigma(details: &[f32]) -> f32 {
+    if details.is_empty() {
+        return 0.;
+    }
+    let mut magnitudes = details.iter().map(|x| x.abs()).collect::<Vec<f32>>();
+    let middle = magnitudes.len() / 2;
+    let (_, &mut median, _) = magnitudes.select_nth_unstable_by(middle, f32::total_cmp);
+    median / 0.6745
+}
 
-        // mean of original
-        let mean = original.iter().map(|&x| x as f64).sum::<f64>() / n;
+/// VisuShrink threshold `sigma * sqrt(2 ln N)` of a detail band.
+///
+/// At coarse levels the signal itself fills most of the band and its deviation no longer
+/// measures noise, so the band's own estimate is capped at `max_sigma`, the estimate of the
+/// finest level.
+pub(crate) fn universal_threshold(details: &[f32], max_sigma: f32) -> f32 {
+    let sigma = noise_sigma(details).min(max_sigma);
+    sigma * (2. * (details.len().max(1) as f32).ln()).sqrt()
+}
 
-        // numerator: squared error
-        let num = original
-            .iter()
-            .zip(reconstructed.iter())
-            .map(|(&x, &y)| {
-                let diff = x as f64 - y as f64;
-                diff * diff
-            })
-            .sum::<f64>();
+/// Noise level of the finest detail band that caps [`universal_threshold`], or zero when
+/// the fixed strategy doesn't need it.
+pub(crate) fn finest_noise_sigma(finest: &[f32], options: CompressionOptions) -> f32 {
+    match options.threshold_strategy {
+        ThresholdStrategy::Fixed => 0.,
+        ThresholdStrategy::Adaptive => noise_sigma(finest),
+    }
+}
 
-        // denominator: signal energy around mean
-        let den = original
-            .iter()
-            .map(|&x| {
-                let centered = x as f64 - mean;
-                centered * centered
-            })
-            .sum::<f64>();
+/// Thresholds quantized detail coefficients against `threshold` quantization steps,
+/// returning how many non-zero ones were zeroed.
+pub(crate) fn threshold(details: &mut [i32], threshold: u32, mode: ThresholdMode) -> usize {
+    let mut zeroed = 0usize;
+    match mode {
+        ThresholdMode::Hard => {
+            for det in details.iter_mut() {
+                if det.unsigned_abs() < threshold {
+                    zeroed += (*det != 0) as usize;
+                    *det = 0;
+                }
+            }
+        }
+        ThresholdMode::Soft => {
+            for det in details.iter_mut() {
+                let shrunk = det.unsigned_abs().saturating_sub(threshold) as i32;
+                zeroed += (*det != 0 && shrunk == 0) as usize;
+                *det = shrunk * det.signum();
+            }
+        }
+    }
+    zeroed
+}
 
-        if den == 0.0 {
-            return 0.0;
+/// Returns the requested DWT depth after checking it against the signal, or picks one
+/// from the length of a `signal_len` long signal padded to `padded_len` samples.
+pub(crate) fn decomposition_levels(
+    levels: Option<DecompositionLevels>,
+    signal_len: usize,
+    working_data: &[f32],
+    dwt_worker: &dyn DwtExecutor<f32>,
+) -> Result<usize, BiolepticError> {
+    let filter_length = dwt_worker.filter_length();
+    let by_length = levels_for_length(signal_len, filter_length);
+    Ok(match levels {
+        Some(DecompositionLevels::Fixed(levels)) => {
+            let levels = levels as usize;
+            if !(1..=10).contains(&levels) || working_data.len() >> (levels - 1) < filter_length {
+                return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+                    "{levels} levels are out of 1..=10 or too deep for {signal_len} samples"
+                )));
+            }
+            levels
         }
+        Some(DecompositionLevels::Auto) => adaptive_levels(working_data, by_length, dwt_worker)?,
+        None => by_length,
+    })
+}
 
-        (num / den).sqrt() * 100.0
+/// Level count chosen when [`CompressionOptions::levels`] is `None`.
+fn levels_for_length(signal_len: usize, filter_length: usize) -> usize {
+    let mut levels = if signal_len < 20 {
+        1
+    } else if signal_len < 40 {
+        2
+    } else if signal_len < 60 {
+        3
+    } else if signal_len < 80 {
+        4
+    } else {
+        compute_max_levels(signal_len, filter_length)
+    };
+    // Long filters on short signals: the deepest level must still span the filter, counting
+    // the wrap extension applied to signals shorter than it.
+    let padded_length = signal_len.max(filter_length);
+    while levels > 1 && padded_length >> (levels - 1) < filter_length {
+        levels -= 1;
     }
+    levels
+}
 
-    #[test]
-    fn test_coding() {
-        let r_means = generate_ppg(500000, 120., 90.);
-        //35245
-        let raw_bytes = r_means.len() * size_of::<f32>();
-        let encoded = compress(
-            &r_means,
-            CompressionOptions::from_method(CompressionMethod::Cdf97),
-        )
-        .unwrap();
+/// Share of the approximation energy a detail band may hold before
+/// [`DecompositionLevels::Auto`] stops decomposing.
+const AUTO_LEVELS_ENERGY_RATIO: f64 = 0.25;
+
+fn adaptive_levels(
+    working_data: &[f32],
+    max_levels: usize,
+    dwt_worker: &dyn DwtExecutor<f32>,
+) -> Result<usize, BiolepticError> {
+    let energy = |x: &[f32]| x.iter().map(|&v| v as f64 * v as f64).sum::<f64>();
+    let mut approximation = working_data.to_vec();
+    for level in 1..max_levels {
+        let dwt = dwt_worker
+            .dwt(&approximation)
+            .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?;
+        let detail_energy = energy(&dwt.details);
+        let approximation_energy = energy(&dwt.approximations);
+        if detail_energy > approximation_energy * AUTO_LEVELS_ENERGY_RATIO {
+            return Ok(level);
+        }
+        approximation = dwt.approximations;
+    }
+    Ok(max_levels)
+}
+
+fn compute_max_levels(signal_len: usize, filter_length: usize) -> usize {
+    if signal_len < filter_length {
+        return 1;
+    }
+    // floor(log2(signal_len / filter_length))
+    let ratio = signal_len / filter_length;
+    let max = usize::BITS as usize - ratio.leading_zeros() as usize - 1;
+    max.clamp(1, 8)
+}
+
+/// Encoder statistics reported by [`compress_with_stats`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CompressionStats {
+    /// Size of the `f32` input in bytes.
+    pub input_bytes: usize,
+    /// Size of the encoded stream in bytes, header and extensions included.
+    pub output_bytes: usize,
+    /// `input_bytes / output_bytes`.
+    pub ratio: f64,
+    /// Encoded bits per input sample, `output_bytes * 8 / signal_length`.
+    pub bits_per_sample: f64,
+    /// Non-zero detail coefficients that thresholding set to zero.
+    pub zeroed_coefficients: usize,
+    /// Number of DWT levels the signal was decomposed into.
+    pub levels: u8,
+}
+
+/// Compresses a slice of `f32` samples into a Bioleptic-encoded byte vector.
+///
+/// Non-finite values (`NaN`, `±inf`) are substituted before processing:
+/// `NaN` and `-inf` become `0.0`, `+inf` becomes `1.0`, unless another
+/// [`CompressionOptions::non_finite`] policy is chosen, and
+/// [`CompressionOptions::preserve_nonfinite`] can keep their positions. The signal is then
+/// mean-centered and range-normalized, transformed with a multi-level DWT,
+/// quantized to `i16`, thresholded, and entropy-coded with deflate.
+///
+/// A signal whose range is at most `1e-5` is stored as flat: every sample decodes to the
+/// middle of the range, so a constant signal comes back exactly.
+pub fn compress(data: &[f32], options: CompressionOptions) -> Result<Vec<u8>, BiolepticError> {
+    compress_with_stats(data, options).map(|(compressed, _)| compressed)
+}
+
+/// Same as [`compress`], additionally reporting the achieved ratio and what the encoder
+/// chose, so the encoder can be monitored without decompressing.
+pub fn compress_with_stats(
+    data: &[f32],
+    options: CompressionOptions,
+) -> Result<(Vec<u8>, CompressionStats), BiolepticError> {
+    let encoded = encode_coefficients(data, options)?;
+    let zeroed_coefficients = encoded.zeroed_coefficients;
+    let levels = encoded.header.levels;
+    let compressed = entropy_code(encoded, options)?;
+    let input_bytes = size_of_val(data);
+    let stats = CompressionStats {
+        input_bytes,
+        output_bytes: compressed.len(),
+        ratio: input_bytes as f64 / compressed.len() as f64,
+        bits_per_sample: (compressed.len() * 8) as f64 / data.len() as f64,
+        zeroed_coefficients,
+        levels,
+    };
+    Ok((compressed, stats))
+}
+
+/// Same as [`compress`], additionally storing `metadata` verbatim, e.g. a device ID, lead
+/// name or acquisition timestamp as UTF-8 or key-value bytes.
+///
+/// The bytes go into the extension section after the payload, so decoders skip them and
+/// [`crate::read_metadata`] returns them without decoding the signal. The codec never
+/// interprets them, the layout is up to the producer. At most `u32::MAX` bytes fit.
+pub fn compress_with_metadata(
+    data: &[f32],
+    options: CompressionOptions,
+    metadata: &[u8],
+) -> Result<Vec<u8>, BiolepticError> {
+    if metadata.len() > u32::MAX as usize {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Metadata can hold at most {} bytes, but it had {}",
+            u32::MAX,
+            metadata.len()
+        )));
+    }
+    let mut encoded = encode_coefficients(data, options)?;
+    encoded.extensions.push(METADATA_TAG, metadata);
+    entropy_code(encoded, options)
+}
+
+/// Runs every stage of [`compress`] up to entropy coding and returns the quantized
+/// coefficients, e.g. to try a different entropy coder.
+///
+/// The first element is the record the coefficients belong to: a header with an empty
+/// payload followed by the extension section, which carries what the pre-transforms and
+/// per-band scales need on decode. Pass it with the coefficients, in the same order, to
+/// [`crate::from_coefficients`] to reconstruct the signal, or read its header with
+/// [`crate::read_header`]. Coefficients are `i32` since [`CoefficientWidth::I32`] and
+/// lossless streams exceed `i16`, with the default width every value fits in an `i16`.
+pub fn to_coefficients(
+    data: &[f32],
+    options: CompressionOptions,
+) -> Result<(Vec<u8>, Vec<i32>), BiolepticError> {
+    let encoded = encode_coefficients(data, options)?;
+    let record = write_stream(encoded.header, &[], encoded.extensions);
+    Ok((record, encoded.coefficients))
+}
+
+/// Compresses any contiguous container of `f32` samples, e.g. a `Vec<f32>`, a
+/// `Box<[f32]>` or an array, see [`compress`].
+///
+/// For `ndarray` views enable the `ndarray` feature and use [`crate::compress_array`].
+pub fn compress_from<T: AsRef<[f32]>>(
+    data: T,
+    options: CompressionOptions,
+) -> Result<Vec<u8>, BiolepticError> {
+    compress(data.as_ref(), options)
+}
+
+/// Compresses integer samples, e.g. straight from an `i16` ADC, without a detour through
+/// `f32` on the caller's side.
+///
+/// With [`CompressionOptions::lossless`] and [`CompressionMethod::Cdf53`] the samples run
+/// through the integer lifting transform and come back bit-exact, otherwise the usual lossy
+/// pipeline applies. The stream is tagged [`DataType::Int16`], decode it with
+/// [`crate::decompress_i16`]; [`crate::decompress`] rejects it.
+pub fn compress_i16(data: &[i16], options: CompressionOptions) -> Result<Vec<u8>, BiolepticError> {
+    let samples = data.iter().map(|&x| x as f32).collect::<Vec<f32>>();
+    let mut encoded = encode_samples(samples, options)?;
+    encoded.header.data_type = DataType::Int16.into();
+    entropy_code(encoded, options)
+}
+
+/// Same as [`compress`], using the samples' own allocation as the scratch buffer instead
+/// of copying them, which saves one `f32` buffer the size of the signal.
+///
+/// `data` is clobbered: it is taken over as working memory and left empty on return,
+/// whether compression succeeds or fails.
+pub fn compress_in_place(
+    data: &mut Vec<f32>,
+    options: CompressionOptions,
+) -> Result<Vec<u8>, BiolepticError> {
+    let encoded = encode_samples(std::mem::take(data), options)?;
+    entropy_code(encoded, options)
+}
+
+/// Same as [`compress`], appending the stream to `out` instead of allocating a new vector,
+/// e.g. to reuse one buffer across records. `out` is left untouched on error.
+///
+/// Reserve [`max_compressed_size`] bytes up front so the append never reallocates.
+pub fn compress_into(
+    data: &[f32],
+    options: CompressionOptions,
+    out: &mut Vec<u8>,
+) -> Result<(), BiolepticError> {
+    let encoded = encode_coefficients(data, options)?;
+    entropy_code_into(encoded, options, out)
+}
+
+/// Returns an upper bound of the stream size [`compress`] produces for `len` samples with
+/// `options`, so a buffer of this size always holds the output.
+///
+/// Covers the header, the worst-case payload of the chosen coefficient width, payload
+/// encoding and entropy coder, and every extension the options can add. Metadata of
+/// [`compress_with_metadata`] comes on top of it, plus 8 bytes of entry framing.
+pub fn max_compressed_size(len: usize, options: &CompressionOptions) -> usize {
+    let fixed_levels = match options.levels {
+        Some(DecompositionLevels::Fixed(levels)) => Some((levels as usize).clamp(1, 10)),
+        _ => None,
+    };
+    let payload = if options.lossless {
+        let levels = fixed_levels.unwrap_or_else(|| compute_max_levels(len, 8));
+        let (approximation_length, detail_lengths) = lossless_layout(len, levels);
+        max_payload_size(
+            approximation_length,
+            &detail_lengths,
+            CoefficientWidth::I32,
+            options.payload_encoding,
+        )
+    } else {
+        let dwt_worker = make_dwt_worker!(options.method, options.border_mode);
+        let filter_length = dwt_worker.filter_length();
+        let levels = fixed_levels.unwrap_or_else(|| levels_for_length(len, filter_length));
+        let mut approximation_length = len.max(filter_length);
+        let mut detail_lengths = Vec::with_capacity(levels);
+        for _ in 0..levels {
+            let size = dwt_worker.dwt_size(approximation_length);
+            detail_lengths.push(size.details_length);
+            approximation_length = size.approx_length;
+        }
+        max_payload_size(
+            approximation_length,
+            &detail_lengths,
+            options.coefficient_width,
+            options.payload_encoding,
+        )
+    };
+
+    // Every extension entry carries a 4-byte tag and a 4-byte length.
+    let entry = |data_len: usize| data_len.saturating_add(8);
+    let mut extensions = 4 + entry(12);
+    if options.preserve_nonfinite {
+        extensions += entry(max_encoded_size(EntropyCoder::Deflate, len.div_ceil(4)));
+    }
+    if let Some(decimation) = options.envelope {
+        extensions += entry(4 + len.div_ceil(decimation.max(1) as usize).saturating_mul(8));
+    }
+    if options.taper.is_some() {
+        extensions += entry(12);
+    }
+    extensions += match options.pre_transform {
+        PreTransform::None => 0,
+        PreTransform::Difference | PreTransform::Log(_) => entry(8),
+        PreTransform::PolyDetrend(_) => entry(32),
+    };
+    if options.level_scales.is_some() {
+        extensions += entry(11);
+    }
+    if options.reserved.is_some() {
+        extensions += entry(16);
+    }
+
+    BIOLEPTIC_HEADER_SIZE
+        .saturating_add(max_encoded_size(options.entropy, payload))
+        .saturating_add(extensions)
+}
+
+/// Compresses `f64` samples, keeping precision that a cast to `f32` would lose.
+///
+/// The mean of the finite samples is removed in `f64` and stored exactly, the residual
+/// then runs through the same pipeline as [`compress`]. This preserves signals riding on
+/// a large offset, the quantization itself is still limited by the scale. Decode with
+/// [`crate::decompress_f64`]. Fails when the residual doesn't fit into `f32`.
+pub fn compress_f64(data: &[f64], options: CompressionOptions) -> Result<Vec<u8>, BiolepticError> {
+    let (sum, count) = data
+        .iter()
+        .filter(|x| x.is_finite())
+        .fold((0f64, 0usize), |(sum, count), &x| (sum + x, count + 1));
+    let offset = if count == 0 { 0. } else { sum / count as f64 };
+
+    let mut residual = Vec::with_capacity(data.len());
+    for &x in data.iter() {
+        let r = (x - offset) as f32;
+        if x.is_finite() && !r.is_finite() {
+            return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+                "Sample {x} is too far from the mean {offset} to be represented"
+            )));
+        }
+        residual.push(r);
+    }
+
+    let mut encoded = encode_coefficients(&residual, options)?;
+    encoded.header.data_type = DataType::Float64.into();
+    encoded
+        .extensions
+        .push(FLOAT64_OFFSET_TAG, &offset.to_le_bytes());
+    entropy_code(encoded, options)
+}
+
+/// Serializes and entropy codes the coefficients, then frames them with the header and
+/// extensions.
+pub(crate) fn entropy_code(
+    encoded: EncodedCoefficients,
+    options: CompressionOptions,
+) -> Result<Vec<u8>, BiolepticError> {
+    let mut stream = Vec::new();
+    entropy_code_into(encoded, options, &mut stream)?;
+    Ok(stream)
+}
+
+fn entropy_code_into(
+    encoded: EncodedCoefficients,
+    options: CompressionOptions,
+    out: &mut Vec<u8>,
+) -> Result<(), BiolepticError> {
+    let mut header = encoded.header;
+
+    if header.is_zero_signal() {
+        write_stream_into(header, &[], encoded.extensions, out);
+        return Ok(());
+    }
+
+    let approximation_bytes = serialize_coefficients(
+        &encoded.coefficients,
+        &encoded.detail_lengths,
+        header.coefficient_width(),
+        options.payload_encoding,
+    );
+
+    let compressed_data =
+        entropy_encode(options.entropy, &approximation_bytes, options.deflate_level)?;
+
+    header.compressed_size = compressed_data.len() as u32;
+
+    write_stream_into(header, &compressed_data, encoded.extensions, out);
+    Ok(())
+}
+
+/// Output of the transform stage, before entropy coding and framing.
+pub(crate) struct EncodedCoefficients {
+    /// Header describing the coefficients, `compressed_size` is left at zero.
+    pub(crate) header: BiolepticHeader,
+    /// Approximation followed by the detail levels from finest to coarsest,
+    /// empty for an all-zero signal.
+    pub(crate) coefficients: Vec<i32>,
+    /// Detail level sizes from finest to coarsest.
+    pub(crate) detail_lengths: Vec<usize>,
+    /// Non-zero detail coefficients zeroed by thresholding.
+    pub(crate) zeroed_coefficients: usize,
+    pub(crate) extensions: ExtensionWriter,
+}
+
+/// Copies `data` with its non-finite samples replaced according to `policy`.
+pub(crate) fn substitute_non_finite(
+    data: &[f32],
+    policy: NonFinitePolicy,
+) -> Result<Vec<f32>, BiolepticError> {
+    let mut working_data = data.to_vec();
+    replace_non_finite(&mut working_data, policy)?;
+    Ok(working_data)
+}
+
+/// Replaces the non-finite samples of `data` according to `policy`, leaving finite ones as
+/// they are, and returns how many `NaN`, `+inf` and `-inf` samples were replaced.
+fn replace_non_finite(
+    data: &mut [f32],
+    policy: NonFinitePolicy,
+) -> Result<(u32, u32, u32), BiolepticError> {
+    let mut counts = (0u32, 0u32, 0u32);
+    let (mut finite_min, mut finite_max) = (f32::INFINITY, f32::NEG_INFINITY);
+    for &x in data.iter() {
+        if x.is_finite() {
+            finite_min = finite_min.min(x);
+            finite_max = finite_max.max(x);
+        } else if x.is_nan() {
+            counts.0 += 1;
+        } else if x.is_sign_positive() {
+            counts.1 += 1;
+        } else {
+            counts.2 += 1;
+        }
+    }
+    if counts == (0, 0, 0) {
+        return Ok(counts);
+    }
+
+    let (finite_min, finite_max) = if finite_min <= finite_max {
+        (finite_min, finite_max)
+    } else {
+        (0., 0.)
+    };
+    let substitute = |x: f32| match policy {
+        NonFinitePolicy::Substitute => {
+            if x == f32::INFINITY {
+                1.
+            } else {
+                0.
+            }
+        }
+        NonFinitePolicy::ClampToRange => {
+            if x.is_nan() {
+                finite_min + (finite_max - finite_min) * 0.5
+            } else if x.is_sign_positive() {
+                finite_max
+            } else {
+                finite_min
+            }
+        }
+        NonFinitePolicy::Zero | NonFinitePolicy::Interpolate | NonFinitePolicy::Error => 0.,
+    };
+
+    match policy {
+        NonFinitePolicy::Error => {
+            let (index, x) = data.iter().enumerate().find(|x| !x.1.is_finite()).unwrap();
+            Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+                "Sample {index} is {x}, but non-finite samples are rejected"
+            )))
+        }
+        NonFinitePolicy::Interpolate => {
+            // Only non-finite runs are written, so the finite neighbors read stay intact.
+            let mut previous: Option<usize> = None;
+            let mut i = 0usize;
+            while i < data.len() {
+                if data[i].is_finite() {
+                    previous = Some(i);
+                    i += 1;
+                    continue;
+                }
+                let end = (i..data.len())
+                    .find(|&j| data[j].is_finite())
+                    .unwrap_or(data.len());
+                match (previous.map(|p| data[p]), data.get(end).copied()) {
+                    (Some(a), Some(b)) => {
+                        let span = (end - i + 1) as f32;
+                        for (k, dst) in data[i..end].iter_mut().enumerate() {
+                            *dst = a + (b - a) * ((k + 1) as f32 / span);
+                        }
+                    }
+                    (Some(a), None) => data[i..end].fill(a),
+                    (None, Some(b)) => data[i..end].fill(b),
+                    (None, None) => data[i..end].fill(0.),
+                }
+                i = end;
+            }
+            Ok(counts)
+        }
+        _ => {
+            for x in data.iter_mut().filter(|x| !x.is_finite()) {
+                *x = substitute(*x);
+            }
+            Ok(counts)
+        }
+    }
+}
+
+/// Substitutes non-finite values, normalizes, transforms, quantizes and thresholds the signal.
+pub(crate) fn encode_coefficients(
+    data: &[f32],
+    options: CompressionOptions,
+) -> Result<EncodedCoefficients, BiolepticError> {
+    encode_samples(data.to_vec(), options)
+}
+
+/// Same as [`encode_coefficients`], using `working_data` itself as the scratch buffer.
+fn encode_samples(
+    mut working_data: Vec<f32>,
+    options: CompressionOptions,
+) -> Result<EncodedCoefficients, BiolepticError> {
+    let data = working_data.as_slice();
+    if data.is_empty() {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "Can't compress empty data".to_string(),
+        ));
+    }
+    if data.len() > i32::MAX as usize {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Can't compress data bigger than {}, but data was {}",
+            i32::MAX,
+            data.len()
+        )));
+    }
+    let max_scale = options
+        .level_scales
+        .into_iter()
+        .flatten()
+        .fold(options.scale, |a, b| a.max(b));
+    if options.coefficient_width == CoefficientWidth::I16 && max_scale > QuantizationScale::S12 {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Scale {} requires CoefficientWidth::I32",
+            max_scale.as_u8()
+        )));
+    }
+    if let Some(percent) = options.taper
+        && !(1..=100).contains(&percent)
+    {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Taper must cover 1..=100 percent of the signal, but it was {percent}"
+        )));
+    }
+    if let PreTransform::PolyDetrend(degree) = options.pre_transform
+        && degree > 3
+    {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Polynomial detrend supports degree up to 3, but it was {degree}"
+        )));
+    }
+    if let PreTransform::Log(exponent) = options.pre_transform
+        && !(-32..=32).contains(&exponent)
+    {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Log companding exponent must be in -32..=32, but it was {exponent}"
+        )));
+    }
+    if options.deflate_level > 9 {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Deflate level must be in 0..=9, but it was {}",
+            options.deflate_level
+        )));
+    }
+    if let Some(hz) = options.sample_rate
+        && !(hz.is_normal() && hz > 0.)
+    {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Sample rate must be a normal positive number, but it was {hz}"
+        )));
+    }
+    if options.envelope == Some(0) {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "Envelope decimation must be at least 1".to_string(),
+        ));
+    }
+    if options.lossless
+        && (options.method != CompressionMethod::Cdf53
+            || options.pre_transform != PreTransform::None
+            || options.taper.is_some())
+    {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "Lossless mode requires Cdf53 without pre-transform and taper".to_string(),
+        ));
+    }
+    if options.lossless && options.skip_normalization {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "Lossless mode can't skip normalization".to_string(),
+        ));
+    }
+    let original_length = data.len();
+    // The map needs the samples before substitution, the counts come from substituting.
+    let non_finite_map = if options.preserve_nonfinite {
+        Some(encode_non_finite_map(data, options.deflate_level)?)
+    } else {
+        None
+    };
+    let non_finite_counts = replace_non_finite(&mut working_data, options.non_finite)?;
+
+    let mut extensions = ExtensionWriter::default();
+    if non_finite_counts != (0, 0, 0) {
+        extensions.push(NON_FINITE_TAG, &encode_non_finite_counts(non_finite_counts));
+        if let Some(map) = non_finite_map {
+            extensions.push(NON_FINITE_MAP_TAG, &map);
+        }
+    }
+    if let Some(decimation) = options.envelope {
+        extensions.push(ENVELOPE_TAG, &encode_envelope(&working_data, decimation));
+    }
+    if let Some(reserved) = options.reserved {
+        extensions.push(RESERVED_TAG, &reserved);
+    }
+
+    if let Some(percent) = options.taper {
+        let alpha = percent as f32 / 100.;
+        let (start, end) = apply_taper(&mut working_data, alpha);
+        extensions.push(TAPER_TAG, &encode_taper(alpha, start, end));
+    }
+
+    let mut flags = 0u16;
+    match options.pre_transform {
+        PreTransform::None => {}
+        PreTransform::Difference => {
+            let initial = difference(&mut working_data);
+            extensions.push(DIFFERENCE_TAG, &initial.to_le_bytes());
+            flags |= FLAG_DIFFERENCE;
+        }
+        PreTransform::PolyDetrend(degree) => {
+            let coefficients = fit_polynomial(&working_data, degree as usize);
+            add_polynomial(&mut working_data, &coefficients, -1.);
+            extensions.push(POLY_TAG, &encode_polynomial(&coefficients));
+        }
+        PreTransform::Log(exponent) => {
+            let k = 2f32.powi(exponent as i32);
+            log_compand(&mut working_data, k);
+            extensions.push(LOG_TAG, &k.to_le_bytes());
+        }
+    }
+
+    // Identity normalization, the decoder maps the samples back unchanged.
+    let mut v_min = 0.;
+    let mut v_max = 1.;
+    let mut v_mean = 0.;
+    if !options.skip_normalization {
+        v_min = f32::INFINITY;
+        v_max = f32::NEG_INFINITY;
+        for &val in working_data.iter() {
+            v_min = val.min(v_min);
+            v_max = val.max(v_max);
+        }
+
+        if v_min == 0. && v_max == 0. {
+            // An all-zero signal carries nothing but its length, so only the header is written
+            // and the decoder rebuilds the zeros directly.
+            let mut header = BiolepticHeader::new(
+                DataType::Float32,
+                options.method,
+                1,
+                options.scale,
+                original_length as u32,
+                0.,
+                0.,
+                0.,
+                0,
+            );
+            header.flags |= FLAG_ZERO_SIGNAL | flags;
+            header.quality_tag = options.quality_tag;
+            header.sample_rate = options.sample_rate.map_or(0, f32::to_bits);
+            return Ok(EncodedCoefficients {
+                header,
+                coefficients: Vec::new(),
+                detail_lengths: Vec::new(),
+                zeroed_coefficients: 0,
+                extensions,
+            });
+        }
+
+        if options.lossless {
+            return encode_lossless(&working_data, options, v_min, v_max, extensions);
+        }
+
+        let mut v_sum = 0.;
+        let range = v_max - v_min;
+        if range > 1e-5 {
+            normalize(&mut working_data, v_min, 1. / range);
+            for &q in working_data.iter() {
+                v_sum += q;
+            }
+            v_mean = v_sum / original_length as f32;
+            // Subtracting is exact when scaled by one.
+            normalize(&mut working_data, v_mean, 1.);
+        } else {
+            // A (nearly) constant signal has nothing left to transform. Its samples are
+            // all reconstructed at the middle of the range, which is the constant itself
+            // when the range is zero and off by at most `range / 2` otherwise.
+            working_data.fill(0.);
+            v_mean = 0.5;
+        }
+    }
+
+    let dwt_worker = make_dwt_worker!(options.method, options.border_mode);
+
+    if working_data.len() < dwt_worker.filter_length() {
+        let target_len = dwt_worker.filter_length();
+        let current_len = working_data.len();
+        let needed = target_len - current_len;
+        // Wrap-extend: mirror the existing samples cyclically to avoid
+        // zero-padding artifacts at the filter boundary.
+        let extension = (0..needed)
+            .map(|i| working_data[i % current_len])
+            .collect::<Vec<f32>>();
+        working_data.extend_from_slice(&extension);
+    }
+
+    let level = decomposition_levels(
+        options.levels,
+        original_length,
+        &working_data,
+        dwt_worker.as_ref(),
+    )?;
+
+    let dwt = dwt_worker
+        .multi_dwt(&working_data, level)
+        .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?;
+
+    if dwt.levels.is_empty() {
+        return Err(BiolepticError::UnderlyingDwtError(
+            "Internal DWT returned zero levels, what shouldn't happen".to_string(),
+        ));
+    }
+
+    let last_dwt_level = match dwt.levels.last() {
+        None => {
+            return Err(BiolepticError::UnderlyingDwtError(
+                "Internal DWT returned zero levels, what shouldn't happen".to_string(),
+            ));
+        }
+        Some(v) => v,
+    };
+
+    let band_scale = |band: usize| options.level_scales.map_or(options.scale, |x| x[band]);
+    let max_sigma = finest_noise_sigma(&dwt.levels[0].details, options);
+    let coefficient_width = options.coefficient_width;
+
+    let approximation_multiplier = band_scale(0).multiplier_f64();
+    let mut approximation = quantize_slice(
+        &last_dwt_level.approximations,
+        approximation_multiplier,
+        coefficient_width,
+    );
+
+    #[cfg(feature = "rayon")]
+    let levels = dwt.levels.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let levels = dwt.levels.iter();
+    let details = levels
+        .enumerate()
+        .map(|(level, x)| {
+            let scale = band_scale(level + 1);
+            let magnitude = band_threshold(&x.details, scale, max_sigma, options);
+            quantize_details(&x.details, scale, magnitude, options)
+        })
+        .collect::<Vec<(Vec<i32>, usize)>>();
+
+    let total_details_length = details.iter().map(|x| x.0.len()).sum::<usize>();
+    let zeroed_coefficients = details.iter().map(|x| x.1).sum::<usize>();
+
+    if options.level_scales.is_some() {
+        let scales = (0..=level)
+            .map(|band| band_scale(band).as_u8())
+            .collect::<Vec<u8>>();
+        extensions.push(LEVEL_SCALES_TAG, &scales);
+    }
+
+    approximation
+        .try_reserve_exact(total_details_length)
+        .map_err(|_| BiolepticError::OutOfMemoryError(total_details_length))?;
+
+    for (level_details, _) in details.iter() {
+        approximation.extend_from_slice(level_details);
+    }
+
+    let mut header = BiolepticHeader::new(
+        DataType::Float32,
+        options.method,
+        level as u8,
+        options.scale,
+        original_length as u32,
+        v_min,
+        v_max,
+        v_mean,
+        0,
+    );
+    header.flags |= flags;
+    header.quality_tag = options.quality_tag;
+    header.sample_rate = options.sample_rate.map_or(0, f32::to_bits);
+    if options.coefficient_width == CoefficientWidth::I32 {
+        header.flags |= FLAG_WIDE_COEFFICIENTS;
+    }
+    if options.dequant_mode == DequantMode::Midpoint {
+        header.flags |= FLAG_MIDPOINT_DEQUANT;
+    }
+    if options.threshold_mode == ThresholdMode::Soft {
+        header.flags |= FLAG_SOFT_THRESHOLD;
+    }
+    header.set_payload_encoding(options.payload_encoding);
+    header.set_entropy_coder(options.entropy);
+    header.set_border_mode(options.border_mode);
+
+    Ok(EncodedCoefficients {
+        header,
+        coefficients: approximation,
+        detail_lengths: details.iter().map(|x| x.0.len()).collect(),
+        zeroed_coefficients,
+        extensions,
+    })
+}
+
+/// Transforms integer-valued samples with reversible CDF 5/3 lifting, skipping
+/// normalization and quantization.
+fn encode_lossless(
+    working_data: &[f32],
+    options: CompressionOptions,
+    v_min: f32,
+    v_max: f32,
+    extensions: ExtensionWriter,
+) -> Result<EncodedCoefficients, BiolepticError> {
+    let mut samples = Vec::with_capacity(working_data.len());
+    for &v in working_data.iter() {
+        if v.fract() != 0. || v.abs() > LOSSLESS_MAX_MAGNITUDE {
+            return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+                "Lossless mode requires whole samples up to {LOSSLESS_MAX_MAGNITUDE} in magnitude, but got {v}"
+            )));
+        }
+        samples.push(v as i32);
+    }
+
+    let level = if let Some(DecompositionLevels::Fixed(levels)) = options.levels {
+        let levels = levels as usize;
+        if !(1..=10).contains(&levels) || samples.len() >> (levels - 1) == 0 {
+            return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+                "{levels} levels are out of 1..=10 or too deep for {} samples",
+                samples.len()
+            )));
+        }
+        levels
+    } else {
+        compute_max_levels(samples.len(), 8)
+    };
+
+    let coefficients = forward_lossless(&samples, level);
+    let (_, detail_lengths) = lossless_layout(samples.len(), level);
+
+    let mut header = BiolepticHeader::new(
+        DataType::Float32,
+        options.method,
+        level as u8,
+        options.scale,
+        samples.len() as u32,
+        v_min,
+        v_max,
+        0.,
+        0,
+    );
+    header.flags |= FLAG_LOSSLESS | FLAG_WIDE_COEFFICIENTS;
+    header.quality_tag = options.quality_tag;
+    header.sample_rate = options.sample_rate.map_or(0, f32::to_bits);
+    header.set_payload_encoding(options.payload_encoding);
+    header.set_entropy_coder(options.entropy);
+
+    Ok(EncodedCoefficients {
+        header,
+        coefficients,
+        detail_lengths,
+        zeroed_coefficients: 0,
+        extensions,
+    })
+}
+
+pub(crate) fn write_stream(
+    header: BiolepticHeader,
+    compressed_data: &[u8],
+    extensions: ExtensionWriter,
+) -> Vec<u8> {
+    let mut stream = Vec::new();
+    write_stream_into(header, compressed_data, extensions, &mut stream);
+    stream
+}
+
+/// Same as [`write_stream`], appending the record to `out`.
+fn write_stream_into(
+    mut header: BiolepticHeader,
+    compressed_data: &[u8],
+    extensions: ExtensionWriter,
+    out: &mut Vec<u8>,
+) {
+    if !extensions.is_empty() {
+        header.flags |= FLAG_EXTENDED;
+    }
+    if !compressed_data.is_empty() {
+        header.payload_crc = payload_crc(compressed_data);
+    }
+    out.extend_from_slice(&header.to_bytes());
+    out.extend_from_slice(compressed_data);
+    out.extend_from_slice(&extensions.finish());
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::BIOLEPTIC_HEADER_SIZE;
+    use crate::decompressor::{decompress, decompress_scaled};
+    use crate::metrics::prd;
+
+    use crate::payload::deserialize_coefficients;
+    pub(crate) use crate::synthetic::synthetic_ppg as generate_ppg;
+    use crate::synthetic::{gaussian, pseudo_noise};
+
+    #[test]
+    fn test_coding() {
+        let r_means = generate_ppg(500000, 120., 90.);
+        //35245
+        let raw_bytes = r_means.len() * size_of::<f32>();
+        let encoded = compress(
+            &r_means,
+            CompressionOptions::from_method(CompressionMethod::Cdf97),
+        )
+        .unwrap();
         let compressed_bytes = encoded.len();
         let decompressed = decompress(&encoded).unwrap();
         let cr = raw_bytes as f32 / compressed_bytes as f32;
         let prd_val = prd(&r_means, &decompressed);
         assert!(prd_val < 0.5, "got PRD {prd_val}");
         println!(
-            "n={:5}  raw={:8}  compressed={:8}  cr={:6.2}:1  PRD={:.4}%",
-            r_means.len(),
-            raw_bytes,
-            compressed_bytes,
-            cr,
-            prd_val
+            "n={:5}  raw={:8}  compressed={:8}  cr={:6.2}:1  PRD={:.4}%",
+            r_means.len(),
+            raw_bytes,
+            compressed_bytes,
+            cr,
+            prd_val
+        );
+    }
+
+    #[test]
+    fn test_coding_small() {
+        let r_means = [1., 2., 3., 4., 5., 6.];
+        let encoded = compress(
+            &r_means,
+            CompressionOptions::from_method(CompressionMethod::Cdf53),
+        )
+        .unwrap();
+        println!("{:?}", encoded.len());
+        let decompressed = decompress(&encoded).unwrap();
+        println!("{:?}", decompressed.len());
+        assert_eq!(decompressed.len(), r_means.len());
+    }
+
+    #[test]
+    fn test_all_methods_round_trip() {
+        let signal = generate_ppg(5000, 120., 75.);
+        for method in [
+            CompressionMethod::Cdf53,
+            CompressionMethod::Cdf97,
+            CompressionMethod::Db2,
+            CompressionMethod::Db4,
+            CompressionMethod::Db6,
+            CompressionMethod::Db8,
+            CompressionMethod::Sym4,
+            CompressionMethod::Sym6,
+            CompressionMethod::Sym8,
+            CompressionMethod::Coif1,
+            CompressionMethod::Coif2,
+            CompressionMethod::Coif3,
+            CompressionMethod::Bior22,
+            CompressionMethod::Bior44,
+            CompressionMethod::Bior68,
+        ] {
+            let compressed = compress(&signal, CompressionOptions::from_method(method)).unwrap();
+            let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+            assert_eq!(header.compression_method().unwrap(), method);
+            let decompressed = decompress(&compressed).unwrap();
+            assert!(prd(&signal, &decompressed) < 1., "{method:?}");
+        }
+    }
+
+    #[test]
+    fn test_short_signals_round_trip() {
+        let signal = generate_ppg(32, 120., 75.);
+        for method in [
+            CompressionMethod::Cdf53,
+            CompressionMethod::Cdf97,
+            CompressionMethod::Db2,
+            CompressionMethod::Db4,
+            CompressionMethod::Db6,
+            CompressionMethod::Db8,
+            CompressionMethod::Sym4,
+            CompressionMethod::Sym6,
+            CompressionMethod::Sym8,
+            CompressionMethod::Coif1,
+            CompressionMethod::Coif2,
+            CompressionMethod::Coif3,
+            CompressionMethod::Bior22,
+            CompressionMethod::Bior44,
+            CompressionMethod::Bior68,
+        ] {
+            let filter_length = make_dwt_worker!(method, BorderMode::default()).filter_length();
+            for length in 1..=32 {
+                let data = &signal[..length];
+                let compressed = compress(data, CompressionOptions::from_method(method))
+                    .unwrap_or_else(|x| panic!("{method:?} {length}: {x}"));
+                let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+                let levels = header.levels as usize;
+                assert!(
+                    levels == 1 || length.max(filter_length) >> (levels - 1) >= filter_length,
+                    "{method:?} {length}: {levels} levels"
+                );
+                let decompressed =
+                    decompress(&compressed).unwrap_or_else(|x| panic!("{method:?} {length}: {x}"));
+                assert_eq!(decompressed.len(), length);
+                let max_error = data
+                    .iter()
+                    .zip(decompressed.iter())
+                    .map(|(a, b)| (a - b).abs())
+                    .fold(0f32, f32::max);
+                assert!(
+                    max_error < 20.,
+                    "{method:?} {length}: max error {max_error}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reflect_border_reduces_edge_error() {
+        // A ramp jumps from its last sample back to its first when wrapped.
+        let signal = (0..4096).map(|i| i as f32 * 0.25).collect::<Vec<f32>>();
+        let edge_error = |border_mode: BorderMode| {
+            let options = CompressionOptions {
+                border_mode,
+                scale: QuantizationScale::S12,
+                ..CompressionOptions::from_method(CompressionMethod::Db4)
+            };
+            let compressed = compress(&signal, options).unwrap();
+            assert_eq!(
+                BiolepticHeader::from_bytes(&compressed)
+                    .unwrap()
+                    .border_mode(),
+                border_mode
+            );
+            let decompressed = decompress(&compressed).unwrap();
+            let edges = (0..32).chain(signal.len() - 32..signal.len());
+            edges
+                .map(|i| (signal[i] - decompressed[i]).abs())
+                .fold(0f32, f32::max)
+        };
+        let wrap = edge_error(BorderMode::Wrap);
+        let reflect = edge_error(BorderMode::Reflect);
+        assert!(reflect < wrap, "reflect {reflect} vs wrap {wrap}");
+    }
+
+    #[test]
+    fn test_config_code_round_trip() {
+        let methods = [
+            CompressionMethod::Cdf53,
+            CompressionMethod::Cdf97,
+            CompressionMethod::Db4,
+            CompressionMethod::Sym4,
+            CompressionMethod::Db2,
+            CompressionMethod::Db6,
+            CompressionMethod::Db8,
+            CompressionMethod::Coif1,
+            CompressionMethod::Coif2,
+            CompressionMethod::Coif3,
+            CompressionMethod::Sym6,
+            CompressionMethod::Sym8,
+            CompressionMethod::Bior22,
+            CompressionMethod::Bior44,
+            CompressionMethod::Bior68,
+        ];
+        let cutoffs = [
+            CutoffLevel::Low,
+            CutoffLevel::Medium,
+            CutoffLevel::High,
+            CutoffLevel::None,
+        ];
+        for method in methods {
+            for scale in 6..=12u8 {
+                for cutoff_level in cutoffs {
+                    for coefficient_width in [CoefficientWidth::I16, CoefficientWidth::I32] {
+                        let options = CompressionOptions {
+                            method,
+                            scale: QuantizationScale::try_from(scale).unwrap(),
+                            cutoff_level,
+                            coefficient_width,
+                            ..Default::default()
+                        };
+                        let code = options.to_code().unwrap();
+                        assert_eq!(CompressionOptions::from_code(code).unwrap(), options);
+                    }
+                }
+            }
+        }
+        assert!(CompressionOptions::from_code(0xff).is_err());
+        assert!(CompressionOptions::from_code(1 << 20).is_err());
+        assert!(CompressionOptions::from_code(1 | (5 << 8)).is_err());
+
+        let unrepresentable = [
+            CompressionOptions::default().with_taper(10),
+            CompressionOptions::default().with_sample_rate(250.),
+            CompressionOptions {
+                pre_transform: PreTransform::Difference,
+                ..Default::default()
+            },
+            CompressionOptions {
+                payload_encoding: PayloadEncoding::Varint,
+                ..Default::default()
+            },
+            CompressionOptions {
+                reserved: Some(*b"experiment-0001\0"),
+                ..Default::default()
+            },
+        ];
+        for options in unrepresentable {
+            assert!(matches!(
+                options.to_code(),
+                Err(BiolepticError::UnsupportedCompressorConfiguration(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_envelope_bounds_decoded_signal() {
+        let signal = generate_ppg(10000, 120., 75.);
+        let decimation = 256;
+        let encoded = compress(
+            &signal,
+            CompressionOptions::default().with_envelope(decimation),
+        )
+        .unwrap();
+        let header = BiolepticHeader::from_bytes(&encoded).unwrap();
+        let envelope = header.envelope(&encoded).unwrap().unwrap();
+        let decompressed = decompress(&encoded).unwrap();
+        assert_eq!(envelope.len(), signal.len().div_ceil(decimation as usize));
+        // The envelope describes the input, so allow for the quantization error.
+        let tolerance = 0.01 * (header.max_f32() - header.min_f32());
+        for (bucket, &(v_min, v_max)) in decompressed.chunks(decimation as usize).zip(&envelope) {
+            for &v in bucket {
+                assert!(v >= v_min - tolerance && v <= v_max + tolerance);
+            }
+        }
+
+        let plain = compress(&signal, CompressionOptions::default()).unwrap();
+        let plain_header = BiolepticHeader::from_bytes(&plain).unwrap();
+        assert!(plain_header.envelope(&plain).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_wide_coefficients_spiky_signal() {
+        let signal = (0..20000)
+            .map(|i| {
+                let base = (i as f32 * 0.05).sin() + 0.1 * pseudo_noise(i);
+                if i % 700 == 0 { base + 5000. } else { base }
+            })
+            .collect::<Vec<f32>>();
+        let narrow = compress(
+            &signal,
+            CompressionOptions {
+                scale: QuantizationScale::S12,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let wide = compress(
+            &signal,
+            CompressionOptions {
+                scale: QuantizationScale::S20,
+                coefficient_width: CoefficientWidth::I32,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let prd_narrow = prd(&signal, &decompress(&narrow).unwrap());
+        let prd_wide = prd(&signal, &decompress(&wide).unwrap());
+        assert!(
+            prd_wide * 10. < prd_narrow,
+            "i32 PRD {prd_wide} vs i16 PRD {prd_narrow}"
+        );
+
+        assert!(
+            compress(
+                &signal,
+                CompressionOptions {
+                    scale: QuantizationScale::S16,
+                    ..Default::default()
+                },
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_sparse_details_round_trip() {
+        let signal = generate_ppg(100000, 120., 75.);
+        let dense_options = CompressionOptions {
+            scale: QuantizationScale::S8,
+            cutoff_level: CutoffLevel::High,
+            ..Default::default()
+        };
+        let sparse_options = CompressionOptions {
+            payload_encoding: PayloadEncoding::SparseDetails,
+            ..dense_options
+        };
+        let dense = compress(&signal, dense_options).unwrap();
+        let sparse = compress(&signal, sparse_options).unwrap();
+        let header = BiolepticHeader::from_bytes(&sparse).unwrap();
+        assert_eq!(
+            header.payload_encoding().unwrap(),
+            PayloadEncoding::SparseDetails
+        );
+        assert_eq!(decompress(&dense).unwrap(), decompress(&sparse).unwrap());
+        println!(
+            "dense={} sparse={} gain={:.3}",
+            dense.len(),
+            sparse.len(),
+            dense.len() as f32 / sparse.len() as f32
+        );
+
+        let wide = compress(
+            &signal,
+            CompressionOptions {
+                coefficient_width: CoefficientWidth::I32,
+                ..sparse_options
+            },
+        )
+        .unwrap();
+        assert_eq!(decompress(&wide).unwrap(), decompress(&dense).unwrap());
+
+        let coefficients = [5, 0, 0, 0, -3, 0, 0];
+        let mut payload = serialize_coefficients(
+            &coefficients,
+            &[4, 2],
+            CoefficientWidth::I16,
+            PayloadEncoding::SparseDetails,
+        );
+        let decode = |payload: &[u8]| {
+            deserialize_coefficients(
+                payload,
+                1,
+                &[4, 2],
+                CoefficientWidth::I16,
+                PayloadEncoding::SparseDetails,
+            )
+        };
+        assert_eq!(decode(&payload).unwrap(), coefficients);
+        payload.extend_from_slice(&[0, 0, 1]);
+        assert!(decode(&payload).is_err());
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        let signal = generate_ppg(100000, 120., 75.);
+        let dense_options = CompressionOptions {
+            scale: QuantizationScale::S8,
+            cutoff_level: CutoffLevel::High,
+            ..Default::default()
+        };
+        let varint_options = CompressionOptions {
+            payload_encoding: PayloadEncoding::Varint,
+            ..dense_options
+        };
+        let dense = compress(&signal, dense_options).unwrap();
+        let varint = compress(&signal, varint_options).unwrap();
+        let header = BiolepticHeader::from_bytes(&varint).unwrap();
+        assert_eq!(header.payload_encoding().unwrap(), PayloadEncoding::Varint);
+        assert_eq!(decompress(&dense).unwrap(), decompress(&varint).unwrap());
+        println!(
+            "dense={} varint={} gain={:.3}",
+            dense.len(),
+            varint.len(),
+            dense.len() as f32 / varint.len() as f32
+        );
+
+        let wide_options = CompressionOptions {
+            scale: QuantizationScale::S16,
+            coefficient_width: CoefficientWidth::I32,
+            ..varint_options
+        };
+        let wide = compress(&signal, wide_options).unwrap();
+        let expected = compress(
+            &signal,
+            CompressionOptions {
+                payload_encoding: PayloadEncoding::Dense,
+                ..wide_options
+            },
+        )
+        .unwrap();
+        assert_eq!(decompress(&wide).unwrap(), decompress(&expected).unwrap());
+
+        let coefficients = [0, -1, 300, i16::MIN as i32, 0, 0, 7];
+        let mut payload = serialize_coefficients(
+            &coefficients,
+            &[4, 2],
+            CoefficientWidth::I16,
+            PayloadEncoding::Varint,
+        );
+        let decode = |payload: &[u8]| {
+            deserialize_coefficients(
+                payload,
+                1,
+                &[4, 2],
+                CoefficientWidth::I16,
+                PayloadEncoding::Varint,
+            )
+        };
+        assert_eq!(decode(&payload).unwrap(), coefficients);
+        payload.push(0);
+        assert!(decode(&payload).is_err());
+        assert!(decode(&payload[..payload.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn test_zero_runs_round_trip() {
+        let signal = generate_ppg(100000, 120., 75.);
+        for width in [CoefficientWidth::I16, CoefficientWidth::I32] {
+            let dense_options = CompressionOptions {
+                cutoff_level: CutoffLevel::High,
+                coefficient_width: width,
+                ..Default::default()
+            };
+            let runs_options = CompressionOptions {
+                payload_encoding: PayloadEncoding::ZeroRuns,
+                ..dense_options
+            };
+            let dense = compress(&signal, dense_options).unwrap();
+            let runs = compress(&signal, runs_options).unwrap();
+            let header = BiolepticHeader::from_bytes(&runs).unwrap();
+            assert_eq!(
+                header.payload_encoding().unwrap(),
+                PayloadEncoding::ZeroRuns
+            );
+            assert_eq!(decompress(&dense).unwrap(), decompress(&runs).unwrap());
+            println!(
+                "{width:?} dense={} zero_runs={} gain={:.3}",
+                dense.len(),
+                runs.len(),
+                dense.len() as f32 / runs.len() as f32
+            );
+        }
+
+        let mut spike = vec![0f32; 4096];
+        spike[2000] = 1.;
+        let options = CompressionOptions {
+            payload_encoding: PayloadEncoding::ZeroRuns,
+            ..Default::default()
+        };
+        let runs = compress(&spike, options).unwrap();
+        let dense = compress(&spike, CompressionOptions::default()).unwrap();
+        assert_eq!(decompress(&runs).unwrap(), decompress(&dense).unwrap());
+        assert!(runs.len() < dense.len());
+
+        let coefficients = [5, 0, 0, 0, -3, 0, 0];
+        let mut payload = serialize_coefficients(
+            &coefficients,
+            &[4, 2],
+            CoefficientWidth::I16,
+            PayloadEncoding::ZeroRuns,
+        );
+        let decode = |payload: &[u8]| {
+            deserialize_coefficients(
+                payload,
+                1,
+                &[4, 2],
+                CoefficientWidth::I16,
+                PayloadEncoding::ZeroRuns,
+            )
+        };
+        assert_eq!(decode(&payload).unwrap(), coefficients);
+        payload.extend_from_slice(&[0, 0, 1]);
+        assert!(decode(&payload).is_err());
+    }
+
+    #[test]
+    fn test_midpoint_dequant_reduces_bias() {
+        let signal = generate_ppg(20000, 120., 75.);
+        let truncate_options = CompressionOptions {
+            scale: QuantizationScale::S6,
+            ..Default::default()
+        };
+        let midpoint_options = CompressionOptions {
+            dequant_mode: DequantMode::Midpoint,
+            ..truncate_options
+        };
+        let mean_bias = |options: CompressionOptions| {
+            let decoded = decompress(&compress(&signal, options).unwrap()).unwrap();
+            let bias = signal
+                .iter()
+                .zip(decoded.iter())
+                .map(|(&a, &b)| (b - a) as f64)
+                .sum::<f64>()
+                / signal.len() as f64;
+            (bias.abs(), prd(&signal, &decoded))
+        };
+        let (truncate_bias, truncate_prd) = mean_bias(truncate_options);
+        let (midpoint_bias, midpoint_prd) = mean_bias(midpoint_options);
+        println!(
+            "truncate bias={truncate_bias:e} prd={truncate_prd} midpoint bias={midpoint_bias:e} prd={midpoint_prd}"
+        );
+        assert!(midpoint_bias < truncate_bias);
+        assert!(midpoint_prd <= truncate_prd);
+    }
+
+    #[test]
+    fn test_difference_pre_transform_ramp() {
+        let signal = (0..20000)
+            .map(|i| 50. + i as f32 * 0.01 + 0.01 * pseudo_noise(i))
+            .collect::<Vec<f32>>();
+        // Smallest record of each path that reaches the same PRD.
+        let target = 0.01;
+        let smallest = |pre_transform: PreTransform| {
+            (6..=16u8)
+                .map(|raw| {
+                    let options = CompressionOptions {
+                        pre_transform,
+                        scale: QuantizationScale::try_from(raw).unwrap(),
+                        coefficient_width: CoefficientWidth::I32,
+                        ..Default::default()
+                    };
+                    let compressed = compress(&signal, options).unwrap();
+                    let prd = prd(&signal, &decompress(&compressed).unwrap());
+                    (raw, compressed.len(), prd)
+                })
+                .find(|x| x.2 <= target)
+                .unwrap()
+        };
+        let direct = smallest(PreTransform::None);
+        let differenced = smallest(PreTransform::Difference);
+        println!("direct={direct:?} difference={differenced:?}");
+        // The wavelets already cancel a linear trend, while the difference hands the
+        // quantizer the whitened noise and integrates its error back, so the direct path
+        // stays smaller on a noisy ramp.
+        assert!(direct.1 < differenced.1);
+
+        // Without noise the difference is a constant and collapses to almost nothing.
+        let clean = (0..20000)
+            .map(|i| 50. + i as f32 * 0.01)
+            .collect::<Vec<f32>>();
+        let options = CompressionOptions {
+            scale: QuantizationScale::S10,
+            ..Default::default()
+        };
+        let direct = compress(&clean, options).unwrap();
+        let differenced = compress(
+            &clean,
+            CompressionOptions {
+                pre_transform: PreTransform::Difference,
+                ..options
+            },
+        )
+        .unwrap();
+        println!(
+            "clean direct={} difference={}",
+            direct.len(),
+            differenced.len()
         );
+        assert!(prd(&clean, &decompress(&differenced).unwrap()) < target);
+        assert!(differenced.len() < direct.len());
+
+        let constant = vec![3.5f32; 100];
+        let compressed = compress(
+            &constant,
+            CompressionOptions {
+                pre_transform: PreTransform::Difference,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(decompress(&compressed).unwrap(), constant);
     }
 
     #[test]
-    fn test_coding_small() {
-        let r_means = [1., 2., 3., 4., 5., 6.];
-        let encoded = compress(
-            &r_means,
-            CompressionOptions::from_method(CompressionMethod::Cdf53),
+    fn test_quantization_round_trip_all_scales() {
+        let values = [0., 1e-6, -3.7e-4, 0.0123, -0.25, 0.5, 0.999_999, -1.];
+        for raw in 6..=20u8 {
+            let scale = QuantizationScale::try_from(raw).unwrap();
+            assert_eq!(scale.multiplier_f64() * scale.reciprocal_f64(), 1.);
+            for mode in [DequantMode::Truncate, DequantMode::Midpoint] {
+                let dequantize = mode.dequantizer();
+                for &x in values.iter() {
+                    let q = quantize(x, scale.multiplier_f64(), CoefficientWidth::I32);
+                    let restored = dequantize(q, scale.reciprocal_f64());
+                    let bound = scale.reciprocal_f64() as f32 + x.abs() * f32::EPSILON;
+                    assert!(
+                        (restored - x).abs() <= bound,
+                        "scale {raw} {mode:?}: {x} -> {q} -> {restored}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_typical_prd_ppg() {
+        let signal = generate_ppg(100000, 120., 75.);
+        for value in 6..=20u8 {
+            let scale = QuantizationScale::try_from(value).unwrap();
+            let coefficient_width = if scale > QuantizationScale::S12 {
+                CoefficientWidth::I32
+            } else {
+                CoefficientWidth::I16
+            };
+            let options = CompressionOptions {
+                scale,
+                coefficient_width,
+                ..Default::default()
+            };
+            let actual = prd(
+                &signal,
+                &decompress(&compress(&signal, options).unwrap()).unwrap(),
+            );
+            let nominal = scale.typical_prd_ppg();
+            assert!(
+                actual < nominal * 3. && actual > nominal / 3.,
+                "{scale:?}: actual PRD {actual} vs nominal {nominal}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_taper_round_trip() {
+        // A snippet from the trough to the systolic upstroke, so the periodic border sees
+        // a jump of over a thousand units between its last and first sample.
+        let signal = generate_ppg(290, 250., 60.);
+        let options = CompressionOptions {
+            scale: QuantizationScale::S7,
+            ..Default::default()
+        };
+        let plain = decompress(&compress(&signal, options).unwrap()).unwrap();
+        let tapered = decompress(&compress(&signal, options.with_taper(20)).unwrap()).unwrap();
+        let edge_error = |decoded: &[f32]| {
+            let n = signal.len();
+            (0..8)
+                .chain(n - 8..n)
+                .map(|i| (signal[i] - decoded[i]).abs())
+                .sum::<f32>()
+        };
+        let plain_edge = edge_error(&plain);
+        let tapered_edge = edge_error(&tapered);
+        println!("edge error plain={plain_edge} tapered={tapered_edge}");
+        assert!(tapered_edge < plain_edge);
+
+        assert!(compress(&signal, options.with_taper(0)).is_err());
+        assert!(compress(&signal, options.with_taper(101)).is_err());
+    }
+
+    #[test]
+    fn test_bitplane_truncation() {
+        let signal = generate_ppg(20000, 120., 75.);
+        let options = CompressionOptions {
+            payload_encoding: PayloadEncoding::Bitplane,
+            ..Default::default()
+        };
+        let compressed = compress(&signal, options).unwrap();
+        let dense = compress(&signal, CompressionOptions::default()).unwrap();
+        assert_eq!(
+            decompress(&compressed).unwrap(),
+            decompress(&dense).unwrap()
+        );
+
+        let payload_size = compressed.len() - BIOLEPTIC_HEADER_SIZE;
+        let mut previous_prd = 0f64;
+        for fraction in [1.0, 0.8, 0.6, 0.4, 0.2, 0.05] {
+            let cut = BIOLEPTIC_HEADER_SIZE + (payload_size as f64 * fraction) as usize;
+            let decoded = decompress(&compressed[..cut]).unwrap();
+            assert_eq!(decoded.len(), signal.len());
+            let truncated_prd = prd(&signal, &decoded);
+            println!("fraction={fraction} prd={truncated_prd}");
+            assert!(truncated_prd >= previous_prd);
+            previous_prd = truncated_prd;
+        }
+        assert!(decompress(&dense[..dense.len() - 10]).is_err());
+    }
+
+    #[test]
+    fn test_non_finite_counts() {
+        let mut signal = generate_ppg(3000, 120., 75.);
+        for i in [10, 200, 1500] {
+            signal[i] = f32::NAN;
+        }
+        for i in [20, 2999] {
+            signal[i] = f32::INFINITY;
+        }
+        signal[0] = f32::NEG_INFINITY;
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.non_finite_counts(&compressed).unwrap(), (3, 2, 1));
+        assert_eq!(decompress(&compressed).unwrap().len(), signal.len());
+
+        let clean = compress(
+            &generate_ppg(3000, 120., 75.),
+            CompressionOptions::default(),
         )
         .unwrap();
-        println!("{:?}", encoded.len());
+        let header = BiolepticHeader::from_bytes(&clean).unwrap();
+        assert_eq!(header.non_finite_counts(&clean).unwrap(), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_preserve_non_finite() {
+        let mut signal = generate_ppg(3000, 120., 75.);
+        for x in signal[1000..1100].iter_mut() {
+            *x = f32::NAN;
+        }
+        signal[5] = f32::INFINITY;
+        signal[2999] = f32::NEG_INFINITY;
+        let options = CompressionOptions {
+            preserve_nonfinite: true,
+            ..Default::default()
+        };
+        let compressed = compress(&signal, options).unwrap();
+        let decompressed = decompress(&compressed).unwrap();
+        for (&original, &restored) in signal.iter().zip(decompressed.iter()) {
+            if original.is_nan() {
+                assert!(restored.is_nan());
+            } else if original.is_infinite() {
+                assert_eq!(original, restored);
+            } else {
+                assert!(restored.is_finite());
+            }
+        }
+
+        let substituted = compress(&signal, CompressionOptions::default()).unwrap();
+        assert!(
+            decompress(&substituted)
+                .unwrap()
+                .iter()
+                .all(|x| x.is_finite())
+        );
+    }
+
+    #[test]
+    fn test_poly_detrend_baseline_wander() {
+        // ECG-like beats riding on a slow cubic baseline several times their amplitude.
+        let samples = 20000;
+        let signal = (0..samples)
+            .map(|i| {
+                let t = i as f32 / samples as f32;
+                let phase = (i as f32 / 250.).fract();
+                let beat = gaussian(phase, 0.3, 0.012) - 0.15 * gaussian(phase, 0.27, 0.01)
+                    + 0.3 * gaussian(phase, 0.6, 0.05);
+                let baseline = 20. * (4. * t - 9. * t * t + 6. * t * t * t);
+                (beat + baseline + 0.002 * pseudo_noise(i)) * 1000.
+            })
+            .collect::<Vec<f32>>();
+        let direct = compress(
+            &signal,
+            CompressionOptions {
+                scale: QuantizationScale::S11,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let detrended_options = CompressionOptions {
+            scale: QuantizationScale::S7,
+            pre_transform: PreTransform::PolyDetrend(3),
+            ..Default::default()
+        };
+        let detrended = compress(&signal, detrended_options).unwrap();
+        let header = BiolepticHeader::from_bytes(&detrended).unwrap();
+        assert_eq!(
+            header.pre_transform(&detrended).unwrap(),
+            PreTransform::PolyDetrend(3)
+        );
+        let direct_prd = prd(&signal, &decompress(&direct).unwrap());
+        let detrended_prd = prd(&signal, &decompress(&detrended).unwrap());
+        println!(
+            "direct={} prd={direct_prd} detrended={} prd={detrended_prd}",
+            direct.len(),
+            detrended.len()
+        );
+        assert!(detrended_prd <= direct_prd);
+        assert!(detrended.len() < direct.len());
+
+        let options = CompressionOptions {
+            pre_transform: PreTransform::PolyDetrend(4),
+            ..Default::default()
+        };
+        assert!(compress(&signal, options).is_err());
+    }
+
+    #[test]
+    fn test_log_companding_wide_dynamic_range() {
+        // Oscillation whose amplitude sweeps four decades, from 0.01 to 100.
+        let samples = 20000;
+        let signal = (0..samples)
+            .map(|i| {
+                let t = i as f32 / samples as f32;
+                let amplitude = 10f32.powf(4. * t - 2.);
+                amplitude * (i as f32 * 0.05).sin()
+            })
+            .collect::<Vec<f32>>();
+        let linear = compress(&signal, CompressionOptions::default()).unwrap();
+        let log_options = CompressionOptions {
+            pre_transform: PreTransform::Log(6),
+            ..Default::default()
+        };
+        let companded = compress(&signal, log_options).unwrap();
+        let header = BiolepticHeader::from_bytes(&companded).unwrap();
+        assert_eq!(
+            header.pre_transform(&companded).unwrap(),
+            PreTransform::Log(6)
+        );
+
+        let low = samples / 5;
+        let linear_prd = prd(&signal[..low], &decompress(&linear).unwrap()[..low]);
+        let log_prd = prd(&signal[..low], &decompress(&companded).unwrap()[..low]);
+        println!("low amplitude prd linear={linear_prd} log={log_prd}");
+        assert!(log_prd * 10. < linear_prd);
+
+        let scaled = decompress_scaled(&companded, 2., 1.).unwrap();
+        let decoded = decompress(&companded).unwrap();
+        for (&s, &d) in scaled.iter().zip(decoded.iter()) {
+            assert!((s - (d * 2. + 1.)).abs() <= 1e-3 * (1. + d.abs()));
+        }
+
+        let options = CompressionOptions {
+            pre_transform: PreTransform::Log(33),
+            ..Default::default()
+        };
+        assert!(compress(&signal, options).is_err());
+    }
+
+    #[test]
+    fn test_deflate_level() {
+        let signal = generate_ppg(20000, 120., 75.);
+        let fast = compress(
+            &signal,
+            CompressionOptions {
+                deflate_level: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let best = compress(
+            &signal,
+            CompressionOptions {
+                deflate_level: 9,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        println!("fast={} best={}", fast.len(), best.len());
+        assert!(best.len() <= fast.len());
+        assert_eq!(decompress(&fast).unwrap(), decompress(&best).unwrap());
+
+        let options = CompressionOptions {
+            deflate_level: 10,
+            ..Default::default()
+        };
+        assert!(compress(&signal, options).is_err());
+    }
+
+    #[test]
+    fn test_threshold_modes() {
+        let mut hard = vec![-5, -2, -1, 0, 1, 2, 5];
+        let mut soft = hard.clone();
+        let magnitude = threshold_magnitude(QuantizationScale::S11, CutoffLevel::Low);
+        threshold(&mut hard, magnitude, ThresholdMode::Hard);
+        threshold(&mut soft, magnitude, ThresholdMode::Soft);
+        assert_eq!(hard, vec![-5, -2, 0, 0, 0, 2, 5]);
+        assert_eq!(soft, vec![-3, 0, 0, 0, 0, 0, 3]);
+
+        let mut kept = vec![-5, -2, -1, 0, 1, 2, 5];
+        let disabled = threshold_magnitude(QuantizationScale::S11, CutoffLevel::None);
+        assert_eq!(threshold(&mut kept, disabled, ThresholdMode::Hard), 0);
+        assert_eq!(threshold(&mut kept, disabled, ThresholdMode::Soft), 0);
+        assert_eq!(kept, vec![-5, -2, -1, 0, 1, 2, 5]);
+
+        let signal = generate_ppg(5000, 120., 75.);
+        let options = CompressionOptions {
+            threshold_mode: ThresholdMode::Soft,
+            ..Default::default()
+        };
+        let compressed = compress(&signal, options).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.threshold_mode(), ThresholdMode::Soft);
+        let decoded = decompress(&compressed).unwrap();
+        assert!(prd(&signal, &decoded) < 1.);
+
+        let default = compress(&signal, CompressionOptions::default()).unwrap();
+        assert_eq!(
+            BiolepticHeader::from_bytes(&default)
+                .unwrap()
+                .threshold_mode(),
+            ThresholdMode::Hard
+        );
+    }
+
+    #[test]
+    fn test_explicit_levels() {
+        let signal = generate_ppg(5000, 120., 75.);
+        for levels in [1u8, 3, 9] {
+            let options = CompressionOptions {
+                levels: Some(DecompositionLevels::Fixed(levels)),
+                ..Default::default()
+            };
+            let compressed = compress(&signal, options).unwrap();
+            assert_eq!(
+                BiolepticHeader::from_bytes(&compressed).unwrap().levels,
+                levels
+            );
+            assert!(prd(&signal, &decompress(&compressed).unwrap()) < 1.);
+        }
+        for levels in [0u8, 11] {
+            let options = CompressionOptions {
+                levels: Some(DecompositionLevels::Fixed(levels)),
+                ..Default::default()
+            };
+            assert!(compress(&signal, options).is_err());
+        }
+        let options = CompressionOptions {
+            levels: Some(DecompositionLevels::Fixed(8)),
+            ..Default::default()
+        };
+        assert!(compress(&signal[..100], options).is_err());
+    }
+
+    #[test]
+    fn test_auto_levels() {
+        let levels_of = |signal: &[f32], levels| {
+            let options = CompressionOptions {
+                levels,
+                ..Default::default()
+            };
+            let compressed = compress(signal, options).unwrap();
+            assert!(prd(signal, &decompress(&compressed).unwrap()) < 1.);
+            BiolepticHeader::from_bytes(&compressed).unwrap().levels
+        };
+        let trend = (0..8192)
+            .map(|i| (i as f32 * std::f32::consts::TAU / 8192.).sin() * 1000.)
+            .collect::<Vec<f32>>();
+        let oscillating = (0..8192)
+            .map(|i| (i as f32 * 2.7).sin() * 1000.)
+            .collect::<Vec<f32>>();
+
+        let auto = Some(DecompositionLevels::Auto);
+        assert_eq!(levels_of(&trend, auto), levels_of(&trend, None));
+        assert_eq!(levels_of(&oscillating, auto), 1);
+    }
+
+    #[test]
+    fn test_lossless_is_bit_exact() {
+        let options = CompressionOptions {
+            lossless: true,
+            ..CompressionOptions::from_method(CompressionMethod::Cdf53)
+        };
+        for length in [1usize, 7, 333, 5000] {
+            let signal = generate_ppg(length, 250., 72.)
+                .iter()
+                .map(|x| x.round())
+                .collect::<Vec<f32>>();
+            let compressed = compress(&signal, options).unwrap();
+            assert!(
+                BiolepticHeader::from_bytes(&compressed)
+                    .unwrap()
+                    .is_lossless()
+            );
+            assert_eq!(decompress(&compressed).unwrap(), signal);
+        }
+
+        let signal = generate_ppg(1000, 250., 72.);
+        assert!(compress(&signal, options).is_err());
+        let rounded = signal.iter().map(|x| x.round()).collect::<Vec<f32>>();
+        let options = CompressionOptions {
+            lossless: true,
+            ..Default::default()
+        };
+        assert!(compress(&rounded, options).is_err());
+    }
+
+    #[test]
+    fn test_level_scales() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let uniform = compress(&signal, CompressionOptions::default()).unwrap();
+        let same = CompressionOptions {
+            level_scales: Some([QuantizationScale::S11; 11]),
+            ..Default::default()
+        };
+        assert_eq!(
+            decompress(&compress(&signal, same).unwrap()).unwrap(),
+            decompress(&uniform).unwrap()
+        );
+
+        let mut scales = [QuantizationScale::S8; 11];
+        scales[0] = QuantizationScale::S16;
+        let coarse_heavy = CompressionOptions {
+            level_scales: Some(scales),
+            coefficient_width: CoefficientWidth::I32,
+            ..Default::default()
+        };
+        let compressed = compress(&signal, coarse_heavy).unwrap();
+        assert!(prd(&signal, &decompress(&compressed).unwrap()) < 5.);
+        assert!(compressed.len() < uniform.len());
+
+        let narrow = CompressionOptions {
+            level_scales: Some(scales),
+            ..Default::default()
+        };
+        assert!(compress(&signal, narrow).is_err());
+    }
+
+    #[test]
+    fn test_compress_with_stats() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let options = CompressionOptions {
+            cutoff_level: CutoffLevel::High,
+            ..Default::default()
+        };
+        let (compressed, stats) = compress_with_stats(&signal, options).unwrap();
+        assert_eq!(compressed, compress(&signal, options).unwrap());
+        assert_eq!(stats.input_bytes, signal.len() * 4);
+        assert_eq!(stats.output_bytes, compressed.len());
+        assert_eq!(
+            stats.ratio,
+            stats.input_bytes as f64 / stats.output_bytes as f64
+        );
+        assert_eq!(
+            stats.bits_per_sample,
+            (compressed.len() * 8) as f64 / signal.len() as f64
+        );
+        assert_eq!(
+            stats.levels,
+            BiolepticHeader::from_bytes(&compressed).unwrap().levels
+        );
+        assert!(stats.zeroed_coefficients > 0);
+
+        let low = compress_with_stats(&signal, CompressionOptions::default()).unwrap();
+        assert!(low.1.zeroed_coefficients < stats.zeroed_coefficients);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_options_serde() {
+        let options = CompressionOptions {
+            scale: QuantizationScale::S12,
+            pre_transform: PreTransform::PolyDetrend(2),
+            ..CompressionOptions::from_method(CompressionMethod::Db4)
+        };
+        let json = serde_json::to_string(&options).unwrap();
+        assert!(json.contains(r#""method":"db4""#));
+        assert!(json.contains(r#""scale":"s12""#));
+        assert!(json.contains(r#""cutoff_level":"low""#));
+        assert!(json.contains(r#""pre_transform":{"poly_detrend":2}"#));
+        let decoded: CompressionOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, options);
+
+        let compressed = compress(&generate_ppg(1000, 120., 75.), options).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        let json = serde_json::to_string(&header).unwrap();
+        assert!(json.contains(r#""magic":[66,73,76,80]"#));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_quantization_matches_sequential() {
+        let signal = generate_ppg(500_000, 120., 75.);
+        let details = signal.iter().map(|x| x / 1024.).collect::<Vec<f32>>();
+        let options = CompressionOptions {
+            cutoff_level: CutoffLevel::Medium,
+            ..Default::default()
+        };
+
+        // Spin up the pool so its startup isn't timed.
+        let magnitude = threshold_magnitude(options.scale, options.cutoff_level);
+        let _ = quantize_details(&details, options.scale, magnitude, options);
+        let start = std::time::Instant::now();
+        let sequential = quantize_band(&details, options.scale, magnitude, options);
+        let sequential_time = start.elapsed();
+        let start = std::time::Instant::now();
+        let parallel = quantize_details(&details, options.scale, magnitude, options);
+        let parallel_time = start.elapsed();
+        println!("sequential={sequential_time:?} parallel={parallel_time:?}");
+        assert_eq!(parallel, sequential);
+
+        let start = std::time::Instant::now();
+        let compressed = compress(&signal, options).unwrap();
+        println!("compress 500k samples: {:?}", start.elapsed());
+        assert!(crate::decompress(&compressed).is_ok());
+    }
+
+    #[test]
+    fn test_skip_normalization() {
+        let raw = generate_ppg(5000, 120., 75.);
+        let (min, max) = raw
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |a, &x| {
+                (a.0.min(x), a.1.max(x))
+            });
+        let mut signal = raw
+            .iter()
+            .map(|x| (x - min) / (max - min))
+            .collect::<Vec<f32>>();
+        let mean = signal.iter().sum::<f32>() / signal.len() as f32;
+        signal.iter_mut().for_each(|x| *x -= mean);
+
+        let options = CompressionOptions {
+            skip_normalization: true,
+            ..Default::default()
+        };
+        let compressed = compress(&signal, options).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(
+            (header.min_f32(), header.max_f32(), header.mean_f32()),
+            (0., 1., 0.)
+        );
+        let decoded = decompress(&compressed).unwrap();
+        let normalized = decompress(&compress(&signal, Default::default()).unwrap()).unwrap();
+        assert!(prd(&signal, &decoded) < prd(&signal, &normalized) * 1.5);
+
+        let lossless = CompressionOptions {
+            lossless: true,
+            method: CompressionMethod::Cdf53,
+            ..options
+        };
+        assert!(compress(&signal, lossless).is_err());
+    }
+
+    #[test]
+    fn test_constant_signal_round_trip() {
+        for method in [CompressionMethod::Cdf53, CompressionMethod::Cdf97] {
+            let options = CompressionOptions::from_method(method);
+            let constant = vec![42f32; 256];
+            let decoded = decompress(&compress(&constant, options).unwrap()).unwrap();
+            assert_eq!(decoded, constant);
+
+            let nearly = (0..256)
+                .map(|i| -7. + (i % 3) as f32 * 2e-6)
+                .collect::<Vec<f32>>();
+            let decoded = decompress(&compress(&nearly, options).unwrap()).unwrap();
+            for (&x, &y) in nearly.iter().zip(decoded.iter()) {
+                assert!((x - y).abs() <= 2.5e-6, "{x} vs {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_finite_policies() {
+        let data = [
+            f32::NAN,
+            2.,
+            4.,
+            f32::NAN,
+            f32::INFINITY,
+            10.,
+            f32::NEG_INFINITY,
+        ];
+        let substituted = |policy| substitute_non_finite(&data, policy).unwrap();
+        assert_eq!(
+            substituted(NonFinitePolicy::Substitute),
+            vec![0., 2., 4., 0., 1., 10., 0.]
+        );
+        assert_eq!(
+            replace_non_finite(&mut data.clone(), NonFinitePolicy::Zero).unwrap(),
+            (2, 1, 1)
+        );
+        assert_eq!(
+            replace_non_finite(&mut [1., 2.], NonFinitePolicy::Error).unwrap(),
+            (0, 0, 0)
+        );
+        assert_eq!(
+            substituted(NonFinitePolicy::Zero),
+            vec![0., 2., 4., 0., 0., 10., 0.]
+        );
+        assert_eq!(
+            substituted(NonFinitePolicy::ClampToRange),
+            vec![6., 2., 4., 6., 10., 10., 2.]
+        );
+        assert_eq!(
+            substituted(NonFinitePolicy::Interpolate),
+            vec![2., 2., 4., 6., 8., 10., 10.]
+        );
+        assert!(substitute_non_finite(&data, NonFinitePolicy::Error).is_err());
+        assert_eq!(
+            substitute_non_finite(&[f32::NAN; 3], NonFinitePolicy::Interpolate).unwrap(),
+            vec![0.; 3]
+        );
+
+        let mut signal = generate_ppg(5000, 120., 75.)
+            .iter()
+            .map(|x| x + 5000.)
+            .collect::<Vec<f32>>();
+        signal[1000..1100].fill(f32::NAN);
+        let options = CompressionOptions {
+            non_finite: NonFinitePolicy::Interpolate,
+            ..Default::default()
+        };
+        let decoded = decompress(&compress(&signal, options).unwrap()).unwrap();
+        let default = decompress(&compress(&signal, Default::default()).unwrap()).unwrap();
+        let finite_error = |decoded: &[f32]| {
+            signal
+                .iter()
+                .zip(decoded.iter())
+                .filter(|x| x.0.is_finite())
+                .fold(0f32, |acc, (&x, &y)| acc.max((x - y).abs()))
+        };
+        println!(
+            "interpolated={} substituted={}",
+            finite_error(&decoded),
+            finite_error(&default)
+        );
+        assert!(finite_error(&decoded) * 2. < finite_error(&default));
+        assert!(
+            compress(
+                &signal,
+                CompressionOptions {
+                    non_finite: NonFinitePolicy::Error,
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_metadata() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let options = CompressionOptions::default().with_envelope(64);
+        let metadata = "device=PX-42;lead=II;t=2026-01-12T08:30:00Z".as_bytes();
+        let compressed = compress_with_metadata(&signal, options, metadata).unwrap();
+        assert_eq!(
+            crate::read_metadata(&compressed).unwrap().as_deref(),
+            Some(metadata)
+        );
+        assert_eq!(
+            decompress(&compressed).unwrap(),
+            decompress(&compress(&signal, options).unwrap()).unwrap()
+        );
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert!(header.envelope(&compressed).unwrap().is_some());
+
+        let plain = compress(&signal, options).unwrap();
+        assert_eq!(crate::read_metadata(&plain).unwrap(), None);
+    }
+
+    #[test]
+    fn test_to_coefficients() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let options = CompressionOptions {
+            pre_transform: PreTransform::PolyDetrend(1),
+            ..Default::default()
+        };
+        let (record, coefficients) = to_coefficients(&signal, options).unwrap();
+        let header = crate::read_header(&record).unwrap();
+        assert_eq!({ header.compressed_size }, 0);
+        assert_eq!(
+            coefficients.len(),
+            header.coefficient_layout().unwrap().total()
+        );
+        assert!(
+            coefficients
+                .iter()
+                .all(|&x| x == x.clamp(i16::MIN as i32, i16::MAX as i32))
+        );
+        assert_eq!(
+            crate::from_coefficients(&record, &coefficients).unwrap(),
+            decompress(&compress(&signal, options).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_options_builder() {
+        let built = CompressionOptions::builder()
+            .method(CompressionMethod::Cdf53)
+            .scale(QuantizationScale::S9)
+            .cutoff_level(CutoffLevel::High)
+            .levels(DecompositionLevels::Fixed(4))
+            .sample_rate(250.)
+            .build();
+        let expected = CompressionOptions {
+            method: CompressionMethod::Cdf53,
+            scale: QuantizationScale::S9,
+            cutoff_level: CutoffLevel::High,
+            levels: Some(DecompositionLevels::Fixed(4)),
+            sample_rate: Some(250.),
+            ..Default::default()
+        };
+        assert_eq!(built, expected);
+        assert_eq!(
+            CompressionOptions::builder().build(),
+            CompressionOptions::default()
+        );
+    }
+
+    #[test]
+    fn test_compress_from_containers() {
+        let signal = generate_ppg(2000, 120., 75.);
+        let options = CompressionOptions::default();
+        let expected = compress(&signal, options).unwrap();
+        assert_eq!(compress_from(&signal, options).unwrap(), expected);
+        assert_eq!(
+            compress_from(signal.clone().into_boxed_slice(), options).unwrap(),
+            expected
+        );
+        assert_eq!(compress_from(signal, options).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_compress_in_place() {
+        let mut signal = generate_ppg(5000, 120., 75.);
+        signal[100] = f32::NAN;
+        signal[200] = f32::INFINITY;
+        for options in [
+            CompressionOptions::default(),
+            CompressionOptions {
+                non_finite: NonFinitePolicy::Interpolate,
+                preserve_nonfinite: true,
+                ..Default::default()
+            },
+        ] {
+            let expected = compress(&signal, options).unwrap();
+            let mut scratch = signal.clone();
+            assert_eq!(compress_in_place(&mut scratch, options).unwrap(), expected);
+            assert!(scratch.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_max_compressed_size() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let noise = (0..5000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 8) as f32)
+            .collect::<Vec<f32>>();
+        let mut with_nan = noise.clone();
+        with_nan[10] = f32::NAN;
+        let all_options = [
+            CompressionOptions::default(),
+            CompressionOptions {
+                coefficient_width: CoefficientWidth::I32,
+                scale: QuantizationScale::S20,
+                cutoff_level: CutoffLevel::Low,
+                ..Default::default()
+            },
+            CompressionOptions {
+                payload_encoding: PayloadEncoding::Bitplane,
+                entropy: EntropyCoder::Raw,
+                preserve_nonfinite: true,
+                pre_transform: PreTransform::PolyDetrend(3),
+                reserved: Some([7; 16]),
+                ..Default::default()
+            }
+            .with_envelope(16)
+            .with_taper(10),
+            CompressionOptions {
+                method: CompressionMethod::Cdf53,
+                lossless: true,
+                ..Default::default()
+            },
+        ];
+        for options in all_options {
+            for data in [&signal, &noise, &with_nan] {
+                let data = if options.lossless {
+                    data.iter().map(|x| x.round() % 1e6).collect::<Vec<f32>>()
+                } else {
+                    data.to_vec()
+                };
+                let compressed = compress(&data, options).unwrap();
+                let bound = max_compressed_size(data.len(), &options);
+                assert!(compressed.len() <= bound, "{} > {bound}", compressed.len());
+
+                let mut out = b"prefix".to_vec();
+                compress_into(&data, options, &mut out).unwrap();
+                assert_eq!(&out[..6], b"prefix");
+                assert_eq!(&out[6..], &compressed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_adaptive_threshold() {
+        let clean = generate_ppg(20000, 120., 75.);
+        let mut state = 0x2545f491u32;
+        let noisy = clean
+            .iter()
+            .map(|&x| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                x + (state as f32 / u32::MAX as f32 - 0.5) * 4.
+            })
+            .collect::<Vec<f32>>();
+
+        let fixed = CompressionOptions::default();
+        let adaptive = CompressionOptions {
+            threshold_strategy: ThresholdStrategy::Adaptive,
+            ..fixed
+        };
+        let fixed_compressed = compress(&noisy, fixed).unwrap();
+        let adaptive_compressed = compress(&noisy, adaptive).unwrap();
+        let decoded = decompress(&adaptive_compressed).unwrap();
+        println!(
+            "fixed={} adaptive={} prd={}",
+            fixed_compressed.len(),
+            adaptive_compressed.len(),
+            prd(&clean, &decoded)
+        );
+        assert!(adaptive_compressed.len() < fixed_compressed.len());
+        assert!(prd(&clean, &decoded) < 1.);
+    }
+
+    #[test]
+    fn test_entropy_coders_decode_identically() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let deflated = compress(&signal, CompressionOptions::default()).unwrap();
+        let expected = decompress(&deflated).unwrap();
+        let mut coders = vec![EntropyCoder::Raw];
+        if cfg!(feature = "lz4") {
+            coders.push(EntropyCoder::Lz4);
+        }
+        for entropy in coders {
+            let options = CompressionOptions {
+                entropy,
+                ..Default::default()
+            };
+            let compressed = compress(&signal, options).unwrap();
+            let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+            assert_eq!(header.entropy_coder().unwrap(), entropy);
+            assert_eq!(decompress(&compressed).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_coding_all_zero() {
+        let zeros = vec![0f32; 100000];
+        let encoded = compress(&zeros, CompressionOptions::default()).unwrap();
+        assert_eq!(encoded.len(), crate::BIOLEPTIC_HEADER_SIZE);
+        let header = BiolepticHeader::from_bytes(&encoded).unwrap();
+        assert!(header.is_zero_signal());
         let decompressed = decompress(&encoded).unwrap();
-        println!("{:?}", decompressed.len());
-        assert_eq!(decompressed.len(), r_means.len());
+        assert_eq!(decompressed, zeros);
     }
 }
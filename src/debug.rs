@@ -0,0 +1,87 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::compressor::{encode_coefficients, write_stream};
+use crate::decompressor::decompress_coefficients;
+use crate::{BiolepticError, CompressionOptions};
+
+/// Runs only the forward DWT, quantization, dequantization and inverse DWT, skipping
+/// entropy coding and header framing, and returns the reconstruction.
+///
+/// The quantized coefficients are handed straight to the regular decode path, the same one
+/// [`crate::decompress_coefficients`] uses, so every inverse step including pre-transforms,
+/// taper and non-finite restoration runs as in a full [`crate::compress`]/[`crate::decompress`]
+/// round trip and the output matches it. Isolates the transform and quantization error
+/// from the serialization path.
+pub fn debug_roundtrip_transform(
+    data: &[f32],
+    options: CompressionOptions,
+) -> Result<Vec<f32>, BiolepticError> {
+    let encoded = encode_coefficients(data, options)?;
+    // A record with an empty payload carries the header and extensions for the decoder.
+    let record = write_stream(encoded.header, &[], encoded.extensions);
+    decompress_coefficients(&record, &encoded.coefficients)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::{CompressionMethod, PreTransform, compress, decompress};
+
+    #[test]
+    fn test_transform_matches_full_round_trip() {
+        let signal = generate_ppg(20000, 120., 75.);
+        for method in [CompressionMethod::Cdf53, CompressionMethod::Cdf97] {
+            let options = CompressionOptions::from_method(method);
+            let transformed = debug_roundtrip_transform(&signal, options).unwrap();
+            let decoded = decompress(&compress(&signal, options).unwrap()).unwrap();
+            assert_eq!(transformed, decoded);
+        }
+
+        let mut signal = signal;
+        signal[100] = f32::NAN;
+        let options = CompressionOptions {
+            pre_transform: PreTransform::Difference,
+            preserve_nonfinite: true,
+            ..CompressionOptions::default().with_taper(10)
+        };
+        let transformed = debug_roundtrip_transform(&signal, options).unwrap();
+        let decoded = decompress(&compress(&signal, options).unwrap()).unwrap();
+        assert_eq!(
+            transformed
+                .iter()
+                .map(|x| x.to_bits())
+                .collect::<Vec<u32>>(),
+            decoded.iter().map(|x| x.to_bits()).collect::<Vec<u32>>()
+        );
+
+        let flat = debug_roundtrip_transform(&[0f32; 64], CompressionOptions::default());
+        assert_eq!(flat.unwrap(), vec![0f32; 64]);
+    }
+}
@@ -26,21 +26,690 @@
  * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
  * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
+use crate::compressor::{PayloadEncoding, PreTransform, QuantizationScale};
+use crate::entropy::entropy_decode;
+use crate::extension::{
+    FLOAT64_OFFSET_TAG, LEVEL_SCALES_TAG, NON_FINITE_MAP_TAG, RESIDUAL_TAG, apply_residual,
+    decode_float64_offset, decode_level_scales, decode_residual, find_extension, record_length,
+    restore_non_finite,
+};
+use crate::lossless::inverse_lossless;
 use crate::mla::fmla;
-use crate::{BIOLEPTIC_HEADER_SIZE, BiolepticError, BiolepticHeader, CompressionMethod};
-use flate2::read::DeflateDecoder;
-use osclet::{BorderMode, DaubechiesFamily, DwtSize, MultiLevelDwtRef, Osclet, SymletFamily};
-use std::io::Read;
+use crate::payload::{deserialize_coefficients, max_payload_size};
+use crate::pretransform::{
+    DIFFERENCE_TAG, LOG_TAG, POLY_TAG, TAPER_TAG, add_polynomial, decode_initial_value,
+    decode_log_factor, decode_polynomial, decode_taper, integrate, log_expand, remove_taper,
+};
+use crate::worker::make_dwt_worker;
+use crate::{BIOLEPTIC_HEADER_SIZE, BiolepticError, BiolepticHeader, CoefficientLayout, DataType};
+use osclet::{DwtSize, MultiLevelDwtRef};
 
 /// Decompresses a Bioleptic-encoded byte slice back into `f32` samples.
 ///
 /// Reads and validates the header, entropy-decodes the payload with deflate,
 /// dequantizes coefficients, reconstructs the signal via inverse multi-level
 /// DWT, then reverses the mean-centering and range normalization applied
-/// during compression.
+/// during compression. Streams written by [`crate::compress_f64`] are rounded to `f32`,
+/// use [`decompress_f64`] to keep their precision.
 pub fn decompress(bytes: &[u8]) -> Result<Vec<f32>, BiolepticError> {
+    let mut out = Vec::new();
+    decompress_into(bytes, &mut out)?;
+    Ok(out)
+}
+
+/// Decompresses like [`decompress`] into `out`, replacing its contents.
+///
+/// The samples are written into the existing allocation when it is large enough, so a
+/// buffer reused across calls stops allocating for the output. Size it up front with
+/// [`decompressed_len`]. Intermediate decode buffers are still allocated per call.
+pub fn decompress_into(bytes: &[u8], out: &mut Vec<f32>) -> Result<(), BiolepticError> {
+    decompress_scaled_into(bytes, None, 1., 0., out)
+}
+
+/// Decompresses a stream whose payload was entropy-decoded by the caller.
+///
+/// `coefficients` are the quantized coefficients in payload order, the approximation
+/// followed by the detail levels from the finest, as sized by
+/// [`BiolepticHeader::coefficient_layout`]. Only the header and the extension section of
+/// `bytes` are read, the payload itself is skipped, so targets without an inflate
+/// implementation can plug in their own entropy decoder.
+pub fn decompress_coefficients(
+    bytes: &[u8],
+    coefficients: &[i32],
+) -> Result<Vec<f32>, BiolepticError> {
+    let mut out = Vec::new();
+    decompress_scaled_into(bytes, Some(coefficients), 1., 0., &mut out)?;
+    Ok(out)
+}
+
+/// Reconstructs the signal from the record and coefficients returned by
+/// [`crate::to_coefficients`], the inverse of that function.
+///
+/// Takes the whole record rather than a [`BiolepticHeader`]: pre-transforms, tapers and
+/// per-band scales keep their parameters in the extension section after the header, so
+/// the header alone can't undo them. Same as [`decompress_coefficients`].
+pub fn from_coefficients(record: &[u8], coefficients: &[i32]) -> Result<Vec<f32>, BiolepticError> {
+    decompress_coefficients(record, coefficients)
+}
+
+/// Decompresses a smoothed, full-length preview of the signal from its approximation band
+/// alone, e.g. for thumbnails.
+///
+/// Every detail band is treated as zero, so the result is a low-pass version of the
+/// signal without its sharp features and noise, not the exact reconstruction. Only the
+/// approximation band is dequantized and the inverse DWT runs over zero detail bands, but
+/// the payload is still inflated in full since its entropy coding spans every band.
+/// Streams using a pre-transform, a taper, lossless coding or a non-finite map are
+/// decoded through the regular path with their detail bands zeroed. Residual corrections
+/// of [`crate::compress_with_max_error`] are skipped, they only refine exact samples.
+pub fn decompress_approximation(bytes: &[u8]) -> Result<Vec<f32>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    let mut signal = Vec::new();
+    if header.is_zero_signal() || header.channels != 1 {
+        decode_signal(bytes, &header, None, 1., 0., &mut signal)?;
+    } else if is_plain_transform(bytes, &header)?
+        && find_extension(bytes, &header, NON_FINITE_MAP_TAG)?.is_none()
+    {
+        let quantized = decode_quantized(bytes, &header)?;
+        let expected_length = header.coefficient_layout()?.total();
+        if quantized.len() != expected_length {
+            return Err(BiolepticError::DecompressionError(format!(
+                "Expected {expected_length} coefficients, but payload holds {}",
+                quantized.len()
+            )));
+        }
+        let band_scales = band_scales(&header, find_extension(bytes, &header, LEVEL_SCALES_TAG)?)?;
+        let levels_length = level_sizes(&header)?;
+        let approximation_length = levels_length[header.levels as usize - 1].approx_length;
+        let rcp_scale = QuantizationScale::try_from(band_scales[0])?.reciprocal_f64();
+        let dequantize = header.dequant_mode().dequantizer();
+        let approximations = quantized[..approximation_length]
+            .iter()
+            .map(|&x| dequantize(x, rcp_scale))
+            .collect::<Vec<f32>>();
+        // The finest band is the longest, every other one is a prefix of the same zeros.
+        let zeros = vec![0f32; levels_length[0].details_length];
+
+        let dwt_worker = make_dwt_worker!(header.compression_method()?, header.border_mode());
+        let iwdt = dwt_worker
+            .multi_idwt(&MultiLevelDwtRef {
+                approximations: &approximations,
+                details: levels_length
+                    .iter()
+                    .map(|x| &zeros[..x.details_length])
+                    .collect(),
+            })
+            .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?;
+        denormalize(&header, iwdt, 1., 0., &mut signal);
+    } else {
+        let mut quantized = decode_quantized(bytes, &header)?;
+        let approximation_length = header.coefficient_layout()?.approximation_length;
+        quantized
+            .get_mut(approximation_length..)
+            .unwrap_or_default()
+            .fill(0);
+        decode_signal(bytes, &header, Some(&quantized), 1., 0., &mut signal)?;
+    }
+    if let Some(mean) = float64_offset(bytes, &header)? {
+        for v in signal.iter_mut() {
+            *v = (*v as f64 + mean) as f32;
+        }
+    }
+    Ok(signal)
+}
+
+/// Decompresses the signal at a reduced resolution by stopping the inverse DWT
+/// `max_levels_dropped` levels early, e.g. to draw a long recording on a narrow screen.
+///
+/// With `k = min(max_levels_dropped, levels)` the result holds exactly
+/// `ceil(signal_length / 2^k)` samples, each one a low-pass estimate of `2^k` consecutive
+/// samples, and `k = 0` matches [`decompress`]. The finest `k` detail bands are never
+/// transformed, though the payload is still inflated in full. Streams using a
+/// pre-transform, a taper or lossless coding are decoded in full and averaged over blocks
+/// of `2^k` samples instead, as are transforms whose levels keep boundary-extension
+/// coefficients, since their coarse samples don't line up with the blocks. Residual corrections of [`crate::compress_with_max_error`]
+/// and restored non-finite samples are skipped on the fast path.
+pub fn decompress_downsampled(
+    bytes: &[u8],
+    max_levels_dropped: u8,
+) -> Result<Vec<f32>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    let dropped = (max_levels_dropped as usize).min(header.levels as usize);
+    if dropped == 0 {
+        return decompress(bytes);
+    }
+    let signal_length = header.signal_length as usize;
+    let output_length = signal_length.div_ceil(1 << dropped);
+
+    let direct = header.channels == 1
+        && !header.is_zero_signal()
+        && is_plain_transform(bytes, &header)?
+        && halves_levels(&header, dropped)?;
+    let mut signal = if direct {
+        let quantized = decode_quantized(bytes, &header)?;
+        let expected_length = header.coefficient_layout()?.total();
+        if quantized.len() != expected_length {
+            return Err(BiolepticError::DecompressionError(format!(
+                "Expected {expected_length} coefficients, but payload holds {}",
+                quantized.len()
+            )));
+        }
+        let band_scales = band_scales(&header, find_extension(bytes, &header, LEVEL_SCALES_TAG)?)?;
+        let (approximations, details) = dequantize_bands(&header, &band_scales, &quantized)?;
+
+        let dwt_worker = make_dwt_worker!(header.compression_method()?, header.border_mode());
+        let mut coarse = if dropped == details.len() {
+            approximations
+        } else {
+            dwt_worker
+                .multi_idwt(&MultiLevelDwtRef {
+                    approximations: &approximations,
+                    details: details[dropped..].iter().map(|x| x.as_slice()).collect(),
+                })
+                .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?
+        };
+
+        // Every analysis level scales a constant by the DC gain of the low-pass filter,
+        // measured on the worker itself so it holds for any filter normalization.
+        let probe = vec![1f32; dwt_worker.filter_length().max(2) * 8];
+        let level_gain = dwt_worker
+            .dwt(&probe)
+            .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?
+            .approximations[probe.len() / 4];
+        let rcp_gain = 1. / level_gain.powi(dropped as i32);
+
+        let range = header.max_f32() - header.min_f32();
+        let v_min = header.min_f32();
+        let v_mean = header.mean_f32();
+        if coarse.len() < output_length {
+            return Err(BiolepticError::DecompressionError(format!(
+                "Level {dropped} holds {} samples, but {output_length} are needed",
+                coarse.len()
+            )));
+        }
+        // Only the wrap extension of a signal shorter than the filter lies past the end.
+        coarse.truncate(output_length);
+        for v in coarse.iter_mut() {
+            *v = fmla(*v * rcp_gain + v_mean, range, v_min);
+        }
+        coarse
+    } else {
+        let full = decompress(bytes)?;
+        full.chunks(1 << dropped)
+            .map(|x| x.iter().sum::<f32>() / x.len() as f32)
+            .collect()
+    };
+
+    if direct && let Some(mean) = float64_offset(bytes, &header)? {
+        for v in signal.iter_mut() {
+            *v = (*v as f64 + mean) as f32;
+        }
+    }
+    Ok(signal)
+}
+
+/// Whether each of the finest `levels` DWT levels holds half of the previous one rounded
+/// up, so approximation sample `i` of level `levels` starts at input sample `i << levels`.
+fn halves_levels(header: &BiolepticHeader, levels: usize) -> Result<bool, BiolepticError> {
+    check_signal_length(header)?;
+    let dwt_worker = make_dwt_worker!(header.compression_method()?, header.border_mode());
+    let mut length = (header.signal_length as usize).max(dwt_worker.filter_length());
+    for _ in 0..levels {
+        let size = dwt_worker.dwt_size(length);
+        if size.approx_length != length.div_ceil(2) {
+            return Ok(false);
+        }
+        length = size.approx_length;
+    }
+    Ok(true)
+}
+
+/// Whether the samples are the inverse DWT of the dequantized coefficients up to the
+/// header's normalization, with no pre-transform, taper or lossless coding on top.
+fn is_plain_transform(bytes: &[u8], header: &BiolepticHeader) -> Result<bool, BiolepticError> {
+    Ok(!header.is_lossless()
+        && !header.is_delta()
+        && header.pre_transform(bytes)? == PreTransform::None
+        && find_extension(bytes, header, TAPER_TAG)?.is_none())
+}
+
+/// Quantized coefficients of a stream held in one buffer, with the DWT bands exposed as
+/// slices into it. Returned by [`decode_coefficients_borrowed`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CoeffView {
+    coefficients: Vec<i32>,
+    layout: CoefficientLayout,
+}
+
+impl CoeffView {
+    /// All coefficients in payload order, the approximation followed by the detail levels
+    /// from the finest.
+    pub fn as_slice(&self) -> &[i32] {
+        &self.coefficients
+    }
+
+    /// The approximation band, the coarsest low-pass coefficients.
+    pub fn approximation(&self) -> &[i32] {
+        &self.coefficients[..self.layout.approximation_length]
+    }
+
+    /// Detail band `level`, counted from the finest, or `None` past the deepest level.
+    pub fn detail(&self, level: usize) -> Option<&[i32]> {
+        let length = *self.layout.detail_lengths.get(level)?;
+        let start = self.layout.approximation_length
+            + self.layout.detail_lengths[..level].iter().sum::<usize>();
+        Some(&self.coefficients[start..start + length])
+    }
+
+    /// Number of detail levels.
+    pub fn levels(&self) -> usize {
+        self.layout.detail_lengths.len()
+    }
+
+    /// Sizes of the bands.
+    pub fn layout(&self) -> &CoefficientLayout {
+        &self.layout
+    }
+}
+
+/// Decodes the quantized coefficients of a stream into a single buffer without
+/// dequantizing or transforming them, e.g. to scan coefficient magnitudes.
+///
+/// Only the entropy decoding allocates, the bands are sliced out of its output by
+/// [`CoeffView`]. All-zero signals report zero coefficients, lossless streams their
+/// integer lifting coefficients.
+pub fn decode_coefficients_borrowed(bytes: &[u8]) -> Result<CoeffView, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    if header.channels != 1 {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Stream holds {} channels, decode it with decompress_multichannel",
+            { header.channels }
+        )));
+    }
+    check_signal_length(&header)?;
+    let layout = header.coefficient_layout()?;
+    let coefficients = if header.is_zero_signal() {
+        vec![0; layout.total()]
+    } else {
+        decode_quantized(bytes, &header)?
+    };
+    if coefficients.len() != layout.total() {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Expected {} coefficients, but payload holds {}",
+            layout.total(),
+            coefficients.len()
+        )));
+    }
+    Ok(CoeffView {
+        coefficients,
+        layout,
+    })
+}
+
+/// Returns the energy, the sum of squared dequantized coefficients, of every DWT band
+/// without running the inverse transform, e.g. as a cheap spectral summary.
+///
+/// Entry 0 is the approximation band and entry `i` the `i`-th detail level counted from
+/// the finest, so higher entries cover successively lower frequency bands. Energies are in
+/// squared signal units; the approximation is taken of the mean-centered signal, so it
+/// excludes the DC level. Lossless streams report the raw lifting coefficients.
+pub fn band_energies(bytes: &[u8]) -> Result<Vec<f64>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    if header.channels != 1 {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Stream holds {} channels, decode it with decompress_multichannel",
+            { header.channels }
+        )));
+    }
+    check_signal_length(&header)?;
+    let bands = header.levels as usize + 1;
+    if header.is_zero_signal() {
+        return Ok(vec![0.; bands]);
+    }
+
+    let layout = header.coefficient_layout()?;
+    let quantized = decode_quantized(bytes, &header)?;
+    if quantized.len() != layout.total() {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Expected {} coefficients, but payload holds {}",
+            layout.total(),
+            quantized.len()
+        )));
+    }
+
+    let (rcp_scales, range) = if header.is_lossless() {
+        (vec![1.; bands], 1.)
+    } else {
+        let uniform_scales = vec![header.scale; bands];
+        let band_scales = match find_extension(bytes, &header, LEVEL_SCALES_TAG)? {
+            Some(scales) => decode_level_scales(scales, bands - 1)?,
+            None => uniform_scales.as_slice(),
+        };
+        let rcp_scales = band_scales
+            .iter()
+            .map(|&scale| QuantizationScale::try_from(scale).map(|x| x.reciprocal_f64()))
+            .collect::<Result<Vec<f64>, BiolepticError>>()?;
+        (rcp_scales, (header.max_f32() - header.min_f32()) as f64)
+    };
+    let dequantize = header.dequant_mode().dequantizer();
+
+    let mut energies = Vec::with_capacity(bands);
+    let mut start = 0usize;
+    let lengths = std::iter::once(layout.approximation_length).chain(layout.detail_lengths);
+    for (length, &rcp_scale) in lengths.zip(rcp_scales.iter()) {
+        let energy = quantized[start..start + length]
+            .iter()
+            .map(|&x| {
+                let v = if header.is_lossless() {
+                    x as f64
+                } else {
+                    dequantize(x, rcp_scale) as f64 * range
+                };
+                v * v
+            })
+            .sum::<f64>();
+        energies.push(energy);
+        start += length;
+    }
+    Ok(energies)
+}
+
+/// Returns the number of samples [`decompress`] yields, reading only the header.
+pub fn decompressed_len(bytes: &[u8]) -> Result<usize, BiolepticError> {
+    Ok(BiolepticHeader::from_bytes(bytes)?.signal_length as usize)
+}
+
+/// Decompresses like [`decompress`] and maps every sample to `sample * gain + offset`,
+/// e.g. to convert raw ADC counts into physical units.
+///
+/// The affine map is folded into the final denormalization, so it costs no extra pass.
+pub fn decompress_scaled(bytes: &[u8], gain: f32, offset: f32) -> Result<Vec<f32>, BiolepticError> {
+    let mut out = Vec::new();
+    decompress_scaled_into(bytes, None, gain, offset, &mut out)?;
+    Ok(out)
+}
+
+fn decompress_scaled_into(
+    bytes: &[u8],
+    quantized: Option<&[i32]>,
+    gain: f32,
+    offset: f32,
+    out: &mut Vec<f32>,
+) -> Result<(), BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    if header.data_type()? == DataType::Int16 {
+        return Err(BiolepticError::DecompressionError(
+            "Stream holds Int16 samples, decode it with decompress_i16".to_string(),
+        ));
+    }
+    decode_samples(bytes, &header, quantized, gain, offset, out)
+}
+
+/// Decodes the samples of a single-channel stream of any data type as `f32`, residual
+/// corrections and a `Float64` mean included. For checks that only compare samples, such
+/// as validation and equivalence, which would otherwise have to special-case `Int16`.
+pub(crate) fn decode_samples(
+    bytes: &[u8],
+    header: &BiolepticHeader,
+    quantized: Option<&[i32]>,
+    gain: f32,
+    offset: f32,
+    out: &mut Vec<f32>,
+) -> Result<(), BiolepticError> {
+    decode_signal(bytes, header, quantized, gain, offset, out)?;
+    if let Some(residual) = find_extension(bytes, header, RESIDUAL_TAG)? {
+        let (step, corrections) = decode_residual(residual, out.len())?;
+        apply_residual(out, step, &corrections, gain);
+    }
+    // A Float64 residual is mapped like any signal, so only the gain applies to its mean.
+    if let Some(mean) = float64_offset(bytes, header)? {
+        let shift = mean * gain as f64;
+        for v in out.iter_mut() {
+            *v = (*v as f64 + shift) as f32;
+        }
+    }
+    Ok(())
+}
+
+/// Decompresses a stream written by [`crate::compress_f64`] back into `f64` samples.
+///
+/// Fails on streams of any other data type, use [`decompress`] for `Float32` and
+/// [`decompress_i16`] for `Int16` ones.
+pub fn decompress_f64(bytes: &[u8]) -> Result<Vec<f64>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    let Some(mean) = float64_offset(bytes, &header)? else {
+        let data_type = header.data_type()?;
+        let decoder = if data_type == DataType::Int16 {
+            "decompress_i16"
+        } else {
+            "decompress"
+        };
+        return Err(BiolepticError::DecompressionError(format!(
+            "Stream holds {data_type:?} samples, decode it with {decoder}"
+        )));
+    };
+    let mut residual = Vec::new();
+    decode_signal(bytes, &header, None, 1., 0., &mut residual)?;
+    Ok(residual.iter().map(|&x| x as f64 + mean).collect())
+}
+
+/// Decompresses a stream written by [`crate::compress_i16`] back into `i16` samples.
+///
+/// Samples are rounded to the nearest integer and saturated to the `i16` range, so lossless
+/// streams come back bit-exact. Fails on streams of any other data type.
+pub fn decompress_i16(bytes: &[u8]) -> Result<Vec<i16>, BiolepticError> {
     let header = BiolepticHeader::from_bytes(bytes)?;
+    let data_type = header.data_type()?;
+    if data_type != DataType::Int16 {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Stream holds {data_type:?} samples, decode it with decompress"
+        )));
+    }
+    let mut signal = Vec::new();
+    decode_signal(bytes, &header, None, 1., 0., &mut signal)?;
+    Ok(signal
+        .iter()
+        .map(|&x| x.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+        .collect())
+}
+
+/// Decompresses like [`decompress`] and rounds every sample to `half::f16`.
+///
+/// The inverse transform still runs in `f32`, but the final denormalization loop writes
+/// its samples straight as `f16`, so no `f32` copy of the output is made. Streams that
+/// need further passes after it, a pre-transform, a taper, restored non-finite samples,
+/// residual corrections or a `Float64` mean, finish in `f32` and are cast afterwards.
+/// `f16` keeps 11 significant bits, adding a
+/// relative error of up to `2^-11` (about 0.05%) on top of the codec's own, and its range
+/// ends at `±65504`: larger samples become infinite, samples below `6.1e-5` in magnitude
+/// lose precision as subnormals. Scale such signals with [`decompress_scaled`] first.
+#[cfg(feature = "half")]
+pub fn decompress_f16(bytes: &[u8]) -> Result<Vec<half::f16>, BiolepticError> {
+    decompress_cast(bytes)
+}
 
+/// Decompresses like [`decompress`] and rounds every sample to `half::bf16`, written the
+/// same way as by [`decompress_f16`].
+///
+/// `bf16` has the range of `f32` but keeps only 8 significant bits, a relative error of
+/// up to `2^-8` (about 0.4%), which usually exceeds the codec's own error.
+#[cfg(feature = "half")]
+pub fn decompress_bf16(bytes: &[u8]) -> Result<Vec<half::bf16>, BiolepticError> {
+    decompress_cast(bytes)
+}
+
+/// Decodes like [`decompress`] into `T`, casting in the final denormalization loop when
+/// nothing runs after it.
+#[cfg(feature = "half")]
+fn decompress_cast<T: DecodedSample>(bytes: &[u8]) -> Result<Vec<T>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    let direct = header.data_type()? == DataType::Float32
+        && !header.is_zero_signal()
+        && (header.is_lossless() || is_plain_transform(bytes, &header)?)
+        && find_extension(bytes, &header, NON_FINITE_MAP_TAG)?.is_none()
+        && find_extension(bytes, &header, RESIDUAL_TAG)?.is_none();
+    if !direct {
+        return Ok(decompress(bytes)?.iter().map(|&x| T::from_f32(x)).collect());
+    }
+    check_decodable(&header)?;
+    let quantized = decode_quantized(bytes, &header)?;
+    let level_scales = find_extension(bytes, &header, LEVEL_SCALES_TAG)?;
+    let mut signal = Vec::new();
+    reconstruct(&header, level_scales, &quantized, 1., 0., &mut signal)?;
+    Ok(signal)
+}
+
+/// Sample types the final denormalization loop writes directly.
+pub(crate) trait DecodedSample: Sized {
+    fn from_f32(v: f32) -> Self;
+
+    /// Replaces the contents of `out` with `map` applied to every sample of `buffer`.
+    fn collect_mapped(buffer: Vec<f32>, map: impl Fn(f32) -> f32, out: &mut Vec<Self>) {
+        out.clear();
+        out.extend(buffer.iter().map(|&v| Self::from_f32(map(v))));
+    }
+}
+
+impl DecodedSample for f32 {
+    fn from_f32(v: f32) -> Self {
+        v
+    }
+
+    /// Reuses the allocation of `out` when it can hold the samples, and adopts `buffer`
+    /// otherwise.
+    fn collect_mapped(mut buffer: Vec<f32>, map: impl Fn(f32) -> f32, out: &mut Vec<Self>) {
+        if out.capacity() >= buffer.len() {
+            out.clear();
+            out.extend(buffer.iter().map(|&v| map(v)));
+        } else {
+            for v in buffer.iter_mut() {
+                *v = map(*v);
+            }
+            *out = buffer;
+        }
+    }
+}
+
+#[cfg(feature = "half")]
+impl DecodedSample for half::f16 {
+    fn from_f32(v: f32) -> Self {
+        half::f16::from_f32(v)
+    }
+}
+
+#[cfg(feature = "half")]
+impl DecodedSample for half::bf16 {
+    fn from_f32(v: f32) -> Self {
+        half::bf16::from_f32(v)
+    }
+}
+
+/// Returns the stored mean of a [`DataType::Float64`] stream, `None` for `Float32`.
+fn float64_offset(bytes: &[u8], header: &BiolepticHeader) -> Result<Option<f64>, BiolepticError> {
+    match header.data_type()? {
+        DataType::Float32 | DataType::Int16 => Ok(None),
+        DataType::Float64 => {
+            let mean = find_extension(bytes, header, FLOAT64_OFFSET_TAG)?
+                .ok_or(BiolepticError::InvalidHeaderExtension)?;
+            decode_float64_offset(mean).map(Some)
+        }
+    }
+}
+
+/// Reconstructs the signal as encoded, before a [`DataType::Float64`] mean is restored.
+///
+/// Coefficients are inflated from the payload of `bytes` unless `quantized` already holds
+/// them, as for the channels of a multichannel record. The result replaces the contents
+/// of `signal`.
+pub(crate) fn decode_signal(
+    bytes: &[u8],
+    header: &BiolepticHeader,
+    quantized: Option<&[i32]>,
+    gain: f32,
+    offset: f32,
+    signal: &mut Vec<f32>,
+) -> Result<(), BiolepticError> {
+    check_decodable(header)?;
+
+    // A differenced signal is integrated after reconstruction, so only the gain applies
+    // to the differences and the offset moves into the initial value. Companding isn't
+    // affine, so the map is applied only after expansion.
+    let pre_transform = header.pre_transform(bytes)?;
+    let differenced = pre_transform == PreTransform::Difference;
+    let companded = matches!(pre_transform, PreTransform::Log(_));
+    let (payload_gain, payload_offset) = match (differenced, companded) {
+        (true, _) => (gain, 0.),
+        (_, true) => (1., 0.),
+        _ => (gain, offset),
+    };
+
+    if header.is_zero_signal() {
+        signal.clear();
+        signal.resize(header.signal_length as usize, payload_offset);
+    } else {
+        match quantized {
+            Some(quantized) => reconstruct(
+                header,
+                find_extension(bytes, header, LEVEL_SCALES_TAG)?,
+                quantized,
+                payload_gain,
+                payload_offset,
+                signal,
+            )?,
+            None => decode_payload(bytes, header, payload_gain, payload_offset, signal)?,
+        }
+    }
+
+    if differenced {
+        let initial = find_extension(bytes, header, DIFFERENCE_TAG)?
+            .ok_or(BiolepticError::InvalidHeaderExtension)?;
+        integrate(signal, fmla(decode_initial_value(initial)?, gain, offset));
+    }
+
+    if let PreTransform::PolyDetrend(_) = pre_transform {
+        let poly = find_extension(bytes, header, POLY_TAG)?
+            .ok_or(BiolepticError::InvalidHeaderExtension)?;
+        add_polynomial(signal, &decode_polynomial(poly)?, gain as f64);
+    }
+
+    if companded {
+        let k = find_extension(bytes, header, LOG_TAG)?
+            .ok_or(BiolepticError::InvalidHeaderExtension)?;
+        log_expand(signal, decode_log_factor(k)?);
+        for v in signal.iter_mut() {
+            *v = fmla(*v, gain, offset);
+        }
+    }
+
+    if let Some(taper) = find_extension(bytes, header, TAPER_TAG)? {
+        let (alpha, start, end) = decode_taper(taper)?;
+        // The offsets are differences, so only the caller's gain applies to them.
+        remove_taper(signal, alpha, start, end, gain);
+    }
+
+    if let Some(map) = find_extension(bytes, header, NON_FINITE_MAP_TAG)? {
+        restore_non_finite(map, signal)?;
+    }
+
+    Ok(())
+}
+
+/// Rejects headers the single-channel decoder can't reconstruct: other record kinds and
+/// lengths or level counts out of range.
+fn check_decodable(header: &BiolepticHeader) -> Result<(), BiolepticError> {
+    if header.channels != 1 {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Stream holds {} channels, decode it with decompress_multichannel",
+            { header.channels }
+        )));
+    }
+    if header.is_delta() {
+        return Err(BiolepticError::DecompressionError(
+            "Stream is delta coded, decode it with decompress_delta".to_string(),
+        ));
+    }
+    check_not_rows(header)?;
+    check_signal_length(header)?;
     let signal_length = header.signal_length as usize;
 
     if signal_length > i32::MAX as usize {
@@ -65,92 +734,942 @@ pub fn decompress(bytes: &[u8]) -> Result<Vec<f32>, BiolepticError> {
             dwt_levels,
         )));
     }
+    Ok(())
+}
+
+fn decode_payload(
+    bytes: &[u8],
+    header: &BiolepticHeader,
+    gain: f32,
+    offset: f32,
+    signal: &mut Vec<f32>,
+) -> Result<(), BiolepticError> {
+    let quantized_data = decode_quantized(bytes, header)?;
+    let level_scales = find_extension(bytes, header, LEVEL_SCALES_TAG)?;
+    reconstruct(header, level_scales, &quantized_data, gain, offset, signal)
+}
+
+/// Rejects records of [`crate::compress_rows`], whose payload holds whole row records
+/// rather than coefficients.
+fn check_not_rows(header: &BiolepticHeader) -> Result<(), BiolepticError> {
+    if header.is_rows() {
+        return Err(BiolepticError::DecompressionError(
+            "Stream holds rows, decode it with decompress_rows".to_string(),
+        ));
+    }
+    Ok(())
+}
 
-    let compression_method = header.compression_method()?;
+/// Inflates and deserializes the quantized coefficients of the payload.
+pub(crate) fn decode_quantized(
+    bytes: &[u8],
+    header: &BiolepticHeader,
+) -> Result<Vec<i32>, BiolepticError> {
+    check_not_rows(header)?;
+    let mut compressed_size = header.compressed_size as usize;
+    let encoding = header.payload_encoding()?;
+    // Bitplane payloads are embedded, so a truncated one still decodes to a coarser signal.
+    let progressive = encoding == PayloadEncoding::Bitplane;
 
-    let dwt_worker = match compression_method {
-        CompressionMethod::Cdf53 => Osclet::make_cdf53_f32(),
-        CompressionMethod::Cdf97 => Osclet::make_cdf97_f32(),
-        CompressionMethod::Db4 => {
-            Osclet::make_daubechies_f32(DaubechiesFamily::Db4, BorderMode::Wrap)
+    let data_remainder_size = bytes.len() - BIOLEPTIC_HEADER_SIZE;
+    if data_remainder_size < compressed_size {
+        if !progressive {
+            return Err(BiolepticError::DecompressionError(format!(
+                "Minimum data size is {}, but it was {}",
+                BIOLEPTIC_HEADER_SIZE + compressed_size,
+                bytes.len(),
+            )));
         }
-        CompressionMethod::Sym4 => Osclet::make_symlet_f32(SymletFamily::Sym4, BorderMode::Wrap),
-    };
+        compressed_size = data_remainder_size;
+    }
+
+    let compressed_data = &bytes[BIOLEPTIC_HEADER_SIZE..BIOLEPTIC_HEADER_SIZE + compressed_size];
+    // A truncated progressive payload can't match the checksum of the whole one.
+    if compressed_size == header.compressed_size as usize {
+        header.verify_payload(compressed_data)?;
+    }
+
+    let layout = header.coefficient_layout()?;
+    let approx_length = layout.approximation_length;
+    let detail_lengths = layout.detail_lengths;
+
+    // Bound the inflated size by what the level layout can hold, so a crafted payload
+    // can't expand into an arbitrarily large allocation.
+    let max_decoded_size = max_payload_size(
+        approx_length,
+        &detail_lengths,
+        header.coefficient_width(),
+        encoding,
+    );
+    let mut decoded_data = Vec::new();
+    let decoded = entropy_decode(
+        header.entropy_coder()?,
+        compressed_data,
+        max_decoded_size,
+        &mut decoded_data,
+    );
+    // A cut stream still yields everything decoded before the cut.
+    if let Err(error) = decoded
+        && !(progressive && compressed_size < header.compressed_size as usize)
+    {
+        return Err(error);
+    }
+    if decoded_data.len() > max_decoded_size {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Payload expands beyond {max_decoded_size} bytes expected for signal of length {}",
+            { header.signal_length },
+        )));
+    }
+
+    deserialize_coefficients(
+        &decoded_data,
+        approx_length,
+        &detail_lengths,
+        header.coefficient_width(),
+        encoding,
+    )
+}
+
+/// Computes the approximation and detail sizes of every DWT level described by `header`.
+pub(crate) fn level_sizes(header: &BiolepticHeader) -> Result<Vec<DwtSize>, BiolepticError> {
+    let dwt_levels = header.levels as usize;
+    check_signal_length(header)?;
+    let dwt_worker = make_dwt_worker!(header.compression_method()?, header.border_mode());
 
     let mut levels_length: Vec<DwtSize> = vec![DwtSize::new(0); dwt_levels];
-    levels_length[0] = dwt_worker.dwt_size(signal_length);
+    // Signals shorter than the filter were wrap-extended to its length before the DWT.
+    let padded_length = (header.signal_length as usize).max(dwt_worker.filter_length());
+    levels_length[0] = dwt_worker.dwt_size(padded_length);
 
     for i in 1..dwt_levels {
         let level_size = dwt_worker.dwt_size(levels_length[i - 1].approx_length);
         levels_length[i] = level_size;
     }
+    // A crafted header can ask for more levels than the signal has samples to split.
+    if let Some(level) = levels_length
+        .iter()
+        .position(|x| x.approx_length == 0 || x.details_length == 0)
+    {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Level {} of {} samples is empty",
+            level + 1,
+            { header.signal_length }
+        )));
+    }
+    Ok(levels_length)
+}
 
-    let compressed_size = header.compressed_size as usize;
+/// Rejects headers claiming an empty signal, which no encoder writes.
+pub(crate) fn check_signal_length(header: &BiolepticHeader) -> Result<(), BiolepticError> {
+    if header.signal_length == 0 {
+        return Err(BiolepticError::DecompressionError(
+            "Signal length must be at least 1, but it was 0".to_string(),
+        ));
+    }
+    Ok(())
+}
 
-    let data_remainder_size = bytes.len() - BIOLEPTIC_HEADER_SIZE;
-    if data_remainder_size < compressed_size {
+/// Dequantizes coefficients laid out as produced by the encoder and runs the inverse
+/// DWT and denormalization described by `header`, followed by `* gain + offset`.
+/// `level_scales` holds the per-band scales when the stream was written with
+/// [`crate::CompressionOptions::level_scales`].
+///
+/// The samples replace the contents of `signal`, reusing its allocation when it can hold
+/// them and adopting the inverse DWT buffer otherwise.
+pub(crate) fn reconstruct<T: DecodedSample>(
+    header: &BiolepticHeader,
+    level_scales: Option<&[u8]>,
+    quantized_data: &[i32],
+    gain: f32,
+    offset: f32,
+    signal: &mut Vec<T>,
+) -> Result<(), BiolepticError> {
+    let signal_length = header.signal_length as usize;
+    let dwt_levels = header.levels as usize;
+    let band_scales = band_scales(header, level_scales)?;
+
+    let expected_length = header.coefficient_layout()?.total();
+    if quantized_data.len() != expected_length {
         return Err(BiolepticError::DecompressionError(format!(
-            "Minimum data size is {}, but it was {}",
-            BIOLEPTIC_HEADER_SIZE + compressed_size,
-            bytes.len(),
+            "Expected {expected_length} coefficients, but payload holds {}",
+            quantized_data.len()
         )));
     }
 
-    let compressed_data = &bytes[BIOLEPTIC_HEADER_SIZE..BIOLEPTIC_HEADER_SIZE + compressed_size];
+    if header.is_lossless() {
+        let samples = inverse_lossless(quantized_data, signal_length, dwt_levels);
+        signal.clear();
+        signal.extend(
+            samples
+                .iter()
+                .map(|&v| T::from_f32(fmla(v as f32, gain, offset))),
+        );
+        return Ok(());
+    }
 
-    let mut decoder = DeflateDecoder::new(compressed_data);
-    let mut decoded_data = vec![0u8; decoder.total_out() as usize];
-    decoder
-        .read_to_end(&mut decoded_data)
-        .map_err(|x| BiolepticError::DecompressionError(x.to_string()))?;
+    let dwt_worker = make_dwt_worker!(header.compression_method()?, header.border_mode());
+    let (approximations, details) = dequantize_bands(header, &band_scales, quantized_data)?;
 
-    let quantized_data = decoded_data
-        .chunks_exact(2)
-        .map(|x| i16::from_le_bytes([x[0], x[1]]))
-        .collect::<Vec<i16>>();
+    let iwdt = dwt_worker
+        .multi_idwt(&MultiLevelDwtRef {
+            approximations: &approximations,
+            details: details.iter().map(|x| x.as_slice()).collect(),
+        })
+        .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?;
+    denormalize(header, iwdt, gain, offset, signal);
+    Ok(())
+}
 
-    let scale = header.scale;
-    if !(6..=12).contains(&scale) {
-        return Err(BiolepticError::DecompressionError(format!(
-            "Supported scales only [6, 12] but it was {scale}"
-        )));
+/// Maps the inverse DWT output back through the header's normalization into `signal`,
+/// then through `gain` and `offset`.
+fn denormalize<T: DecodedSample>(
+    header: &BiolepticHeader,
+    mut iwdt: Vec<f32>,
+    gain: f32,
+    offset: f32,
+    signal: &mut Vec<T>,
+) {
+    let signal_length = header.signal_length as usize;
+    let range = (header.max_f32() - header.min_f32()) * gain;
+    let v_min = fmla(header.min_f32(), gain, offset);
+    let v_mean = header.mean_f32();
+
+    // DWT might produce for odd sized data different size, so we'll truncate it
+    iwdt.resize(signal_length, 0.);
+    T::collect_mapped(iwdt, |v| fmla(v + v_mean, range, v_min), signal);
+}
+
+/// Resolves the quantization scale of every band, the approximation first, and checks
+/// that each one is supported by the coefficient width.
+fn band_scales(
+    header: &BiolepticHeader,
+    level_scales: Option<&[u8]>,
+) -> Result<Vec<u8>, BiolepticError> {
+    let dwt_levels = header.levels as usize;
+    let band_scales = match level_scales {
+        Some(scales) => decode_level_scales(scales, dwt_levels)?.to_vec(),
+        None => vec![header.scale; dwt_levels + 1],
+    };
+    let max_scale = if header.has_wide_coefficients() {
+        20
+    } else {
+        12
+    };
+    for &scale in band_scales.iter().chain(std::iter::once(&header.scale)) {
+        if !(6..=max_scale).contains(&scale) {
+            return Err(BiolepticError::DecompressionError(format!(
+                "Supported scales only [6, {max_scale}] but it was {scale}"
+            )));
+        }
     }
+    Ok(band_scales)
+}
+
+/// Splits quantized coefficients in payload order into the dequantized approximation and
+/// the dequantized detail levels, the finest first.
+fn dequantize_bands(
+    header: &BiolepticHeader,
+    band_scales: &[u8],
+    quantized_data: &[i32],
+) -> Result<(Vec<f32>, Vec<Vec<f32>>), BiolepticError> {
+    let dwt_levels = header.levels as usize;
+    let levels_length = level_sizes(header)?;
 
-    let rcp_scale = 1. / (1 << scale) as f32;
+    let rcp_scales = band_scales
+        .iter()
+        .map(|&scale| QuantizationScale::try_from(scale).map(|x| x.reciprocal_f64()))
+        .collect::<Result<Vec<f64>, BiolepticError>>()?;
+    let dequantize = header.dequant_mode().dequantizer();
 
     let mut details = vec![];
     let mut details_start = levels_length[dwt_levels - 1].approx_length;
     for level in 0..dwt_levels {
+        let rcp_scale = rcp_scales[level + 1];
         let detail_level: Vec<f32> = quantized_data
             [details_start..details_start + levels_length[level].details_length]
             .iter()
-            .map(|&x| x as f32 * rcp_scale)
+            .map(|&x| dequantize(x, rcp_scale))
             .collect();
         details.push(detail_level);
         details_start += levels_length[level].details_length;
     }
 
-    let mut iwdt = dwt_worker
-        .multi_idwt(&MultiLevelDwtRef {
-            approximations: &quantized_data[..levels_length[dwt_levels - 1].approx_length]
-                .iter()
-                .map(|&x| x as f32 * rcp_scale)
-                .collect::<Vec<f32>>(),
-            details: details.iter().map(|x| x.as_slice()).collect(),
+    let approximations = quantized_data[..levels_length[dwt_levels - 1].approx_length]
+        .iter()
+        .map(|&x| dequantize(x, rcp_scales[0]))
+        .collect::<Vec<f32>>();
+    Ok((approximations, details))
+}
+
+/// Decodes a stream of consecutive records frame by frame, invoking `callback` with
+/// each frame's samples as soon as it is reconstructed.
+///
+/// Only one frame is held in memory at a time. A single-record stream calls back once
+/// with the whole signal. Returns the number of frames decoded.
+pub fn decompress_streaming(
+    bytes: &[u8],
+    callback: &mut dyn FnMut(&[f32]),
+) -> Result<usize, BiolepticError> {
+    let mut frames = 0usize;
+    for samples in decode_concatenated(bytes) {
+        callback(&samples?);
+        frames += 1;
+    }
+    Ok(frames)
+}
+
+/// Checks whether two encoded streams decode to the same signal within `tolerance`.
+///
+/// Byte-identical streams are equal without decoding, streams whose headers disagree on
+/// the signal length never are. Otherwise both are decoded and compared by maximum
+/// absolute error, which covers payloads re-encoded with different entropy settings.
+/// Streams of any data type compare by value, an `Int16` stream in sample units.
+pub fn files_equivalent(a: &[u8], b: &[u8], tolerance: f32) -> Result<bool, BiolepticError> {
+    let header_a = BiolepticHeader::from_bytes(a)?;
+    let header_b = BiolepticHeader::from_bytes(b)?;
+    if a == b {
+        return Ok(true);
+    }
+    if header_a.signal_length != header_b.signal_length {
+        return Ok(false);
+    }
+    let mut decoded_a = Vec::new();
+    decode_samples(a, &header_a, None, 1., 0., &mut decoded_a)?;
+    let mut decoded_b = Vec::new();
+    decode_samples(b, &header_b, None, 1., 0., &mut decoded_b)?;
+    Ok(decoded_a
+        .iter()
+        .zip(decoded_b.iter())
+        .all(|(&x, &y)| (x - y).abs() <= tolerance))
+}
+
+/// Decodes every record of a stream made by concatenating single-record files.
+///
+/// Records are self-delimiting, so each one is located from its header and decoded lazily
+/// with [`decompress`], which rejects `Int16` records. A truncated or malformed trailing
+/// record yields an error as the last item.
+pub fn decode_concatenated(
+    bytes: &[u8],
+) -> impl Iterator<Item = Result<Vec<f32>, BiolepticError>> + '_ {
+    let mut offset = 0usize;
+    std::iter::from_fn(move || {
+        if offset >= bytes.len() {
+            return None;
+        }
+        let record = &bytes[offset..];
+        let decoded = BiolepticHeader::from_bytes(record)
+            .and_then(|header| record_length(record, &header))
+            .and_then(|length| {
+                offset += length;
+                decompress(&record[..length])
+            });
+        if decoded.is_err() {
+            // Nothing after a broken record can be located, so stop here.
+            offset = bytes.len();
+        }
+        Some(decoded)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::header::payload_crc;
+    use crate::{CompressionMethod, CompressionOptions, compress};
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_float64_round_trip_keeps_offset_precision() {
+        // Millivolt-scale content riding on a large offset that f32 can't resolve.
+        let signal = generate_ppg(5000, 120., 75.)
+            .iter()
+            .map(|&x| 1e7 + x as f64 * 1e-3)
+            .collect::<Vec<f64>>();
+        let options = CompressionOptions::default();
+        let compressed = crate::compress_f64(&signal, options).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.data_type().unwrap(), DataType::Float64);
+
+        let decoded = decompress_f64(&compressed).unwrap();
+        let f64_error = signal
+            .iter()
+            .zip(decoded.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0f64, f64::max);
+
+        let downcast = signal.iter().map(|&x| x as f32).collect::<Vec<f32>>();
+        let f32_decoded = decompress(&compress(&downcast, options).unwrap()).unwrap();
+        let f32_error = signal
+            .iter()
+            .zip(f32_decoded.iter())
+            .map(|(a, &b)| (a - b as f64).abs())
+            .fold(0f64, f64::max);
+        println!("max error f64={f64_error} f32={f32_error}");
+        assert!(f64_error * 10. < f32_error);
+
+        let rounded = decompress(&compressed).unwrap();
+        assert!((rounded[100] as f64 - decoded[100]).abs() <= 1.);
+
+        let float32 = compress(&downcast, options).unwrap();
+        assert!(matches!(
+            decompress_f64(&float32),
+            Err(BiolepticError::DecompressionError(x))
+                if x == "Stream holds Float32 samples, decode it with decompress"
+        ));
+        let int16 = crate::compress_i16(&[1, 2, 3, 4, 5, 6, 7, 8], options).unwrap();
+        assert!(matches!(
+            decompress_f64(&int16),
+            Err(BiolepticError::DecompressionError(x))
+                if x == "Stream holds Int16 samples, decode it with decompress_i16"
+        ));
+    }
+
+    #[test]
+    fn test_decompress_into_reuses_buffer() {
+        let signal = generate_ppg(3000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let length = decompressed_len(&compressed).unwrap();
+        assert_eq!(length, signal.len());
+
+        let mut out = Vec::with_capacity(length);
+        out.push(42.);
+        let pointer = out.as_ptr();
+        decompress_into(&compressed, &mut out).unwrap();
+        assert_eq!(out.as_ptr(), pointer);
+        assert_eq!(out, decompress(&compressed).unwrap());
+
+        let zeros = compress(&[0f32; 100], CompressionOptions::default()).unwrap();
+        decompress_into(&zeros, &mut out).unwrap();
+        assert_eq!(out.as_ptr(), pointer);
+        assert_eq!(out, vec![0f32; 100]);
+    }
+
+    #[test]
+    fn test_streaming_callback_per_frame() {
+        let signal = generate_ppg(3000, 120., 75.);
+        let mut stream = Vec::new();
+        let mut expected = Vec::new();
+        for frame in signal.chunks(1000) {
+            let encoded = compress(frame, CompressionOptions::default().with_envelope(64)).unwrap();
+            expected.extend(decompress(&encoded).unwrap());
+            stream.extend(encoded);
+        }
+
+        let mut calls = 0usize;
+        let mut collected = Vec::new();
+        let frames = decompress_streaming(&stream, &mut |samples| {
+            calls += 1;
+            collected.extend_from_slice(samples);
         })
-        .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?;
+        .unwrap();
+        assert_eq!(frames, 3);
+        assert_eq!(calls, 3);
+        assert_eq!(collected, expected);
 
-    let range = header.max_f32() - header.min_f32();
-    let v_min = header.min_f32();
-    let v_mean = header.mean_f32();
+        let single = compress(&signal, CompressionOptions::default()).unwrap();
+        let mut calls = 0usize;
+        decompress_streaming(&single, &mut |samples| {
+            calls += 1;
+            assert_eq!(samples.len(), signal.len());
+        })
+        .unwrap();
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_decode_concatenated() {
+        let signals = [
+            generate_ppg(1500, 120., 75.),
+            generate_ppg(700, 250., 60.),
+            generate_ppg(3000, 100., 90.),
+        ];
+        let mut stream = Vec::new();
+        let mut expected = Vec::new();
+        for signal in signals.iter() {
+            let encoded = compress(signal, CompressionOptions::default()).unwrap();
+            expected.push(decompress(&encoded).unwrap());
+            stream.extend(encoded);
+        }
 
-    for v in iwdt.iter_mut() {
-        *v = fmla(*v + v_mean, range, v_min);
+        let decoded = decode_concatenated(&stream)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(decoded, expected);
+
+        stream.extend_from_slice(b"BILP garbage");
+        let items = decode_concatenated(&stream).collect::<Vec<_>>();
+        assert_eq!(items.len(), 4);
+        assert!(items[..3].iter().all(|x| x.is_ok()));
+        assert!(items[3].is_err());
     }
-    // DWT might produce for odd sized data different size, so we'll truncate it
-    if iwdt.len() != header.signal_length as usize {
-        iwdt.resize(header.signal_length as usize, 0.);
+
+    #[test]
+    fn test_files_equivalent() {
+        use flate2::Compression;
+        use flate2::write::DeflateEncoder;
+        use std::io::Write;
+
+        let signal = generate_ppg(4000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        assert!(files_equivalent(&compressed, &compressed.clone(), 0.).unwrap());
+
+        // Same coefficients, re-deflated at a different level.
+        let payload = &compressed[BIOLEPTIC_HEADER_SIZE..];
+        let mut coefficients = Vec::new();
+        DeflateDecoder::new(payload)
+            .read_to_end(&mut coefficients)
+            .unwrap();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(&coefficients).unwrap();
+        let recompressed_payload = encoder.finish().unwrap();
+        assert_ne!(recompressed_payload, payload);
+        let mut recompressed_header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        recompressed_header.compressed_size = recompressed_payload.len() as u32;
+        recompressed_header.payload_crc = payload_crc(&recompressed_payload);
+        let mut recompressed = recompressed_header.to_bytes().to_vec();
+        recompressed.extend_from_slice(&recompressed_payload);
+        assert!(files_equivalent(&compressed, &recompressed, 0.).unwrap());
+
+        let different = compress(
+            &generate_ppg(4000, 120., 90.),
+            CompressionOptions::default(),
+        )
+        .unwrap();
+        assert!(!files_equivalent(&compressed, &different, 1.).unwrap());
+        let shorter = compress(&signal[..3000], CompressionOptions::default()).unwrap();
+        assert!(!files_equivalent(&compressed, &shorter, 1.).unwrap());
+
+        let samples = signal
+            .iter()
+            .map(|&x| (x * 1000.) as i16)
+            .collect::<Vec<_>>();
+        let integers = crate::compress_i16(&samples, CompressionOptions::default()).unwrap();
+        let coarser = crate::compress_i16(
+            &samples,
+            CompressionOptions {
+                scale: crate::QuantizationScale::S10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        // Rounding to whole samples moves each one by at most half a unit.
+        let error = decompress_i16(&integers)
+            .unwrap()
+            .iter()
+            .zip(decompress_i16(&coarser).unwrap())
+            .map(|(&x, y)| (x as f32 - y as f32).abs())
+            .fold(0f32, f32::max);
+        assert!(files_equivalent(&integers, &coarser, error + 1.).unwrap());
+        assert!(!files_equivalent(&integers, &coarser, 0.).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_garbage_payload() {
+        let signal = generate_ppg(1000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let mut header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        // Block type 3 is reserved, so the deflate stream is invalid from its first bit.
+        let garbage = vec![0xFFu8; 64];
+        header.compressed_size = garbage.len() as u32;
+        header.payload_crc = payload_crc(&garbage);
+        let mut crafted = header.to_bytes().to_vec();
+        crafted.extend_from_slice(&garbage);
+        assert!(matches!(
+            decompress(&crafted),
+            Err(BiolepticError::UnderlyingCompressorError(_))
+        ));
     }
 
-    Ok(iwdt)
+    #[test]
+    fn test_decompress_pre_decoded_coefficients() {
+        let signal = generate_ppg(3000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        let coefficients = decode_quantized(&compressed, &header).unwrap();
+        // The payload is never inflated, only the header and extensions are read.
+        let mut stripped = compressed.clone();
+        stripped[BIOLEPTIC_HEADER_SIZE..].fill(0);
+        assert_eq!(
+            decompress_coefficients(&stripped, &coefficients).unwrap(),
+            decompress(&compressed).unwrap()
+        );
+        assert!(decompress_coefficients(&stripped, &coefficients[1..]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_corrupted_payload_checksum() {
+        let signal = generate_ppg(1000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_ne!({ header.payload_crc }, 0);
+
+        let mut corrupted = compressed.clone();
+        corrupted[BIOLEPTIC_HEADER_SIZE + 10] ^= 0x10;
+        assert!(matches!(
+            decompress(&corrupted),
+            Err(BiolepticError::ChecksumMismatch(..))
+        ));
+
+        // Streams without checksums are decoded unchecked.
+        let mut legacy = compressed.clone();
+        legacy[42..48].fill(0);
+        assert_eq!(
+            decompress(&legacy).unwrap(),
+            decompress(&compressed).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_coefficient_count_mismatch() {
+        use flate2::Compression;
+        use flate2::write::DeflateEncoder;
+        use std::io::Write;
+
+        let signal = generate_ppg(1000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let mut header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        let mut coefficients = Vec::new();
+        DeflateDecoder::new(&compressed[BIOLEPTIC_HEADER_SIZE..])
+            .read_to_end(&mut coefficients)
+            .unwrap();
+
+        for length in [coefficients.len() - 2, coefficients.len() / 2, 0] {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&coefficients[..length]).unwrap();
+            let payload = encoder.finish().unwrap();
+            header.compressed_size = payload.len() as u32;
+            header.payload_crc = payload_crc(&payload);
+            let mut crafted = header.to_bytes().to_vec();
+            crafted.extend_from_slice(&payload);
+            assert!(matches!(
+                decompress(&crafted),
+                Err(BiolepticError::DecompressionError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_rejects_expanding_payload() {
+        use flate2::Compression;
+        use flate2::write::DeflateEncoder;
+        use std::io::Write;
+
+        let signal = generate_ppg(100, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let mut header = BiolepticHeader::from_bytes(&compressed).unwrap();
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&vec![0u8; 16 * 1024 * 1024]).unwrap();
+        let bomb = encoder.finish().unwrap();
+        header.compressed_size = bomb.len() as u32;
+        header.payload_crc = payload_crc(&bomb);
+
+        let mut crafted = header.to_bytes().to_vec();
+        crafted.extend_from_slice(&bomb);
+        assert!(matches!(
+            decompress(&crafted),
+            Err(BiolepticError::DecompressionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_decompress_scaled_matches_manual_scaling() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let (gain, offset) = (0.0375f32, -12.5f32);
+        for options in [
+            CompressionOptions::default(),
+            CompressionOptions {
+                pre_transform: crate::PreTransform::Difference,
+                ..Default::default()
+            },
+        ] {
+            let compressed = compress(&signal, options).unwrap();
+            let expected = decompress(&compressed).unwrap();
+            let scaled = decompress_scaled(&compressed, gain, offset).unwrap();
+            // Differenced signals accumulate rounding along the running sum, so compare
+            // against the signal magnitude rather than per sample.
+            let tolerance = 1e-4 * expected.iter().fold(0f32, |acc, &x| acc.max(x.abs())) * gain;
+            for (&a, &b) in expected.iter().zip(scaled.iter()) {
+                let manual = a * gain + offset;
+                assert!((manual - b).abs() <= tolerance, "{manual} vs {b}");
+            }
+        }
+        let zeros = compress(&[0f32; 64], CompressionOptions::default()).unwrap();
+        assert_eq!(
+            decompress_scaled(&zeros, gain, offset).unwrap(),
+            vec![offset; 64]
+        );
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_decompress_half() {
+        // Whole samples, so the lossless mode accepts them.
+        let signal = generate_ppg(5000, 120., 75.)
+            .iter()
+            .map(|x| (x * 100.).round())
+            .collect::<Vec<f32>>();
+        // Written directly by the denormalization loop, then through the f32 fallback.
+        for options in [
+            CompressionOptions::default(),
+            CompressionOptions {
+                lossless: true,
+                method: CompressionMethod::Cdf53,
+                ..Default::default()
+            },
+            CompressionOptions {
+                pre_transform: PreTransform::Difference,
+                ..Default::default()
+            },
+        ] {
+            let compressed = compress(&signal, options).unwrap();
+            let expected = decompress(&compressed).unwrap();
+            let f16 = decompress_f16(&compressed).unwrap();
+            let bf16 = decompress_bf16(&compressed).unwrap();
+            assert_eq!(f16.len(), expected.len());
+            assert_eq!(bf16.len(), expected.len());
+            for ((&x, a), b) in expected.iter().zip(f16.iter()).zip(bf16.iter()) {
+                assert_eq!(*a, half::f16::from_f32(x));
+                assert_eq!(*b, half::bf16::from_f32(x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_decompress_approximation() {
+        let signal = generate_ppg(20000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let full = decompress(&compressed).unwrap();
+        let preview = decompress_approximation(&compressed).unwrap();
+        assert_eq!(preview.len(), signal.len());
+
+        // The preview follows the signal, but is much smoother than it.
+        let roughness = |x: &[f32]| x.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f32>();
+        assert!(roughness(&preview) * 2. < roughness(&full));
+        let mean = |x: &[f32]| x.iter().sum::<f32>() / x.len() as f32;
+        assert!((mean(&preview) - mean(&full)).abs() < 0.01 * mean(&full).abs());
+
+        // Matches a full decode of the same coefficients with the details zeroed.
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        let mut quantized = decode_quantized(&compressed, &header).unwrap();
+        let approximation_length = header.coefficient_layout().unwrap().approximation_length;
+        quantized[approximation_length..].fill(0);
+        assert_eq!(
+            preview,
+            decompress_coefficients(&compressed, &quantized).unwrap()
+        );
+
+        let zeros = compress(&[0f32; 64], CompressionOptions::default()).unwrap();
+        assert_eq!(decompress_approximation(&zeros).unwrap(), vec![0f32; 64]);
+    }
+
+    #[test]
+    fn test_band_energies() {
+        let clean = generate_ppg(4096, 120., 75.);
+        let noisy = clean
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| if i % 2 == 0 { x + 40. } else { x - 40. })
+            .collect::<Vec<f32>>();
+        let options = CompressionOptions::default();
+
+        let clean_energies = band_energies(&compress(&clean, options).unwrap()).unwrap();
+        let noisy_energies = band_energies(&compress(&noisy, options).unwrap()).unwrap();
+        let header = BiolepticHeader::from_bytes(&compress(&clean, options).unwrap()).unwrap();
+        assert_eq!(clean_energies.len(), header.levels as usize + 1);
+        // The slow pulse lives in the coarse bands, the alternating noise in the finest one.
+        let coarsest = clean_energies.iter().cloned().fold(0f64, f64::max);
+        assert!(clean_energies[1] < coarsest * 0.01);
+        assert!(noisy_energies[1] > clean_energies[1] * 10.);
+
+        let flat = compress(&vec![0f32; 1000], options).unwrap();
+        assert!(band_energies(&flat).unwrap().iter().all(|&x| x == 0.));
+    }
+
+    #[test]
+    fn test_decompress_downsampled() {
+        let signal = (0..10001)
+            .map(|i| (i as f32 * std::f32::consts::TAU / 4000.).sin() * 1000. + 500.)
+            .collect::<Vec<f32>>();
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let levels = BiolepticHeader::from_bytes(&compressed).unwrap().levels;
+        assert_eq!(
+            decompress_downsampled(&compressed, 0).unwrap(),
+            decompress(&compressed).unwrap()
+        );
+
+        for dropped in 1..=3u8 {
+            let coarse = decompress_downsampled(&compressed, dropped).unwrap();
+            let block = 1usize << dropped;
+            assert_eq!(coarse.len(), signal.len().div_ceil(block));
+            // Compare away from the borders against block means of the original.
+            let inner = &coarse[4..coarse.len() - 4];
+            let max_error = inner
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| {
+                    let start = (i + 4) * block;
+                    let mean = signal[start..start + block].iter().sum::<f32>() / block as f32;
+                    (v - mean).abs()
+                })
+                .fold(0f32, f32::max);
+            assert!(max_error < 25., "dropped {dropped}: error {max_error}");
+        }
+
+        let all = decompress_downsampled(&compressed, u8::MAX).unwrap();
+        assert_eq!(all.len(), signal.len().div_ceil(1 << levels));
+
+        // Longer filters on a length that isn't a multiple of the block still line up
+        // with the block means of the full decode.
+        for method in [CompressionMethod::Db4, CompressionMethod::Sym4] {
+            let compressed = compress(
+                &signal[..7777],
+                CompressionOptions {
+                    method,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let full = decompress(&compressed).unwrap();
+            for dropped in 1..=3u8 {
+                let coarse = decompress_downsampled(&compressed, dropped).unwrap();
+                let block = 1usize << dropped;
+                assert_eq!(coarse.len(), 7777usize.div_ceil(block));
+                let averaged = full
+                    .chunks(block)
+                    .map(|x| x.iter().sum::<f32>() / x.len() as f32)
+                    .collect::<Vec<f32>>();
+                let max_error = coarse[4..coarse.len() - 4]
+                    .iter()
+                    .zip(averaged[4..].iter())
+                    .map(|(&x, &y)| (x - y).abs())
+                    .fold(0f32, f32::max);
+                assert!(
+                    max_error < 25.,
+                    "{method:?} dropped {dropped}: error {max_error}"
+                );
+            }
+        }
+
+        let differenced = compress(
+            &signal,
+            CompressionOptions {
+                pre_transform: PreTransform::Difference,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let full = decompress(&differenced).unwrap();
+        let averaged = decompress_downsampled(&differenced, 2).unwrap();
+        assert_eq!(averaged.len(), signal.len().div_ceil(4));
+        assert_eq!(averaged[1], full[4..8].iter().sum::<f32>() / 4.);
+    }
+
+    #[test]
+    fn test_zero_signal_length_is_rejected() {
+        let signal = generate_ppg(3000, 120., 75.);
+        let flat = vec![0f32; 100];
+        let lossless = CompressionOptions {
+            method: crate::CompressionMethod::Cdf53,
+            lossless: true,
+            ..Default::default()
+        };
+        let streams = [
+            compress(&signal, CompressionOptions::default()).unwrap(),
+            compress(&flat, CompressionOptions::default()).unwrap(),
+            compress(
+                &signal.iter().map(|x| x.round()).collect::<Vec<_>>(),
+                lossless,
+            )
+            .unwrap(),
+        ];
+        for stream in streams {
+            for levels in [1u8, 5, 10] {
+                let mut bytes = stream.clone();
+                bytes[12] = levels;
+                bytes[16..20].copy_from_slice(&0u32.to_le_bytes());
+                // Crafted headers come without a header checksum.
+                bytes[46..48].fill(0);
+                assert!(matches!(
+                    decompress(&bytes),
+                    Err(BiolepticError::DecompressionError(_))
+                ));
+                assert!(decompress_approximation(&bytes).is_err());
+                assert!(decompress_downsampled(&bytes, 2).is_err());
+                assert!(band_energies(&bytes).is_err());
+                assert!(!crate::validate(&bytes).unwrap().is_valid());
+            }
+        }
+
+        // More levels than a two-sample signal can be split into.
+        let mut bytes = compress(&signal, CompressionOptions::default()).unwrap();
+        bytes[12] = 10;
+        bytes[16..20].copy_from_slice(&2u32.to_le_bytes());
+        bytes[46..48].fill(0);
+        assert!(decompress(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_int16_round_trip() {
+        let samples = generate_ppg(5000, 120., 75.)
+            .iter()
+            .map(|&x| (x * 40.) as i16 - 2000)
+            .collect::<Vec<i16>>();
+        let lossless = CompressionOptions {
+            lossless: true,
+            ..CompressionOptions::from_method(crate::CompressionMethod::Cdf53)
+        };
+        let compressed = crate::compress_i16(&samples, lossless).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.data_type().unwrap(), DataType::Int16);
+        assert_eq!(decompress_i16(&compressed).unwrap(), samples);
+        assert!(matches!(
+            decompress(&compressed),
+            Err(BiolepticError::DecompressionError(_))
+        ));
+
+        let lossy = crate::compress_i16(&samples, CompressionOptions::default()).unwrap();
+        let decoded = decompress_i16(&lossy).unwrap();
+        let max_error = samples
+            .iter()
+            .zip(decoded.iter())
+            .map(|(&a, &b)| (a as i32 - b as i32).abs())
+            .max()
+            .unwrap();
+        assert!(max_error < 100, "max error {max_error}");
+
+        let float = compress(
+            &generate_ppg(1000, 120., 75.),
+            CompressionOptions::default(),
+        )
+        .unwrap();
+        assert!(decompress_i16(&float).is_err());
+    }
+
+    #[test]
+    fn test_coefficient_view() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        let view = decode_coefficients_borrowed(&compressed).unwrap();
+        assert_eq!(
+            view.as_slice(),
+            decode_quantized(&compressed, &header).unwrap()
+        );
+        assert_eq!(view.levels(), header.levels as usize);
+        assert_eq!(view.layout(), &header.coefficient_layout().unwrap());
+
+        let mut bands = view.approximation().to_vec();
+        for level in 0..view.levels() {
+            bands.extend_from_slice(view.detail(level).unwrap());
+        }
+        assert_eq!(bands, view.as_slice());
+        assert!(view.detail(view.levels()).is_none());
+
+        let zero = compress(&[0f32; 300], CompressionOptions::default()).unwrap();
+        let view = decode_coefficients_borrowed(&zero).unwrap();
+        assert!(view.as_slice().iter().all(|&x| x == 0));
+    }
 }
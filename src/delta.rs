@@ -0,0 +1,205 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::compressor::{encode_coefficients, entropy_code};
+use crate::decompressor::decode_signal;
+use crate::extension::find_extension;
+use crate::header::FLAG_DELTA;
+use crate::{BiolepticError, BiolepticHeader, CompressionOptions};
+
+/// Fingerprint of the reference a delta record was coded against: the CRC32 of its samples
+/// as `f32` LE bytes.
+pub(crate) const DELTA_TAG: [u8; 4] = *b"dlta";
+
+/// Compresses `data` as its difference to `reference`, e.g. a beat against a template beat
+/// or a channel against a strongly correlated neighbour.
+///
+/// The residual `data - reference` runs through the same pipeline as [`crate::compress`],
+/// so the closer the two signals are, the smaller the record. Both slices must have the
+/// same length and the reference must be finite. The reference isn't stored, only its
+/// fingerprint: decode with [`decompress_delta`] and the very same reference, the plain
+/// decoders reject the result.
+pub fn compress_delta(
+    data: &[f32],
+    reference: &[f32],
+    options: CompressionOptions,
+) -> Result<Vec<u8>, BiolepticError> {
+    if data.len() != reference.len() {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Reference must have {} samples, but it had {}",
+            data.len(),
+            reference.len()
+        )));
+    }
+    if reference.iter().any(|x| !x.is_finite()) {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "Reference must not contain NaN or infinite samples".to_string(),
+        ));
+    }
+    let residual = data
+        .iter()
+        .zip(reference.iter())
+        .map(|(&x, &r)| x - r)
+        .collect::<Vec<f32>>();
+
+    let mut encoded = encode_coefficients(&residual, options)?;
+    encoded.header.flags |= FLAG_DELTA;
+    encoded
+        .extensions
+        .push(DELTA_TAG, &reference_crc(reference).to_le_bytes());
+    entropy_code(encoded, options)
+}
+
+/// Decompresses a record written by [`compress_delta`] and adds `reference` back.
+///
+/// Fails when `reference` doesn't match the one the record was coded against, either in
+/// length or in content.
+pub fn decompress_delta(bytes: &[u8], reference: &[f32]) -> Result<Vec<f32>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    if !header.is_delta() {
+        return Err(BiolepticError::DecompressionError(
+            "Stream isn't delta coded, decode it with decompress".to_string(),
+        ));
+    }
+    if reference.len() != header.signal_length as usize {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Reference must have {} samples, but it had {}",
+            { header.signal_length },
+            reference.len()
+        )));
+    }
+    let fingerprint = find_extension(bytes, &header, DELTA_TAG)?
+        .and_then(|x| x.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or(BiolepticError::InvalidHeaderExtension)?;
+    if fingerprint != reference_crc(reference) {
+        return Err(BiolepticError::DecompressionError(
+            "Reference doesn't match the one the stream was coded against".to_string(),
+        ));
+    }
+
+    let mut signal = decode_delta_residual(bytes)?;
+    for (v, &r) in signal.iter_mut().zip(reference.iter()) {
+        *v += r;
+    }
+    Ok(signal)
+}
+
+/// Decodes the residual of a delta record without its reference.
+pub(crate) fn decode_delta_residual(bytes: &[u8]) -> Result<Vec<f32>, BiolepticError> {
+    let mut header = BiolepticHeader::from_bytes(bytes)?;
+    header.flags &= !FLAG_DELTA;
+    let mut residual = Vec::new();
+    decode_signal(bytes, &header, None, 1., 0., &mut residual)?;
+    Ok(residual)
+}
+
+fn reference_crc(reference: &[f32]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    for x in reference.iter() {
+        crc.update(&x.to_le_bytes());
+    }
+    crc.sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::{QuantizationScale, compress, decompress};
+
+    #[test]
+    fn test_delta_round_trip() {
+        let reference = generate_ppg(5000, 120., 75.);
+        let data = reference
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| x * 1.01 + (i as f32 * 0.05).sin() * 2.)
+            .collect::<Vec<f32>>();
+        let options = CompressionOptions::default();
+
+        let compressed = compress_delta(&data, &reference, options).unwrap();
+        assert!(BiolepticHeader::from_bytes(&compressed).unwrap().is_delta());
+        let decoded = decompress_delta(&compressed, &reference).unwrap();
+        assert_eq!(decoded.len(), data.len());
+        let max_error = data
+            .iter()
+            .zip(decoded.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0f32, f32::max);
+        assert!(max_error < 0.5, "max error {max_error}");
+
+        // The residual spans a fraction of the signal's range, so the same scale quantizes
+        // it far more finely.
+        let plain = compress(&data, options).unwrap();
+        let plain_error = data
+            .iter()
+            .zip(decompress(&plain).unwrap().iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0f32, f32::max);
+        println!(
+            "delta={} ({max_error}) plain={} ({plain_error})",
+            compressed.len(),
+            plain.len()
+        );
+        assert!(max_error < plain_error);
+
+        // Even the coarsest scale keeps the residual more accurate than the plain record,
+        // at a fraction of its size.
+        let coarse = compress_delta(
+            &data,
+            &reference,
+            CompressionOptions {
+                scale: QuantizationScale::S6,
+                ..options
+            },
+        )
+        .unwrap();
+        let coarse_error = data
+            .iter()
+            .zip(decompress_delta(&coarse, &reference).unwrap().iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0f32, f32::max);
+        println!("coarse delta={} ({coarse_error})", coarse.len());
+        assert!(coarse_error < plain_error);
+        assert!(coarse.len() < plain.len());
+
+        assert!(matches!(
+            decompress(&compressed),
+            Err(BiolepticError::DecompressionError(_))
+        ));
+        assert!(crate::Validator::validate(&compressed).unwrap().is_valid());
+        assert!(decompress_delta(&plain, &reference).is_err());
+
+        let mut other = reference.clone();
+        other[10] += 1.;
+        assert!(decompress_delta(&compressed, &other).is_err());
+        assert!(decompress_delta(&compressed, &reference[1..]).is_err());
+        assert!(compress_delta(&data, &reference[1..], options).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::BiolepticError;
+use crate::compressor::{
+    CompressionOptions, ThresholdMode, ThresholdStrategy, decomposition_levels, finest_noise_sigma,
+    substitute_non_finite, threshold_magnitude, universal_threshold,
+};
+use crate::simd::normalize;
+use crate::worker::make_dwt_worker;
+use osclet::MultiLevelDwtRef;
+
+/// Denoises `data` with the wavelet thresholding of [`crate::compress`], without
+/// quantizing or storing anything.
+///
+/// The signal is normalized and transformed like in `compress`, detail coefficients below
+/// the threshold picked by `options.threshold_strategy` are removed per
+/// `options.threshold_mode`, and the result is transformed back right away. Only the
+/// method, border mode, levels, scale, per-band scales, cutoff, threshold mode, strategy and
+/// non-finite policy are used, the storage options are ignored. Since nothing is
+/// quantized, the output differs from the input only by the removed noise.
+pub fn denoise(data: &[f32], options: CompressionOptions) -> Result<Vec<f32>, BiolepticError> {
+    if data.is_empty() {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "Can't denoise empty data".to_string(),
+        ));
+    }
+    let mut working_data = substitute_non_finite(data, options.non_finite)?;
+    let (v_min, v_max) = working_data
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &x| {
+            (lo.min(x), hi.max(x))
+        });
+    let range = v_max - v_min;
+    if range <= 1e-5 {
+        return Ok(working_data);
+    }
+    normalize(&mut working_data, v_min, 1. / range);
+    let v_mean = working_data.iter().sum::<f32>() / working_data.len() as f32;
+    normalize(&mut working_data, v_mean, 1.);
+
+    let dwt_worker = make_dwt_worker!(options.method, options.border_mode);
+    let filter_length = dwt_worker.filter_length();
+    if working_data.len() < filter_length {
+        let current_len = working_data.len();
+        let extension = (0..filter_length - current_len)
+            .map(|i| working_data[i % current_len])
+            .collect::<Vec<f32>>();
+        working_data.extend_from_slice(&extension);
+    }
+    let level = decomposition_levels(
+        options.levels,
+        data.len(),
+        &working_data,
+        dwt_worker.as_ref(),
+    )?;
+    let mut dwt = dwt_worker
+        .multi_dwt(&working_data, level)
+        .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?;
+
+    let band_scale = |band: usize| options.level_scales.map_or(options.scale, |x| x[band]);
+    let max_sigma = dwt
+        .levels
+        .first()
+        .map_or(0., |x| finest_noise_sigma(&x.details, options));
+    for (index, dwt_level) in dwt.levels.iter_mut().enumerate() {
+        let scale = band_scale(index + 1);
+        // Quantized details below the threshold are exactly those below it in steps.
+        let threshold = match options.threshold_strategy {
+            ThresholdStrategy::Fixed => {
+                threshold_magnitude(scale, options.cutoff_level) as f32
+                    * scale.reciprocal_f64() as f32
+            }
+            ThresholdStrategy::Adaptive => universal_threshold(&dwt_level.details, max_sigma),
+        };
+        for x in dwt_level.details.iter_mut() {
+            *x = match options.threshold_mode {
+                ThresholdMode::Hard if x.abs() < threshold => 0.,
+                ThresholdMode::Hard => *x,
+                ThresholdMode::Soft => (x.abs() - threshold).max(0.).copysign(*x),
+            };
+        }
+    }
+
+    let approximations = match dwt.levels.last() {
+        Some(last) => last.approximations.as_slice(),
+        None => {
+            return Err(BiolepticError::UnderlyingDwtError(
+                "Internal DWT returned zero levels, what shouldn't happen".to_string(),
+            ));
+        }
+    };
+    let mut signal = dwt_worker
+        .multi_idwt(&MultiLevelDwtRef {
+            approximations,
+            details: dwt.levels.iter().map(|x| x.details.as_slice()).collect(),
+        })
+        .map_err(|x| BiolepticError::UnderlyingDwtError(x.to_string()))?;
+    signal.truncate(data.len());
+    for v in signal.iter_mut() {
+        *v = (*v + v_mean) * range + v_min;
+    }
+    Ok(signal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::{CutoffLevel, compress, decompress, prd};
+
+    #[test]
+    fn test_denoise() {
+        let clean = generate_ppg(10000, 120., 75.);
+        let noisy = clean
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| x + 40. * (((i as f32 * 12.9898).sin() * 43758.547).fract() - 0.5))
+            .collect::<Vec<f32>>();
+        let options = CompressionOptions {
+            cutoff_level: CutoffLevel::High,
+            ..Default::default()
+        };
+        let denoised = denoise(&noisy, options).unwrap();
+        assert_eq!(denoised.len(), noisy.len());
+        println!(
+            "noisy={} denoised={}",
+            prd(&clean, &noisy),
+            prd(&clean, &denoised)
+        );
+        assert!(prd(&clean, &denoised) < prd(&clean, &noisy));
+
+        let decompressed = decompress(&compress(&noisy, options).unwrap()).unwrap();
+        assert!(prd(&denoised, &decompressed) < 1.);
+        assert!(denoise(&[], options).is_err());
+        assert_eq!(denoise(&[3.; 16], options).unwrap(), vec![3.; 16]);
+    }
+}
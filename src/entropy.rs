@@ -0,0 +1,158 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::BiolepticError;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::io::{Read, Write};
+
+/// Backend coding the serialized coefficients, recorded in the header so the decoder
+/// picks the matching one.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum EntropyCoder {
+    /// Deflate at [`crate::CompressionOptions::deflate_level`].
+    #[default]
+    Deflate,
+    /// LZ4 block format, decodes several times faster than deflate at a lower ratio.
+    /// Requires the `lz4` feature on both ends.
+    Lz4,
+    /// Stores the serialized coefficients as is, for targets without any decoder.
+    Raw,
+}
+
+pub(crate) fn entropy_encode(
+    coder: EntropyCoder,
+    data: &[u8],
+    deflate_level: u32,
+) -> Result<Vec<u8>, BiolepticError> {
+    match coder {
+        EntropyCoder::Deflate => {
+            let mut e = DeflateEncoder::new(Vec::new(), Compression::new(deflate_level));
+            e.write_all(data)
+                .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+            e.finish()
+                .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))
+        }
+        #[cfg(feature = "lz4")]
+        EntropyCoder::Lz4 => Ok(lz4_flex::block::compress_prepend_size(data)),
+        #[cfg(not(feature = "lz4"))]
+        EntropyCoder::Lz4 => Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "EntropyCoder::Lz4 requires the `lz4` feature".to_string(),
+        )),
+        EntropyCoder::Raw => Ok(data.to_vec()),
+    }
+}
+
+/// Upper bound of [`entropy_encode`] output for `len` input bytes.
+///
+/// Deflate falls back to stored blocks on incompressible input, so a generous margin over
+/// their 5-byte-per-block framing covers every level.
+pub(crate) fn max_encoded_size(coder: EntropyCoder, len: usize) -> usize {
+    match coder {
+        EntropyCoder::Deflate => len.saturating_add(len / 8).saturating_add(64),
+        EntropyCoder::Lz4 => len.saturating_add(len / 255).saturating_add(20),
+        EntropyCoder::Raw => len,
+    }
+}
+
+/// Decodes at most `max_size + 1` bytes into `out`, so callers can detect an oversized
+/// payload without letting it expand into an arbitrarily large allocation.
+///
+/// On error `out` keeps whatever was decoded before the failure.
+pub(crate) fn entropy_decode(
+    coder: EntropyCoder,
+    data: &[u8],
+    max_size: usize,
+    out: &mut Vec<u8>,
+) -> Result<(), BiolepticError> {
+    match coder {
+        EntropyCoder::Deflate => {
+            DeflateDecoder::new(data)
+                .take(max_size as u64 + 1)
+                .read_to_end(out)
+                .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+        }
+        #[cfg(feature = "lz4")]
+        EntropyCoder::Lz4 => {
+            let size = data
+                .get(0..4)
+                .map(|x| u32::from_le_bytes(x.try_into().unwrap()) as usize)
+                .ok_or_else(|| {
+                    BiolepticError::UnderlyingCompressorError(
+                        "LZ4 block is missing its size".to_string(),
+                    )
+                })?;
+            let decoded = lz4_flex::block::decompress(&data[4..], size.min(max_size + 1))
+                .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+            out.extend_from_slice(&decoded);
+        }
+        #[cfg(not(feature = "lz4"))]
+        EntropyCoder::Lz4 => {
+            return Err(BiolepticError::DecompressionError(
+                "Payload is LZ4 coded, enable the `lz4` feature to decode it".to_string(),
+            ));
+        }
+        EntropyCoder::Raw => {
+            out.extend_from_slice(&data[..data.len().min(max_size + 1)]);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entropy_coders_round_trip() {
+        let data = (0..5000u32)
+            .flat_map(|i| ((i % 97) as i16).to_le_bytes())
+            .collect::<Vec<u8>>();
+        let mut coders = vec![EntropyCoder::Deflate, EntropyCoder::Raw];
+        if cfg!(feature = "lz4") {
+            coders.push(EntropyCoder::Lz4);
+        }
+        for coder in coders {
+            let encoded = entropy_encode(coder, &data, 6).unwrap();
+            let mut decoded = Vec::new();
+            entropy_decode(coder, &encoded, data.len(), &mut decoded).unwrap();
+            assert_eq!(decoded, data, "{coder:?}");
+
+            // The bound stops decoding one byte past the limit.
+            let mut bounded = Vec::new();
+            let _ = entropy_decode(coder, &encoded, 100, &mut bounded);
+            assert!(bounded.len() <= 101, "{coder:?}");
+        }
+    }
+}
@@ -34,6 +34,13 @@ pub enum BiolepticError {
     InvalidMagic([u8; 4]),
     InvalidDataType([u8; 2]),
     InvalidVersion([u8; 2]),
+    /// The record has the same major version as this build but a newer minor one, so it
+    /// is well-formed but may use features this reader doesn't know. Versions are
+    /// `[major, minor]`.
+    UnsupportedVersion {
+        found: [u8; 2],
+        supported: [u8; 2],
+    },
     UnderlyingDwtError(String),
     UnderlyingCompressorError(String),
     OutOfMemoryError(usize),
@@ -41,6 +48,40 @@ pub enum BiolepticError {
     UnsupportedCompressorConfiguration(String),
     DecompressionError(String),
     InvalidQuantizationScale(u8),
+    OutOfBounds(usize, usize, usize),
+    InvalidConfigCode(u32),
+    InvalidHeaderExtension,
+    InvalidBatchIndex(String),
+    /// Stored and computed CRC32 of the compressed payload differ.
+    ChecksumMismatch(u32, u32),
+}
+
+impl BiolepticError {
+    /// Returns a stable, machine-readable name of the variant, e.g. `"invalid_header"`.
+    ///
+    /// Unlike the display message, the kind never changes between releases, so bindings
+    /// can map it to exception subclasses or error names.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BiolepticError::InvalidCompressionMethod(_) => "invalid_compression_method",
+            BiolepticError::InvalidMagic(_) => "invalid_magic",
+            BiolepticError::InvalidDataType(_) => "invalid_data_type",
+            BiolepticError::InvalidVersion(_) => "invalid_version",
+            BiolepticError::UnsupportedVersion { .. } => "unsupported_version",
+            BiolepticError::UnderlyingDwtError(_) => "dwt_error",
+            BiolepticError::UnderlyingCompressorError(_) => "compressor_error",
+            BiolepticError::OutOfMemoryError(_) => "out_of_memory",
+            BiolepticError::InvalidHeader => "invalid_header",
+            BiolepticError::UnsupportedCompressorConfiguration(_) => "unsupported_configuration",
+            BiolepticError::DecompressionError(_) => "decompression_error",
+            BiolepticError::InvalidQuantizationScale(_) => "invalid_quantization_scale",
+            BiolepticError::OutOfBounds(..) => "out_of_bounds",
+            BiolepticError::InvalidConfigCode(_) => "invalid_config_code",
+            BiolepticError::InvalidHeaderExtension => "invalid_header_extension",
+            BiolepticError::InvalidBatchIndex(_) => "invalid_batch_index",
+            BiolepticError::ChecksumMismatch(..) => "checksum_mismatch",
+        }
+    }
 }
 
 impl Display for BiolepticError {
@@ -56,18 +97,26 @@ impl Display for BiolepticError {
             }
             BiolepticError::InvalidDataType(v) => {
                 let s = std::str::from_utf8(v).unwrap_or("??");
-                f.write_fmt(format_args!("Invalid compression method '{}'", s))
+                f.write_fmt(format_args!("Invalid data type '{}'", s))
             }
             BiolepticError::InvalidVersion(v) => {
                 let s = std::str::from_utf8(v).unwrap_or("??");
                 f.write_fmt(format_args!("Invalid header version '{}'", s))
             }
+            BiolepticError::UnsupportedVersion { found, supported } => f.write_fmt(format_args!(
+                "Format version {}.{} is newer than the supported {}.{}",
+                found[0], found[1], supported[0], supported[1]
+            )),
             BiolepticError::OutOfMemoryError(s) => f.write_fmt(format_args!(
                 "Out of memory, can't allocate additional {}",
                 s
             )),
             BiolepticError::UnderlyingDwtError(e) => f.write_fmt(format_args!("{}", e)),
             BiolepticError::InvalidHeader => f.write_str("Header is invalid"),
+            BiolepticError::InvalidHeaderExtension => f.write_str("Header extension is invalid"),
+            BiolepticError::InvalidBatchIndex(s) => {
+                f.write_fmt(format_args!("Batch index is invalid, reason: {}", s))
+            }
             BiolepticError::UnderlyingCompressorError(e) => f.write_fmt(format_args!("{}", e)),
             BiolepticError::UnsupportedCompressorConfiguration(s) => f.write_fmt(format_args!(
                 "Unsupported compression configuration '{}'",
@@ -77,8 +126,81 @@ impl Display for BiolepticError {
                 f.write_fmt(format_args!("Can't decompress data, reason: {}", s))
             }
             BiolepticError::InvalidQuantizationScale(s) => f.write_fmt(format_args!(
-                "Only scaled 6..12 is supported, but it was {s}"
+                "Only scales 6..20 are supported, but it was {s}"
             )),
+            BiolepticError::InvalidConfigCode(code) => {
+                f.write_fmt(format_args!("Invalid configuration code {code:#010x}"))
+            }
+            BiolepticError::OutOfBounds(start, len, total) => f.write_fmt(format_args!(
+                "Window of {len} samples at {start} is out of bounds for signal of length {total}"
+            )),
+            BiolepticError::ChecksumMismatch(stored, computed) => f.write_fmt(format_args!(
+                "Payload checksum {computed:#010x} doesn't match stored {stored:#010x}"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_kinds_stable_and_unique() {
+        let errors = [
+            (
+                BiolepticError::InvalidCompressionMethod(*b"xxxx"),
+                "invalid_compression_method",
+            ),
+            (BiolepticError::InvalidMagic(*b"xxxx"), "invalid_magic"),
+            (BiolepticError::InvalidDataType(*b"xx"), "invalid_data_type"),
+            (BiolepticError::InvalidVersion(*b"xx"), "invalid_version"),
+            (
+                BiolepticError::UnsupportedVersion {
+                    found: [0, 0],
+                    supported: [0, 0],
+                },
+                "unsupported_version",
+            ),
+            (
+                BiolepticError::UnderlyingDwtError(String::new()),
+                "dwt_error",
+            ),
+            (
+                BiolepticError::UnderlyingCompressorError(String::new()),
+                "compressor_error",
+            ),
+            (BiolepticError::OutOfMemoryError(0), "out_of_memory"),
+            (BiolepticError::InvalidHeader, "invalid_header"),
+            (
+                BiolepticError::UnsupportedCompressorConfiguration(String::new()),
+                "unsupported_configuration",
+            ),
+            (
+                BiolepticError::DecompressionError(String::new()),
+                "decompression_error",
+            ),
+            (
+                BiolepticError::InvalidQuantizationScale(0),
+                "invalid_quantization_scale",
+            ),
+            (BiolepticError::OutOfBounds(0, 0, 0), "out_of_bounds"),
+            (BiolepticError::InvalidConfigCode(0), "invalid_config_code"),
+            (
+                BiolepticError::InvalidHeaderExtension,
+                "invalid_header_extension",
+            ),
+            (
+                BiolepticError::InvalidBatchIndex(String::new()),
+                "invalid_batch_index",
+            ),
+            (BiolepticError::ChecksumMismatch(0, 0), "checksum_mismatch"),
+        ];
+        let mut seen = HashSet::new();
+        for (error, kind) in errors.iter() {
+            assert_eq!(error.kind(), *kind);
+            assert!(seen.insert(error.kind()), "duplicate kind {kind}");
         }
     }
 }
@@ -0,0 +1,345 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::header::FLAG_EXTENDED;
+use crate::{BIOLEPTIC_HEADER_SIZE, BiolepticError, BiolepticHeader};
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::io::{Read, Write};
+
+/// Min/max envelope track, see [`crate::CompressionOptions::with_envelope`].
+pub(crate) const ENVELOPE_TAG: [u8; 4] = *b"envl";
+/// Counts of substituted NaN, +inf and -inf samples.
+pub(crate) const NON_FINITE_TAG: [u8; 4] = *b"nfin";
+/// Deflated positions and kinds of the non-finite samples, see
+/// [`crate::CompressionOptions::preserve_nonfinite`].
+pub(crate) const NON_FINITE_MAP_TAG: [u8; 4] = *b"nfmp";
+/// One quantization scale per coefficient band, see
+/// [`crate::CompressionOptions::level_scales`].
+pub(crate) const LEVEL_SCALES_TAG: [u8; 4] = *b"lvsc";
+/// `f64` mean removed from a [`crate::DataType::Float64`] signal before encoding.
+pub(crate) const FLOAT64_OFFSET_TAG: [u8; 4] = *b"f64o";
+/// Caller-supplied provenance bytes, see [`crate::compress_with_metadata`].
+pub(crate) const METADATA_TAG: [u8; 4] = *b"meta";
+/// Per-sample corrections bounding the reconstruction error, see
+/// [`crate::compress_with_max_error`].
+pub(crate) const RESIDUAL_TAG: [u8; 4] = *b"resd";
+/// Opaque experiment bytes, see [`crate::CompressionOptions::reserved`].
+pub(crate) const RESERVED_TAG: [u8; 4] = *b"rsvd";
+
+/// Collects tagged entries of the optional extension section.
+///
+/// The section follows the compressed payload and is laid out as a `u32` LE byte length
+/// followed by entries of a 4-byte tag, a `u32` LE entry length and the entry bytes.
+#[derive(Default)]
+pub(crate) struct ExtensionWriter {
+    entries: Vec<u8>,
+}
+
+impl ExtensionWriter {
+    /// Starts from the raw entries of an existing section, see [`extension_section`].
+    pub(crate) fn from_entries(entries: &[u8]) -> Self {
+        ExtensionWriter {
+            entries: entries.to_vec(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, tag: [u8; 4], data: &[u8]) {
+        self.entries.extend_from_slice(&tag);
+        self.entries
+            .extend_from_slice(&(data.len() as u32).to_le_bytes());
+        self.entries.extend_from_slice(data);
+    }
+
+    /// Returns the serialized section, or nothing if no entries were pushed.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        if self.entries.is_empty() {
+            return Vec::new();
+        }
+        let mut section = Vec::with_capacity(self.entries.len() + 4);
+        section.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        section.extend_from_slice(&self.entries);
+        section
+    }
+}
+
+/// Returns the raw entries of the extension section, excluding its length prefix.
+pub(crate) fn extension_section<'a>(
+    bytes: &'a [u8],
+    header: &BiolepticHeader,
+) -> Result<&'a [u8], BiolepticError> {
+    if header.flags & FLAG_EXTENDED == 0 {
+        return Ok(&[]);
+    }
+    let start = BIOLEPTIC_HEADER_SIZE + header.compressed_size as usize;
+    let length_bytes = bytes
+        .get(start..start + 4)
+        .ok_or(BiolepticError::InvalidHeaderExtension)?;
+    let length = u32::from_le_bytes(length_bytes.try_into().unwrap()) as usize;
+    bytes
+        .get(start + 4..start + 4 + length)
+        .ok_or(BiolepticError::InvalidHeaderExtension)
+}
+
+/// Returns the total byte length of the record starting at `bytes`, including the payload
+/// and the extension section, so consecutive records can be walked.
+pub(crate) fn record_length(
+    bytes: &[u8],
+    header: &BiolepticHeader,
+) -> Result<usize, BiolepticError> {
+    let payload_end = BIOLEPTIC_HEADER_SIZE + header.compressed_size as usize;
+    if bytes.len() < payload_end {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Minimum data size is {}, but it was {}",
+            payload_end,
+            bytes.len(),
+        )));
+    }
+    if header.flags & FLAG_EXTENDED == 0 {
+        return Ok(payload_end);
+    }
+    Ok(payload_end + 4 + extension_section(bytes, header)?.len())
+}
+
+/// Finds the entry with `tag` in the extension section, if present.
+pub(crate) fn find_extension<'a>(
+    bytes: &'a [u8],
+    header: &BiolepticHeader,
+    tag: [u8; 4],
+) -> Result<Option<&'a [u8]>, BiolepticError> {
+    let mut section = extension_section(bytes, header)?;
+    while !section.is_empty() {
+        if section.len() < 8 {
+            return Err(BiolepticError::InvalidHeaderExtension);
+        }
+        let entry_tag: [u8; 4] = section[0..4].try_into().unwrap();
+        let length = u32::from_le_bytes(section[4..8].try_into().unwrap()) as usize;
+        let data = section
+            .get(8..8 + length)
+            .ok_or(BiolepticError::InvalidHeaderExtension)?;
+        if entry_tag == tag {
+            return Ok(Some(data));
+        }
+        section = &section[8 + length..];
+    }
+    Ok(None)
+}
+
+/// Computes one `(min, max)` pair per `decimation` samples, serialized as
+/// the `u32` decimation followed by `f32` LE pairs.
+pub(crate) fn encode_envelope(data: &[f32], decimation: u32) -> Vec<u8> {
+    let buckets = data.chunks(decimation as usize);
+    let mut encoded = Vec::with_capacity(4 + buckets.len() * 8);
+    encoded.extend_from_slice(&decimation.to_le_bytes());
+    for bucket in buckets {
+        let (v_min, v_max) = bucket
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+                (v.min(lo), v.max(hi))
+            });
+        encoded.extend_from_slice(&v_min.to_le_bytes());
+        encoded.extend_from_slice(&v_max.to_le_bytes());
+    }
+    encoded
+}
+
+pub(crate) fn decode_envelope(data: &[u8]) -> Result<Vec<(f32, f32)>, BiolepticError> {
+    if data.len() < 4 || !(data.len() - 4).is_multiple_of(8) {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok(data[4..]
+        .chunks_exact(8)
+        .map(|x| {
+            (
+                f32::from_le_bytes(x[0..4].try_into().unwrap()),
+                f32::from_le_bytes(x[4..8].try_into().unwrap()),
+            )
+        })
+        .collect())
+}
+
+/// Serializes the NaN, +inf and -inf substitution counts as three `u32` LE values.
+pub(crate) fn encode_non_finite_counts(counts: (u32, u32, u32)) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(12);
+    encoded.extend_from_slice(&counts.0.to_le_bytes());
+    encoded.extend_from_slice(&counts.1.to_le_bytes());
+    encoded.extend_from_slice(&counts.2.to_le_bytes());
+    encoded
+}
+
+pub(crate) fn decode_non_finite_counts(data: &[u8]) -> Result<(u32, u32, u32), BiolepticError> {
+    if data.len() != 12 {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok((
+        u32::from_le_bytes(data[0..4].try_into().unwrap()),
+        u32::from_le_bytes(data[4..8].try_into().unwrap()),
+        u32::from_le_bytes(data[8..12].try_into().unwrap()),
+    ))
+}
+
+pub(crate) fn decode_float64_offset(data: &[u8]) -> Result<f64, BiolepticError> {
+    let offset = f64::from_le_bytes(
+        data.try_into()
+            .map_err(|_| BiolepticError::InvalidHeaderExtension)?,
+    );
+    if !offset.is_finite() {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok(offset)
+}
+
+/// Packs a 2-bit code per sample, `0` finite, `1` NaN, `2` +inf and `3` -inf, four samples
+/// per byte, and deflates it. Dropouts come in bursts, so the map mostly compresses away.
+pub(crate) fn encode_non_finite_map(data: &[f32], level: u32) -> Result<Vec<u8>, BiolepticError> {
+    let mut packed = vec![0u8; data.len().div_ceil(4)];
+    for (i, &v) in data.iter().enumerate() {
+        let code = if v.is_finite() {
+            0
+        } else if v.is_nan() {
+            1
+        } else if v.is_sign_positive() {
+            2
+        } else {
+            3
+        };
+        packed[i / 4] |= code << ((i % 4) * 2);
+    }
+    let mut e = DeflateEncoder::new(Vec::new(), Compression::new(level));
+    e.write_all(&packed)
+        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+    e.finish()
+        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))
+}
+
+/// Writes the NaN and infinity values recorded by [`encode_non_finite_map`] back into
+/// `signal`.
+pub(crate) fn restore_non_finite(data: &[u8], signal: &mut [f32]) -> Result<(), BiolepticError> {
+    let expected = signal.len().div_ceil(4);
+    let mut packed = Vec::with_capacity(expected);
+    DeflateDecoder::new(data)
+        .take(expected as u64 + 1)
+        .read_to_end(&mut packed)
+        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+    if packed.len() != expected {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    for (i, v) in signal.iter_mut().enumerate() {
+        match (packed[i / 4] >> ((i % 4) * 2)) & 0b11 {
+            1 => *v = f32::NAN,
+            2 => *v = f32::INFINITY,
+            3 => *v = f32::NEG_INFINITY,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Serializes residual corrections as the `f32` LE step followed by the deflated zigzag
+/// LEB128 multiples of it, one per sample. Most corrections are zero and deflate away.
+pub(crate) fn encode_residual(
+    step: f32,
+    corrections: &[i32],
+    level: u32,
+) -> Result<Vec<u8>, BiolepticError> {
+    let mut varints = Vec::with_capacity(corrections.len());
+    for &q in corrections {
+        let mut v = ((q << 1) ^ (q >> 31)) as u32;
+        while v >= 0x80 {
+            varints.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        varints.push(v as u8);
+    }
+    let mut e = DeflateEncoder::new(step.to_le_bytes().to_vec(), Compression::new(level));
+    e.write_all(&varints)
+        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+    e.finish()
+        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))
+}
+
+/// Decodes the corrections written by [`encode_residual`], one per sample of a
+/// `signal_length` long signal, returning them with the step.
+pub(crate) fn decode_residual(
+    data: &[u8],
+    signal_length: usize,
+) -> Result<(f32, Vec<i32>), BiolepticError> {
+    if data.len() < 4 {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    let step = f32::from_le_bytes(data[0..4].try_into().unwrap());
+    // A 32-bit varint takes at most 5 bytes.
+    let max_size = signal_length * 5;
+    let mut varints = Vec::new();
+    DeflateDecoder::new(&data[4..])
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut varints)
+        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+    let mut corrections = Vec::with_capacity(signal_length);
+    let mut v = 0u32;
+    let mut shift = 0u32;
+    for &byte in varints.iter() {
+        if shift > 28 {
+            return Err(BiolepticError::InvalidHeaderExtension);
+        }
+        v |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            corrections.push((v >> 1) as i32 ^ -((v & 1) as i32));
+            v = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    if shift != 0 || corrections.len() != signal_length {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok((step, corrections))
+}
+
+/// Adds `correction * step * gain` to every sample.
+pub(crate) fn apply_residual(signal: &mut [f32], step: f32, corrections: &[i32], gain: f32) {
+    let step = step * gain;
+    for (v, &q) in signal.iter_mut().zip(corrections.iter()) {
+        *v += q as f32 * step;
+    }
+}
+
+/// Returns the scale shifts of the approximation followed by every detail level, finest
+/// first, checking there is one per band of a `levels` deep transform.
+pub(crate) fn decode_level_scales(data: &[u8], levels: usize) -> Result<&[u8], BiolepticError> {
+    if data.len() != levels + 1 {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok(data)
+}
@@ -26,26 +26,74 @@
  * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
  * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
-use crate::compressor::QuantizationScale;
+use crate::compressor::{
+    BorderMode, CoefficientWidth, DequantMode, PayloadEncoding, PreTransform, QuantizationScale,
+    ThresholdMode,
+};
+use crate::decompressor::{check_signal_length, level_sizes};
+use crate::entropy::EntropyCoder;
 use crate::error::BiolepticError;
+use crate::extension::{
+    ENVELOPE_TAG, METADATA_TAG, NON_FINITE_TAG, RESERVED_TAG, decode_envelope,
+    decode_non_finite_counts, find_extension,
+};
+use crate::lossless::lossless_layout;
+use crate::pretransform::{LOG_TAG, POLY_TAG, decode_log_factor, decode_polynomial};
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum CompressionMethod {
     Cdf53,
     Cdf97,
     Db4,
     Sym4,
+    Db2,
+    Db6,
+    Db8,
+    Coif1,
+    Coif2,
+    Coif3,
+    Sym6,
+    Sym8,
+    /// Biorthogonal 2.2, the shortest of the added biorthogonal filters.
+    Bior22,
+    /// Biorthogonal 4.4.
+    Bior44,
+    /// Biorthogonal 6.8, long filters that keep sharp transients such as EEG spikes.
+    Bior68,
 }
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
 pub enum DataType {
     Float32,
+    /// Written by [`crate::compress_f64`]. The mean is removed in `f64` and stored in the
+    /// extension section, so `min`, `max` and `mean` in the header describe the `f32`
+    /// residual around it rather than the original samples.
+    Float64,
+    /// Written by [`crate::compress_i16`], e.g. for raw ADC samples. Decoded with
+    /// [`crate::decompress_i16`], which rounds back to integers.
+    Int16,
 }
 
 const CDF53: u32 = u32::from_le_bytes(*b"cf53");
 const CDF97: u32 = u32::from_le_bytes(*b"cf97");
 const DB4: u32 = u32::from_le_bytes(*b"db04");
 const SYM4: u32 = u32::from_le_bytes(*b"sym4");
+const DB2: u32 = u32::from_le_bytes(*b"db02");
+const DB6: u32 = u32::from_le_bytes(*b"db06");
+const DB8: u32 = u32::from_le_bytes(*b"db08");
+const COIF1: u32 = u32::from_le_bytes(*b"cof1");
+const COIF2: u32 = u32::from_le_bytes(*b"cof2");
+const COIF3: u32 = u32::from_le_bytes(*b"cof3");
+const SYM6: u32 = u32::from_le_bytes(*b"sym6");
+const SYM8: u32 = u32::from_le_bytes(*b"sym8");
+const BIOR22: u32 = u32::from_le_bytes(*b"bi22");
+const BIOR44: u32 = u32::from_le_bytes(*b"bi44");
+const BIOR68: u32 = u32::from_le_bytes(*b"bi68");
 
 impl TryFrom<u32> for CompressionMethod {
     type Error = BiolepticError;
@@ -55,8 +103,19 @@ impl TryFrom<u32> for CompressionMethod {
             CDF53 => Ok(CompressionMethod::Cdf53),
             DB4 => Ok(CompressionMethod::Db4),
             SYM4 => Ok(CompressionMethod::Sym4),
+            DB2 => Ok(CompressionMethod::Db2),
+            DB6 => Ok(CompressionMethod::Db6),
+            DB8 => Ok(CompressionMethod::Db8),
+            COIF1 => Ok(CompressionMethod::Coif1),
+            COIF2 => Ok(CompressionMethod::Coif2),
+            COIF3 => Ok(CompressionMethod::Coif3),
+            SYM6 => Ok(CompressionMethod::Sym6),
+            SYM8 => Ok(CompressionMethod::Sym8),
+            BIOR22 => Ok(CompressionMethod::Bior22),
+            BIOR44 => Ok(CompressionMethod::Bior44),
+            BIOR68 => Ok(CompressionMethod::Bior68),
             _ => Err(BiolepticError::InvalidCompressionMethod(
-                value.to_ne_bytes(),
+                value.to_le_bytes(),
             )),
         }
     }
@@ -69,11 +128,24 @@ impl From<CompressionMethod> for u32 {
             CompressionMethod::Cdf97 => CDF97,
             CompressionMethod::Db4 => DB4,
             CompressionMethod::Sym4 => SYM4,
+            CompressionMethod::Db2 => DB2,
+            CompressionMethod::Db6 => DB6,
+            CompressionMethod::Db8 => DB8,
+            CompressionMethod::Coif1 => COIF1,
+            CompressionMethod::Coif2 => COIF2,
+            CompressionMethod::Coif3 => COIF3,
+            CompressionMethod::Sym6 => SYM6,
+            CompressionMethod::Sym8 => SYM8,
+            CompressionMethod::Bior22 => BIOR22,
+            CompressionMethod::Bior44 => BIOR44,
+            CompressionMethod::Bior68 => BIOR68,
         }
     }
 }
 
 const FLOAT_32: u16 = u16::from_le_bytes(*b"f3");
+const FLOAT_64: u16 = u16::from_le_bytes(*b"f6");
+const INT_16: u16 = u16::from_le_bytes(*b"i1");
 
 impl TryFrom<u16> for DataType {
     type Error = BiolepticError;
@@ -81,7 +153,9 @@ impl TryFrom<u16> for DataType {
     fn try_from(value: u16) -> Result<Self, BiolepticError> {
         match value {
             FLOAT_32 => Ok(DataType::Float32),
-            _ => Err(BiolepticError::InvalidDataType(value.to_ne_bytes())),
+            FLOAT_64 => Ok(DataType::Float64),
+            INT_16 => Ok(DataType::Int16),
+            _ => Err(BiolepticError::InvalidDataType(value.to_le_bytes())),
         }
     }
 }
@@ -90,19 +164,183 @@ impl From<DataType> for u16 {
     fn from(val: DataType) -> Self {
         match val {
             DataType::Float32 => FLOAT_32,
+            DataType::Float64 => FLOAT_64,
+            DataType::Int16 => INT_16,
         }
     }
 }
 
 pub const BIOLEPTIC_MAGIC: [u8; 4] = *b"BILP";
 
-/// Current format version.
+/// Current format version, stored as a major byte followed by a minor byte.
 pub const BIOLEPTIC_VERSION: u16 = u16::from_le_bytes([1, 0]);
 
+/// Accepts the current version, telling a newer minor revision of the same major format
+/// apart from bytes that are no known version at all.
+pub(crate) fn check_version(version: u16) -> Result<(), BiolepticError> {
+    if version == BIOLEPTIC_VERSION {
+        return Ok(());
+    }
+    let [major, minor] = version.to_le_bytes();
+    let [supported_major, supported_minor] = BIOLEPTIC_VERSION.to_le_bytes();
+    if major == supported_major && minor > supported_minor {
+        return Err(BiolepticError::UnsupportedVersion {
+            found: [major, minor],
+            supported: [supported_major, supported_minor],
+        });
+    }
+    Err(BiolepticError::InvalidVersion(version.to_le_bytes()))
+}
+
+/// Header flag: the signal is exactly zero and no payload is stored.
+pub(crate) const FLAG_ZERO_SIGNAL: u16 = 1 << 0;
+/// Header flag: an extension section follows the compressed payload.
+pub(crate) const FLAG_EXTENDED: u16 = 1 << 1;
+/// Header flag: coefficients are stored as `i32` instead of `i16`.
+pub(crate) const FLAG_WIDE_COEFFICIENTS: u16 = 1 << 2;
+/// Header flag: coefficients are reconstructed at the center of their quantization bin.
+pub(crate) const FLAG_MIDPOINT_DEQUANT: u16 = 1 << 6;
+/// Header flag: the signal was differenced before the DWT and is integrated on decode.
+pub(crate) const FLAG_DIFFERENCE: u16 = 1 << 7;
+/// Header flag: detail coefficients were soft thresholded.
+pub(crate) const FLAG_SOFT_THRESHOLD: u16 = 1 << 8;
+/// Header flag: integer samples were transformed losslessly, see
+/// [`crate::CompressionOptions::lossless`].
+pub(crate) const FLAG_LOSSLESS: u16 = 1 << 9;
+/// Header flag: the payload holds the difference to a reference signal, see
+/// [`crate::compress_delta`].
+pub(crate) const FLAG_DELTA: u16 = 1 << 14;
+/// Header flag: the payload holds the records of independently compressed rows, see
+/// [`crate::compress_rows`].
+pub(crate) const FLAG_ROWS: u16 = 1 << 15;
+/// Header flags bits 3..6 hold the [`PayloadEncoding`].
+const PAYLOAD_ENCODING_SHIFT: u16 = 3;
+const PAYLOAD_ENCODING_MASK: u16 = 0b111 << PAYLOAD_ENCODING_SHIFT;
+/// Header flags bits 12..14 hold the [`EntropyCoder`].
+const ENTROPY_CODER_SHIFT: u16 = 12;
+const ENTROPY_CODER_MASK: u16 = 0b11 << ENTROPY_CODER_SHIFT;
+/// Header flags bits 10..12 hold the [`BorderMode`].
+const BORDER_MODE_SHIFT: u16 = 10;
+const BORDER_MODE_MASK: u16 = 0b11 << BORDER_MODE_SHIFT;
+
+/// Number of coefficients stored per DWT level, as laid out in the payload.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CoefficientLayout {
+    /// Length of the coarsest approximation, stored first.
+    pub approximation_length: usize,
+    /// Detail lengths from the finest level to the coarsest, in payload order after the
+    /// approximation.
+    pub detail_lengths: Vec<usize>,
+}
+
+impl CoefficientLayout {
+    /// Total number of stored coefficients.
+    pub fn total(&self) -> usize {
+        self.approximation_length + self.detail_lengths.iter().sum::<usize>()
+    }
+}
+
+/// Reads and validates only the header of an encoded stream, e.g. to catalog files by
+/// length, method and value range without decoding the payload.
+pub fn read_header(bytes: &[u8]) -> Result<BiolepticHeader, BiolepticError> {
+    BiolepticHeader::from_bytes(bytes)
+}
+
+/// Returns the metadata stored by [`crate::compress_with_metadata`], `None` when the
+/// stream has none. Only the header and the extension section are read.
+pub fn read_metadata(bytes: &[u8]) -> Result<Option<Vec<u8>>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    Ok(find_extension(bytes, &header, METADATA_TAG)?.map(|x| x.to_vec()))
+}
+
+/// Returns the bytes stored with [`crate::CompressionOptions::reserved`], `None` when the
+/// stream has none. Only the header and the extension section are read.
+pub fn read_reserved(bytes: &[u8]) -> Result<Option<[u8; 16]>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    find_extension(bytes, &header, RESERVED_TAG)?
+        .map(|x| {
+            x.try_into()
+                .map_err(|_| BiolepticError::InvalidHeaderExtension)
+        })
+        .transpose()
+}
+
+/// Summarizes an encoded stream in one line, e.g. for CLI tools and logs:
+/// `BILP v1.0 cdf97 500000 samples, 5 levels, scale 11, payload 12345 B, ratio 162.0x`.
+///
+/// The ratio relates the size of the raw samples in their original data type to the whole
+/// of `bytes`. Only the header is parsed, the payload isn't checked.
+pub fn describe(bytes: &[u8]) -> Result<String, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    let [major, minor] = header.version.to_le_bytes();
+    let method = format!("{:?}", header.compression_method()?).to_lowercase();
+    let sample_size = match header.data_type()? {
+        DataType::Float32 => 4,
+        DataType::Float64 => 8,
+        DataType::Int16 => 2,
+    };
+    let raw_size = header.signal_length as usize * header.channels as usize * sample_size;
+
+    let mut summary = format!("BILP v{major}.{minor} {method} {} samples", {
+        header.signal_length
+    });
+    if header.channels > 1 {
+        summary += &format!(" x {} channels", { header.channels });
+    }
+    summary += &format!(", {} levels", { header.levels });
+    if header.is_lossless() {
+        summary += ", lossless";
+    } else {
+        summary += &format!(", scale {}", { header.scale });
+    }
+    summary += &format!(
+        ", payload {} B, ratio {:.1}x",
+        { header.compressed_size },
+        raw_size as f64 / bytes.len() as f64
+    );
+    Ok(summary)
+}
+
+/// CRC32 of the compressed payload as stored in [`BiolepticHeader::payload_crc`].
+pub(crate) fn payload_crc(payload: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(payload);
+    crc.sum()
+}
+
+/// CRC-16/CCITT-FALSE of a serialized header, skipping the checksum field at `46..48`.
+fn header_crc(buf: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for &byte in buf[..46]
+        .iter()
+        .chain(buf[48..BIOLEPTIC_HEADER_SIZE].iter())
+    {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Verifies the header checksum of a serialized header, accepting headers without one.
+pub(crate) fn check_header_crc(buf: &[u8]) -> Result<(), BiolepticError> {
+    let stored = u16::from_le_bytes(buf[46..48].try_into().unwrap());
+    if stored != 0 && stored != header_crc(buf) {
+        return Err(BiolepticError::InvalidHeader);
+    }
+    Ok(())
+}
+
 /// Fixed size of the header in bytes.
 pub const BIOLEPTIC_HEADER_SIZE: usize = size_of::<BiolepticHeader>();
 
 #[repr(C, packed)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BiolepticHeader {
     /// Magic bytes identifying the format: `b"BILP"`.
     pub magic: [u8; 4],
@@ -115,10 +353,17 @@ pub struct BiolepticHeader {
     /// Number of DWT decomposition levels applied during compression.
     pub levels: u8,
     /// Quantization scale factor — DWT coefficients are multiplied by `1 << scale`
-    /// before being cast to `i16`.
+    /// before being cast to `i16` (or `i32` for wide coefficients).
     pub scale: u8,
-    /// Reserved for future use — must be zero.
-    pub reserved0: [u8; 2],
+    /// Bit flags describing how the payload was produced, stored little-endian.
+    /// Bit 0 marks an all-zero signal that is reconstructed without a payload,
+    /// bit 1 marks an extension section following the payload, bit 2 marks `i32`
+    /// coefficients, bits 3..6 hold the payload encoding, bit 6 selects midpoint
+    /// dequantization, bit 7 marks the difference pre-transform, bit 8 marks soft
+    /// thresholding, bit 9 marks the lossless integer transform, bits 10..12 hold the
+    /// border mode, bits 12..14 hold the entropy coder, bit 14 marks delta coding against
+    /// a reference signal, bit 15 marks a record of compressed rows.
+    pub flags: u16,
     /// Number of samples in the original signal before compression.
     pub signal_length: u32,
     /// Minimum value of the signal after non-finite substitution, stored as `f32` bits
@@ -137,8 +382,24 @@ pub struct BiolepticHeader {
     /// to allocate exactly the right buffer and detect truncated streams without having
     /// to rely on EOF.
     pub compressed_size: u32,
-    /// Reserved for future use — must be zero.
-    pub reserved1: [u8; 16],
+    /// Number of interleaved channels in the signal, always at least 1.
+    pub channels: u8,
+    /// Producer-assigned quality score, carried verbatim and never interpreted by the codec.
+    pub quality_tag: u8,
+    /// Sample rate of the original signal in Hz, stored as `f32` bits via `f32::to_bits()`.
+    /// Zero when the producer didn't record it.
+    pub sample_rate: u32,
+    /// CRC32 of the compressed payload, verified on decode. Zero in streams written
+    /// before it was introduced, which are decoded unchecked.
+    pub payload_crc: u32,
+    /// CRC-16/CCITT-FALSE of every other header byte, computed by
+    /// [`BiolepticHeader::to_bytes`] and verified by [`BiolepticHeader::from_bytes`]. Zero in
+    /// streams written before it was introduced, which are read unchecked.
+    pub header_crc: u16,
+    /// Reserved for future use, zero as written by the encoder. Carried verbatim and
+    /// ignored by the decoder. User bytes go through [`crate::CompressionOptions::reserved`]
+    /// instead, since these 4 are all the fixed header has left.
+    pub reserved1: [u8; 4],
 }
 
 impl BiolepticHeader {
@@ -163,13 +424,18 @@ impl BiolepticHeader {
             compression_method: compression_method_impl.to_le_bytes(),
             levels,
             scale: scale.as_u8(),
-            reserved0: [0; 2],
+            flags: 0,
             signal_length,
             min: min.to_bits(),
             max: max.to_bits(),
             mean: mean.to_bits(),
-            reserved1: [0; 16],
             compressed_size,
+            channels: 1,
+            quality_tag: 0,
+            sample_rate: 0,
+            payload_crc: 0,
+            header_crc: 0,
+            reserved1: [0; 4],
         }
     }
 
@@ -182,17 +448,28 @@ impl BiolepticHeader {
         buf[8..12].copy_from_slice(&self.compression_method);
         buf[12] = self.levels;
         buf[13] = self.scale;
-        buf[14..16].copy_from_slice(&self.reserved0); // reserved0
+        buf[14..16].copy_from_slice(&self.flags.to_le_bytes());
         buf[16..20].copy_from_slice(&self.signal_length.to_le_bytes());
         buf[20..24].copy_from_slice(&self.min.to_le_bytes());
         buf[24..28].copy_from_slice(&self.max.to_le_bytes());
         buf[28..32].copy_from_slice(&self.mean.to_le_bytes());
         buf[32..36].copy_from_slice(&self.compressed_size.to_le_bytes());
-        buf[36..52].copy_from_slice(&self.reserved1);
+        buf[36] = self.channels;
+        buf[37] = self.quality_tag;
+        buf[38..42].copy_from_slice(&self.sample_rate.to_le_bytes());
+        buf[42..46].copy_from_slice(&self.payload_crc.to_le_bytes());
+        buf[48..52].copy_from_slice(&self.reserved1);
+        let crc = header_crc(&buf);
+        buf[46..48].copy_from_slice(&crc.to_le_bytes());
         buf
     }
 
     /// Deserializes a header from bytes, validating magic and version.
+    ///
+    /// Records of a newer minor version fail with [`BiolepticError::UnsupportedVersion`],
+    /// any other version mismatch with [`BiolepticError::InvalidVersion`]. A header whose
+    /// bytes don't match [`BiolepticHeader::header_crc`] fails with
+    /// [`BiolepticError::InvalidHeader`].
     pub fn from_bytes(buf: &[u8]) -> Result<Self, BiolepticError> {
         if buf.len() < BIOLEPTIC_HEADER_SIZE {
             return Err(BiolepticError::InvalidHeader);
@@ -206,9 +483,7 @@ impl BiolepticHeader {
         }
 
         let version = u16::from_le_bytes(buf[4..6].try_into().unwrap());
-        if version != BIOLEPTIC_VERSION {
-            return Err(BiolepticError::InvalidVersion(version.to_ne_bytes()));
-        }
+        check_version(version)?;
 
         let data_type = u16::from_le_bytes(buf[6..8].try_into().unwrap());
         let compression_method = u32::from_le_bytes(buf[8..12].try_into().unwrap());
@@ -222,8 +497,14 @@ impl BiolepticHeader {
         let f_max = u32::from_le_bytes(buf[24..28].try_into().unwrap());
         let f_mean = u32::from_le_bytes(buf[28..32].try_into().unwrap());
 
+        let sample_rate = u32::from_le_bytes(buf[38..42].try_into().unwrap());
+        let f_sample_rate = f32::from_bits(sample_rate);
+        if sample_rate != 0 && !(f_sample_rate.is_normal() && f_sample_rate > 0.) {
+            return Err(BiolepticError::InvalidHeader);
+        }
+
         match v_data_type {
-            DataType::Float32 => {
+            DataType::Float32 | DataType::Float64 | DataType::Int16 => {
                 if !f32::from_bits(f_min).is_finite() {
                     return Err(BiolepticError::InvalidHeader);
                 }
@@ -236,6 +517,8 @@ impl BiolepticHeader {
             }
         }
 
+        check_header_crc(buf)?;
+
         Ok(Self {
             magic,
             version,
@@ -243,16 +526,86 @@ impl BiolepticHeader {
             compression_method: buf[8..12].try_into().unwrap(),
             levels: buf[12],
             scale: buf[13],
-            reserved0: buf[14..16].try_into().unwrap(),
+            flags: u16::from_le_bytes(buf[14..16].try_into().unwrap()),
             signal_length: u32::from_le_bytes(buf[16..20].try_into().unwrap()),
             min: f_min,
             max: f_max,
             mean: f_mean,
             compressed_size: u32::from_le_bytes(buf[32..36].try_into().unwrap()),
-            reserved1: buf[36..52].try_into().unwrap(),
+            // v1.0 records written before the channel count existed hold zero there.
+            channels: buf[36].max(1),
+            quality_tag: buf[37],
+            sample_rate,
+            payload_crc: u32::from_le_bytes(buf[42..46].try_into().unwrap()),
+            header_crc: u16::from_le_bytes(buf[46..48].try_into().unwrap()),
+            reserved1: buf[48..52].try_into().unwrap(),
         })
     }
 
+    /// Returns the number of samples per channel of the original signal.
+    pub fn signal_length(&self) -> usize {
+        self.signal_length as usize
+    }
+
+    /// Returns the number of DWT decomposition levels.
+    pub fn levels(&self) -> u8 {
+        self.levels
+    }
+
+    /// Returns the quantization scale.
+    pub fn scale(&self) -> Result<QuantizationScale, BiolepticError> {
+        QuantizationScale::try_from(self.scale)
+    }
+
+    /// Returns the number of channels in the signal.
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    /// Returns the quality score set with [`crate::CompressionOptions::with_quality_tag`],
+    /// `0` when the producer didn't set one.
+    pub fn quality_tag(&self) -> u8 {
+        self.quality_tag
+    }
+
+    /// Returns the 4 spare header bytes 48..52, zero as written by the encoder and ignored
+    /// by the decoder. The 16 bytes of [`crate::CompressionOptions::reserved`] are stored in
+    /// an extension entry instead, read them with [`crate::read_reserved`].
+    pub fn reserved1(&self) -> [u8; 4] {
+        self.reserved1
+    }
+
+    /// Gives mutable access to the reserved bytes, e.g. to patch them before
+    /// [`BiolepticHeader::to_bytes`].
+    pub fn reserved_mut(&mut self) -> &mut [u8; 4] {
+        &mut self.reserved1
+    }
+
+    /// Replaces the reserved bytes.
+    pub fn set_reserved1(&mut self, reserved: [u8; 4]) {
+        self.reserved1 = reserved;
+    }
+
+    /// Returns the sample rate in Hz set with [`crate::CompressionOptions::with_sample_rate`],
+    /// or `None` when the producer didn't record it.
+    pub fn sample_rate(&self) -> Option<f32> {
+        let rate = f32::from_bits(self.sample_rate);
+        (rate > 0.).then_some(rate)
+    }
+
+    /// Checks `payload` against the stored CRC32, streams without one pass unchecked.
+    pub(crate) fn verify_payload(&self, payload: &[u8]) -> Result<(), BiolepticError> {
+        let stored = self.payload_crc;
+        if stored == 0 {
+            return Ok(());
+        }
+        let computed = payload_crc(payload);
+        if computed != stored {
+            return Err(BiolepticError::ChecksumMismatch(stored, computed));
+        }
+        Ok(())
+    }
+
     /// Returns the normalization min as `f32`.
     pub fn min_f32(&self) -> f32 {
         f32::from_bits(self.min)
@@ -268,6 +621,186 @@ impl BiolepticHeader {
         f32::from_bits(self.mean)
     }
 
+    /// Returns `true` if the stream encodes an all-zero signal without a payload.
+    pub fn is_zero_signal(&self) -> bool {
+        self.flags & FLAG_ZERO_SIGNAL != 0
+    }
+
+    /// Returns the decimated `(min, max)` envelope stored alongside the signal, if any.
+    ///
+    /// `bytes` is the full encoded stream this header was read from, since the envelope
+    /// lives in the extension section after the payload.
+    pub fn envelope(&self, bytes: &[u8]) -> Result<Option<Vec<(f32, f32)>>, BiolepticError> {
+        find_extension(bytes, self, ENVELOPE_TAG)?
+            .map(decode_envelope)
+            .transpose()
+    }
+
+    /// Returns how many NaN, +inf and -inf samples were substituted during compression.
+    ///
+    /// `bytes` is the full encoded stream this header was read from, streams without
+    /// non-finite samples report `(0, 0, 0)`.
+    pub fn non_finite_counts(&self, bytes: &[u8]) -> Result<(u32, u32, u32), BiolepticError> {
+        Ok(find_extension(bytes, self, NON_FINITE_TAG)?
+            .map(decode_non_finite_counts)
+            .transpose()?
+            .unwrap_or((0, 0, 0)))
+    }
+
+    /// Returns `true` if coefficients are stored as `i32`.
+    pub fn has_wide_coefficients(&self) -> bool {
+        self.flags & FLAG_WIDE_COEFFICIENTS != 0
+    }
+
+    /// Returns the width coefficients are stored with.
+    pub fn coefficient_width(&self) -> CoefficientWidth {
+        if self.has_wide_coefficients() {
+            CoefficientWidth::I32
+        } else {
+            CoefficientWidth::I16
+        }
+    }
+
+    /// Returns how quantized coefficients are mapped back to real values.
+    pub fn dequant_mode(&self) -> DequantMode {
+        if self.flags & FLAG_MIDPOINT_DEQUANT != 0 {
+            DequantMode::Midpoint
+        } else {
+            DequantMode::Truncate
+        }
+    }
+
+    /// Returns `true` if the stream holds integer lifting coefficients that reconstruct the
+    /// samples exactly.
+    pub fn is_lossless(&self) -> bool {
+        self.flags & FLAG_LOSSLESS != 0
+    }
+
+    /// Returns `true` if the stream holds the difference to a reference signal and must be
+    /// decoded with [`crate::decompress_delta`].
+    pub fn is_delta(&self) -> bool {
+        self.flags & FLAG_DELTA != 0
+    }
+
+    /// Returns `true` if the stream holds rows written by [`crate::compress_rows`] and must
+    /// be decoded with [`crate::decompress_rows`].
+    pub fn is_rows(&self) -> bool {
+        self.flags & FLAG_ROWS != 0
+    }
+
+    /// Returns the backend the payload was coded with.
+    pub fn entropy_coder(&self) -> Result<EntropyCoder, BiolepticError> {
+        match (self.flags & ENTROPY_CODER_MASK) >> ENTROPY_CODER_SHIFT {
+            0 => Ok(EntropyCoder::Deflate),
+            1 => Ok(EntropyCoder::Lz4),
+            2 => Ok(EntropyCoder::Raw),
+            _ => Err(BiolepticError::InvalidHeader),
+        }
+    }
+
+    pub(crate) fn set_entropy_coder(&mut self, coder: EntropyCoder) {
+        let value: u16 = match coder {
+            EntropyCoder::Deflate => 0,
+            EntropyCoder::Lz4 => 1,
+            EntropyCoder::Raw => 2,
+        };
+        self.flags = (self.flags & !ENTROPY_CODER_MASK) | (value << ENTROPY_CODER_SHIFT);
+    }
+
+    /// Returns how the wavelet extended the signal past its edges.
+    pub fn border_mode(&self) -> BorderMode {
+        match (self.flags & BORDER_MODE_MASK) >> BORDER_MODE_SHIFT {
+            0 => BorderMode::Wrap,
+            1 => BorderMode::Reflect,
+            2 => BorderMode::Clamp,
+            _ => BorderMode::Zeros,
+        }
+    }
+
+    pub(crate) fn set_border_mode(&mut self, mode: BorderMode) {
+        let value: u16 = match mode {
+            BorderMode::Wrap => 0,
+            BorderMode::Reflect => 1,
+            BorderMode::Clamp => 2,
+            BorderMode::Zeros => 3,
+        };
+        self.flags = (self.flags & !BORDER_MODE_MASK) | (value << BORDER_MODE_SHIFT);
+    }
+
+    /// Returns how detail coefficients were thresholded. Informational only, decoding is
+    /// the same for both modes.
+    pub fn threshold_mode(&self) -> ThresholdMode {
+        if self.flags & FLAG_SOFT_THRESHOLD != 0 {
+            ThresholdMode::Soft
+        } else {
+            ThresholdMode::Hard
+        }
+    }
+
+    /// Returns the transform applied to the raw signal before the DWT.
+    ///
+    /// `bytes` is the full encoded stream this header was read from, since the polynomial
+    /// baseline lives in the extension section after the payload.
+    pub fn pre_transform(&self, bytes: &[u8]) -> Result<PreTransform, BiolepticError> {
+        if self.flags & FLAG_DIFFERENCE != 0 {
+            return Ok(PreTransform::Difference);
+        }
+        if let Some(poly) = find_extension(bytes, self, POLY_TAG)? {
+            return Ok(PreTransform::PolyDetrend(
+                (decode_polynomial(poly)?.len() - 1) as u8,
+            ));
+        }
+        match find_extension(bytes, self, LOG_TAG)? {
+            Some(k) => Ok(PreTransform::Log(decode_log_factor(k)?.log2().round() as i8)),
+            None => Ok(PreTransform::None),
+        }
+    }
+
+    /// Returns the layout of the coefficient stream.
+    pub fn payload_encoding(&self) -> Result<PayloadEncoding, BiolepticError> {
+        match (self.flags & PAYLOAD_ENCODING_MASK) >> PAYLOAD_ENCODING_SHIFT {
+            0 => Ok(PayloadEncoding::Dense),
+            1 => Ok(PayloadEncoding::SparseDetails),
+            2 => Ok(PayloadEncoding::Bitplane),
+            3 => Ok(PayloadEncoding::Varint),
+            4 => Ok(PayloadEncoding::ZeroRuns),
+            _ => Err(BiolepticError::InvalidHeader),
+        }
+    }
+
+    pub(crate) fn set_payload_encoding(&mut self, encoding: PayloadEncoding) {
+        let value: u16 = match encoding {
+            PayloadEncoding::Dense => 0,
+            PayloadEncoding::SparseDetails => 1,
+            PayloadEncoding::Bitplane => 2,
+            PayloadEncoding::Varint => 3,
+            PayloadEncoding::ZeroRuns => 4,
+        };
+        self.flags = (self.flags & !PAYLOAD_ENCODING_MASK) | (value << PAYLOAD_ENCODING_SHIFT);
+    }
+
+    /// Returns how many approximation and per-level detail coefficients the payload holds,
+    /// derived from the method, signal length and level count alone.
+    pub fn coefficient_layout(&self) -> Result<CoefficientLayout, BiolepticError> {
+        if self.levels == 0 {
+            return Err(BiolepticError::InvalidHeader);
+        }
+        if self.is_lossless() {
+            check_signal_length(self)?;
+            let (approximation_length, detail_lengths) =
+                lossless_layout(self.signal_length as usize, self.levels as usize);
+            return Ok(CoefficientLayout {
+                approximation_length,
+                detail_lengths,
+            });
+        }
+        let sizes = level_sizes(self)?;
+        Ok(CoefficientLayout {
+            approximation_length: sizes[sizes.len() - 1].approx_length,
+            detail_lengths: sizes.iter().map(|x| x.details_length).collect(),
+        })
+    }
+
     /// Returns the compression method as an enum.
     pub fn compression_method(&self) -> Result<CompressionMethod, BiolepticError> {
         CompressionMethod::try_from(u32::from_le_bytes(self.compression_method))
@@ -277,6 +810,19 @@ impl BiolepticHeader {
     pub fn data_type(&self) -> Result<DataType, BiolepticError> {
         DataType::try_from(self.data_type)
     }
+
+    /// Checks whether both headers describe the same transform: data type, compression
+    /// method, scale and level count.
+    ///
+    /// Normalization statistics, lengths, checksums and flags are ignored, so re-encoding
+    /// different data with the same options yields compatible headers. Equality
+    /// compares every field.
+    pub fn is_compatible_with(&self, other: &BiolepticHeader) -> bool {
+        self.compression_method == other.compression_method
+            && { self.data_type } == { other.data_type }
+            && self.scale == other.scale
+            && self.levels == other.levels
+    }
 }
 
 impl std::fmt::Debug for BiolepticHeader {
@@ -288,10 +834,301 @@ impl std::fmt::Debug for BiolepticHeader {
             .field("compression_method", &self.compression_method())
             .field("levels", &self.levels)
             .field("scale", &self.scale)
+            .field("flags", &{ self.flags })
             .field("signal_length", &{ self.signal_length })
+            .field("channels", &self.channels)
             .field("min", &self.min_f32())
             .field("max", &self.max_f32())
             .field("mean", &self.mean_f32())
             .finish()
     }
 }
+
+impl PartialEq for BiolepticHeader {
+    fn eq(&self, other: &Self) -> bool {
+        // Fields of a packed struct can't be borrowed, so each one is copied out first.
+        self.magic == other.magic
+            && { self.version } == { other.version }
+            && { self.data_type } == { other.data_type }
+            && self.compression_method == other.compression_method
+            && self.levels == other.levels
+            && self.scale == other.scale
+            && { self.flags } == { other.flags }
+            && { self.signal_length } == { other.signal_length }
+            && { self.min } == { other.min }
+            && { self.max } == { other.max }
+            && { self.mean } == { other.mean }
+            && { self.compressed_size } == { other.compressed_size }
+            && self.channels == other.channels
+            && self.quality_tag == other.quality_tag
+            && { self.sample_rate } == { other.sample_rate }
+            && { self.payload_crc } == { other.payload_crc }
+            && { self.header_crc } == { other.header_crc }
+            && self.reserved1 == other.reserved1
+    }
+}
+
+impl Eq for BiolepticHeader {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::encode_coefficients;
+    use crate::compressor::tests::generate_ppg;
+    use crate::{CompressionOptions, compress};
+
+    #[test]
+    fn test_coefficient_layout() {
+        for length in [100, 1001, 20000] {
+            let signal = generate_ppg(length, 120., 75.);
+            let options = CompressionOptions::default();
+            let encoded = encode_coefficients(&signal, options).unwrap();
+            let compressed = compress(&signal, options).unwrap();
+            let layout = BiolepticHeader::from_bytes(&compressed)
+                .unwrap()
+                .coefficient_layout()
+                .unwrap();
+            assert_eq!(layout.detail_lengths, encoded.detail_lengths);
+            assert_eq!(layout.total(), encoded.coefficients.len());
+        }
+    }
+
+    #[test]
+    fn test_invalid_tags_are_reported_as_stored() {
+        let compressed = compress(
+            &generate_ppg(1000, 120., 75.),
+            CompressionOptions::default(),
+        )
+        .unwrap();
+        let corrupt = |at: usize, tag: &[u8]| {
+            let mut bytes = compressed.clone();
+            bytes[at..at + tag.len()].copy_from_slice(tag);
+            BiolepticHeader::from_bytes(&bytes).unwrap_err()
+        };
+
+        let error = corrupt(8, b"zz99");
+        assert!(matches!(error, BiolepticError::InvalidCompressionMethod(x) if x == *b"zz99"));
+        assert_eq!(error.to_string(), "Invalid compression method 'zz99'");
+        let error = corrupt(6, b"qq");
+        assert!(matches!(error, BiolepticError::InvalidDataType(x) if x == *b"qq"));
+        assert_eq!(error.to_string(), "Invalid data type 'qq'");
+        let error = corrupt(4, b"v9");
+        assert!(matches!(error, BiolepticError::InvalidVersion(x) if x == *b"v9"));
+        assert_eq!(error.to_string(), "Invalid header version 'v9'");
+        let error = corrupt(4, &[1, 3]);
+        assert!(matches!(
+            error,
+            BiolepticError::UnsupportedVersion {
+                found: [1, 3],
+                supported: [1, 0]
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Format version 1.3 is newer than the supported 1.0"
+        );
+        assert!(matches!(
+            corrupt(4, &[2, 0]),
+            BiolepticError::InvalidVersion([2, 0])
+        ));
+    }
+
+    #[test]
+    fn test_quality_tag() {
+        let signal = generate_ppg(2000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        assert_eq!(
+            BiolepticHeader::from_bytes(&compressed)
+                .unwrap()
+                .quality_tag(),
+            0
+        );
+
+        let options = CompressionOptions::default().with_quality_tag(87);
+        let compressed = compress(&signal, options).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.quality_tag(), 87);
+        assert_eq!(compressed[37], 87);
+
+        let zeros = compress(&[0.; 100], options).unwrap();
+        assert_eq!(
+            BiolepticHeader::from_bytes(&zeros).unwrap().quality_tag(),
+            87
+        );
+    }
+
+    #[test]
+    fn test_read_header() {
+        let signal = generate_ppg(3000, 120., 75.);
+        let options = CompressionOptions {
+            method: CompressionMethod::Db4,
+            scale: QuantizationScale::S9,
+            ..Default::default()
+        };
+        let compressed = compress(&signal, options).unwrap();
+        let header = read_header(&compressed).unwrap();
+        assert_eq!(header.signal_length(), 3000);
+        assert_eq!(header.compression_method().unwrap(), CompressionMethod::Db4);
+        assert_eq!(header.scale().unwrap(), QuantizationScale::S9);
+        assert!(header.levels() >= 1);
+        let min = signal.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = signal.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        assert_eq!(header.min_f32(), min);
+        assert_eq!(header.max_f32(), max);
+        assert!(read_header(&compressed[..10]).is_err());
+    }
+
+    #[test]
+    fn test_channels_field() {
+        let signal = generate_ppg(2000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.channels(), 1);
+
+        let mut legacy = compressed.clone();
+        legacy[36] = 0;
+        legacy[46..48].fill(0);
+        assert_eq!(BiolepticHeader::from_bytes(&legacy).unwrap().channels(), 1);
+    }
+
+    #[test]
+    fn test_legacy_zeroed_tail() {
+        let signal = generate_ppg(4000, 120., 75.);
+        let options = CompressionOptions::default()
+            .with_quality_tag(7)
+            .with_sample_rate(120.);
+        let compressed = compress(&signal, options).unwrap();
+
+        // Records written before channels, quality tag, sample rate and checksums kept
+        // every byte from 36 on zeroed.
+        let mut legacy = compressed.clone();
+        legacy[36..BIOLEPTIC_HEADER_SIZE].fill(0);
+        let header = BiolepticHeader::from_bytes(&legacy).unwrap();
+        assert_eq!(header.channels(), 1);
+        assert_eq!(header.quality_tag(), 0);
+        assert_eq!(header.sample_rate(), None);
+        assert_eq!(
+            crate::decompress(&legacy).unwrap(),
+            crate::decompress(&compressed).unwrap()
+        );
+        assert!(crate::validate(&legacy).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_header_equality() {
+        let signal = generate_ppg(4000, 120., 75.);
+        let options = CompressionOptions::default();
+        let compressed = compress(&signal, options).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header, BiolepticHeader::from_bytes(&compressed).unwrap());
+
+        let shifted = signal.iter().map(|&x| x + 100.).collect::<Vec<f32>>();
+        let other = BiolepticHeader::from_bytes(&compress(&shifted, options).unwrap()).unwrap();
+        assert_ne!(header, other);
+        assert!(header.is_compatible_with(&other));
+
+        let cdf53 = CompressionOptions::from_method(CompressionMethod::Cdf53);
+        let other = BiolepticHeader::from_bytes(&compress(&signal, cdf53).unwrap()).unwrap();
+        assert!(!header.is_compatible_with(&other));
+    }
+
+    #[test]
+    fn test_describe() {
+        let signal = generate_ppg(4000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(
+            describe(&compressed).unwrap(),
+            format!(
+                "BILP v1.0 cdf97 4000 samples, {} levels, scale 11, payload {} B, ratio {:.1}x",
+                { header.levels },
+                { header.compressed_size },
+                16000. / compressed.len() as f64
+            )
+        );
+
+        let lossless = compress(
+            &[1f32, 2., 3., 4., 5., 6., 7., 8.],
+            CompressionOptions {
+                method: CompressionMethod::Cdf53,
+                lossless: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let summary = describe(&lossless).unwrap();
+        assert!(summary.starts_with("BILP v1.0 cdf53 8 samples, "));
+        assert!(summary.contains(" levels, lossless, payload "));
+        assert!(!summary.contains("scale"));
+
+        let channels =
+            crate::compress_multichannel(&[&signal, &signal], CompressionOptions::default())
+                .unwrap();
+        assert!(
+            describe(&channels)
+                .unwrap()
+                .starts_with("BILP v1.0 cdf97 4000 samples x 2 channels, ")
+        );
+
+        assert!(describe(&compressed[..10]).is_err());
+    }
+
+    #[test]
+    fn test_reserved_bytes_round_trip() {
+        let signal = generate_ppg(4000, 120., 75.);
+        let options = CompressionOptions {
+            reserved: Some(*b"experiment-0001\0"),
+            ..Default::default()
+        };
+        let compressed = compress(&signal, options).unwrap();
+        assert_eq!(read_reserved(&compressed).unwrap(), options.reserved);
+        let plain = compress(&signal, CompressionOptions::default()).unwrap();
+        assert_eq!(read_reserved(&plain).unwrap(), None);
+        assert_eq!(
+            crate::decompress(&compressed).unwrap(),
+            crate::decompress(&plain).unwrap()
+        );
+
+        let flat = compress(&[0f32; 64], options).unwrap();
+        assert_eq!(read_reserved(&flat).unwrap(), options.reserved);
+        let channels = crate::compress_multichannel(&[&signal, &signal], options).unwrap();
+        assert_eq!(read_reserved(&channels).unwrap(), options.reserved);
+
+        let mut header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.reserved1(), [0; 4]);
+        header.set_reserved1(*b"exp1");
+        header.reserved_mut()[0] = b'X';
+        assert_eq!(header.reserved1(), *b"Xxp1");
+        header.set_reserved1([7; 4]);
+        let reread = BiolepticHeader::from_bytes(&header.to_bytes()).unwrap();
+        assert_eq!(reread.reserved1(), [7; 4]);
+    }
+
+    #[test]
+    fn test_header_checksum() {
+        let signal = generate_ppg(4000, 120., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_ne!({ header.header_crc }, 0);
+
+        // A bogus length still passes every value check, only the checksum catches it.
+        let mut corrupted = compressed.clone();
+        corrupted[19] = 0x7f;
+        assert!(matches!(
+            BiolepticHeader::from_bytes(&corrupted),
+            Err(BiolepticError::InvalidHeader)
+        ));
+
+        let mut legacy = compressed.clone();
+        legacy[46..48].fill(0);
+        assert_eq!(BiolepticHeader::from_bytes(&legacy).unwrap(), {
+            let mut expected = BiolepticHeader::from_bytes(&compressed).unwrap();
+            expected.header_crc = 0;
+            expected
+        });
+        assert_eq!(
+            crate::decompress(&legacy).unwrap(),
+            crate::decompress(&compressed).unwrap()
+        );
+    }
+}
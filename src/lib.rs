@@ -26,16 +26,75 @@
  * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
  * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
  */
+#[cfg(feature = "ndarray")]
+mod array;
+mod batch;
+mod cached;
 mod compressor;
+#[cfg(feature = "debug")]
+mod debug;
 mod decompressor;
+mod delta;
+mod denoise;
+mod entropy;
 mod error;
+mod extension;
 mod header;
+mod lossless;
+mod metrics;
 mod mla;
+mod multichannel;
+mod payload;
+mod pretransform;
+mod rate;
+mod resample;
+mod rows;
+mod simd;
+mod stream;
+#[cfg(any(test, feature = "testutils"))]
+mod synthetic;
+mod validator;
+mod worker;
 
-pub use compressor::{CompressionOptions, CutoffLevel, QuantizationScale, compress};
-pub use decompressor::decompress;
+#[cfg(feature = "ndarray")]
+pub use array::{compress_array, decompress_array};
+pub use batch::{BatchIndex, decompress_nth, write_batch};
+pub use cached::CachedDecoder;
+pub use compressor::{
+    BorderMode, CoefficientWidth, CompressionOptions, CompressionStats, CutoffLevel,
+    DecompositionLevels, DequantMode, NonFinitePolicy, OptionsBuilder, PayloadEncoding,
+    PreTransform, QuantizationScale, ThresholdMode, ThresholdStrategy, compress, compress_f64,
+    compress_from, compress_i16, compress_in_place, compress_into, compress_with_metadata,
+    compress_with_stats, max_compressed_size, to_coefficients,
+};
+#[cfg(feature = "debug")]
+pub use debug::debug_roundtrip_transform;
+pub use decompressor::{
+    CoeffView, band_energies, decode_coefficients_borrowed, decode_concatenated, decompress,
+    decompress_approximation, decompress_coefficients, decompress_downsampled, decompress_f64,
+    decompress_i16, decompress_into, decompress_scaled, decompress_streaming, decompressed_len,
+    files_equivalent, from_coefficients,
+};
+#[cfg(feature = "half")]
+pub use decompressor::{decompress_bf16, decompress_f16};
+pub use delta::{compress_delta, decompress_delta};
+pub use denoise::denoise;
+pub use entropy::EntropyCoder;
 pub use error::BiolepticError;
 pub use header::{
-    BIOLEPTIC_HEADER_SIZE, BIOLEPTIC_MAGIC, BIOLEPTIC_VERSION, BiolepticHeader, CompressionMethod,
-    DataType,
+    BIOLEPTIC_HEADER_SIZE, BIOLEPTIC_MAGIC, BIOLEPTIC_VERSION, BiolepticHeader, CoefficientLayout,
+    CompressionMethod, DataType, describe, read_header, read_metadata, read_reserved,
+};
+pub use metrics::{prd, rmse};
+pub use multichannel::{
+    compress_multichannel, decompress_multichannel, decompress_multichannel_interleaved,
+};
+pub use rate::{
+    compress_to_bitrate, compress_to_quality, compress_with_max_error, scale_for_budget, transcode,
 };
+pub use resample::decompress_to_rate;
+pub use rows::{compress_rows, decompress_rows};
+pub use stream::{BiolepticReader, StreamingCompressor, StreamingDecompressor};
+#[cfg(feature = "testutils")]
+pub use synthetic::{synthetic_ecg, synthetic_ppg};
+pub use validator::{ValidationIssue, ValidationReport, Validator, validate};
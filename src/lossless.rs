@@ -0,0 +1,147 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+/// Largest magnitude a sample may have, lifting grows the range by a few bits.
+pub(crate) const LOSSLESS_MAX_MAGNITUDE: f32 = (1 << 24) as f32;
+
+/// Returns the approximation length and the detail lengths, finest first, of `levels`
+/// reversible CDF 5/3 lifting steps over `length` samples.
+///
+/// Boundaries use whole-sample symmetric extension, so a level of `n` samples yields
+/// `n.div_ceil(2)` approximation and `n / 2` detail coefficients.
+pub(crate) fn lossless_layout(length: usize, levels: usize) -> (usize, Vec<usize>) {
+    let mut approx_length = length;
+    let mut detail_lengths = Vec::with_capacity(levels);
+    for _ in 0..levels {
+        detail_lengths.push(approx_length / 2);
+        approx_length = approx_length.div_ceil(2);
+    }
+    (approx_length, detail_lengths)
+}
+
+/// Neighbour of `values[i]` to the right under symmetric extension.
+#[inline]
+fn right(values: &[i32], i: usize, fallback: i32) -> i32 {
+    values.get(i).copied().unwrap_or(fallback)
+}
+
+fn forward_level(x: &[i32]) -> (Vec<i32>, Vec<i32>) {
+    let half = x.len() / 2;
+    let details = (0..half)
+        .map(|i| x[2 * i + 1] - ((x[2 * i] + right(x, 2 * i + 2, x[2 * i])) >> 1))
+        .collect::<Vec<i32>>();
+    let approximations = (0..x.len().div_ceil(2))
+        .map(|i| {
+            let Some(&last) = details.last() else {
+                return x[2 * i];
+            };
+            let previous = if i == 0 { details[0] } else { details[i - 1] };
+            x[2 * i] + ((previous + right(&details, i, last) + 2) >> 2)
+        })
+        .collect();
+    (approximations, details)
+}
+
+fn inverse_level(approximations: &[i32], details: &[i32]) -> Vec<i32> {
+    let length = approximations.len() + details.len();
+    let mut x = vec![0i32; length];
+    for (i, &s) in approximations.iter().enumerate() {
+        x[2 * i] = match details.last() {
+            None => s,
+            Some(&last) => {
+                let previous = if i == 0 { details[0] } else { details[i - 1] };
+                s - ((previous + right(details, i, last) + 2) >> 2)
+            }
+        };
+    }
+    for (i, &d) in details.iter().enumerate() {
+        let next = if 2 * i + 2 < length {
+            x[2 * i + 2]
+        } else {
+            x[2 * i]
+        };
+        x[2 * i + 1] = d + ((x[2 * i] + next) >> 1);
+    }
+    x
+}
+
+/// Transforms `samples` by `levels` lifting steps, returning the coefficients in payload
+/// order.
+pub(crate) fn forward_lossless(samples: &[i32], levels: usize) -> Vec<i32> {
+    let mut approximations = samples.to_vec();
+    let mut details = Vec::with_capacity(levels);
+    for _ in 0..levels {
+        let (next, level_details) = forward_level(&approximations);
+        approximations = next;
+        details.push(level_details);
+    }
+    for level_details in details {
+        approximations.extend_from_slice(&level_details);
+    }
+    approximations
+}
+
+/// Reverses [`forward_lossless`] exactly. `coefficients` must match
+/// [`lossless_layout`] for `length` and `levels`.
+pub(crate) fn inverse_lossless(coefficients: &[i32], length: usize, levels: usize) -> Vec<i32> {
+    let (approx_length, detail_lengths) = lossless_layout(length, levels);
+    let mut starts = Vec::with_capacity(levels);
+    let mut start = approx_length;
+    for &detail_length in detail_lengths.iter() {
+        starts.push(start);
+        start += detail_length;
+    }
+    let mut signal = coefficients[..approx_length].to_vec();
+    for (&start, &detail_length) in starts.iter().zip(detail_lengths.iter()).rev() {
+        signal = inverse_level(&signal, &coefficients[start..start + detail_length]);
+    }
+    signal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifting_is_reversible() {
+        for length in [1usize, 2, 3, 7, 16, 101, 1000] {
+            let samples = (0..length)
+                .map(|i| ((i as i32 * 7919) % 4001) - 2000)
+                .collect::<Vec<i32>>();
+            for levels in 1..=4 {
+                let coefficients = forward_lossless(&samples, levels);
+                let (approx_length, detail_lengths) = lossless_layout(length, levels);
+                assert_eq!(
+                    coefficients.len(),
+                    approx_length + detail_lengths.iter().sum::<usize>()
+                );
+                assert_eq!(inverse_lossless(&coefficients, length, levels), samples);
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+/// Percentage root-mean-square difference between a signal and its reconstruction.
+///
+/// The error energy is normalized by the energy of `original` around its mean, so the
+/// result doesn't depend on a DC offset. Returns `0.0` for a constant `original`.
+///
+/// # Panics
+///
+/// Panics when the signals differ in length.
+pub fn prd(original: &[f32], reconstructed: &[f32]) -> f64 {
+    assert_eq!(
+        original.len(),
+        reconstructed.len(),
+        "PRD needs signals of equal length"
+    );
+    let n = original.len() as f64;
+
+    // mean of original
+    let mean = original.iter().map(|&x| x as f64).sum::<f64>() / n;
+
+    // numerator: squared error
+    let num = squared_error(original, reconstructed);
+
+    // denominator: signal energy around mean
+    let den = original
+        .iter()
+        .map(|&x| {
+            let centered = x as f64 - mean;
+            centered * centered
+        })
+        .sum::<f64>();
+
+    if den == 0.0 {
+        return 0.0;
+    }
+
+    (num / den).sqrt() * 100.0
+}
+
+/// Root-mean-square error between a signal and its reconstruction, in signal units.
+///
+/// Returns `0.0` for empty signals.
+///
+/// # Panics
+///
+/// Panics when the signals differ in length.
+pub fn rmse(original: &[f32], reconstructed: &[f32]) -> f64 {
+    assert_eq!(
+        original.len(),
+        reconstructed.len(),
+        "RMSE needs signals of equal length"
+    );
+    if original.is_empty() {
+        return 0.0;
+    }
+    (squared_error(original, reconstructed) / original.len() as f64).sqrt()
+}
+
+fn squared_error(original: &[f32], reconstructed: &[f32]) -> f64 {
+    original
+        .iter()
+        .zip(reconstructed.iter())
+        .map(|(&x, &y)| {
+            let diff = x as f64 - y as f64;
+            diff * diff
+        })
+        .sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let original = [1f32, 2., 3., 4.];
+        assert_eq!(prd(&original, &original), 0.);
+        assert_eq!(rmse(&original, &original), 0.);
+
+        let shifted = original.map(|x| x + 0.5);
+        assert!((rmse(&original, &shifted) - 0.5).abs() < 1e-12);
+        // Energy around the mean is 5, the error energy is 1.
+        assert!((prd(&original, &shifted) - (1f64 / 5.).sqrt() * 100.).abs() < 1e-9);
+
+        assert_eq!(prd(&[2., 2.], &[1., 3.]), 0.);
+        assert_eq!(rmse(&[], &[]), 0.);
+    }
+
+    #[test]
+    #[should_panic(expected = "equal length")]
+    fn test_length_mismatch_panics() {
+        prd(&[1., 2.], &[1.]);
+    }
+}
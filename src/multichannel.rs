@@ -0,0 +1,354 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::compressor::{
+    CoefficientWidth, DequantMode, EncodedCoefficients, ThresholdMode, encode_coefficients,
+    write_stream,
+};
+use crate::decompressor::{decode_signal, decompress};
+use crate::entropy::{entropy_decode, entropy_encode};
+use crate::extension::{ExtensionWriter, RESERVED_TAG, find_extension};
+use crate::header::{FLAG_MIDPOINT_DEQUANT, FLAG_SOFT_THRESHOLD, FLAG_WIDE_COEFFICIENTS};
+use crate::payload::{deserialize_coefficients, max_payload_size, serialize_coefficients};
+use crate::{BIOLEPTIC_HEADER_SIZE, BiolepticError, BiolepticHeader, CompressionOptions, DataType};
+
+/// Per-channel table of a multichannel record.
+///
+/// For every channel, a `u32` LE byte length of its coefficients in the shared payload,
+/// a `u32` LE byte length of its channel record, then the record itself: a header with
+/// an empty payload followed by the channel's own extension section.
+pub(crate) const MULTICHANNEL_TAG: [u8; 4] = *b"mchn";
+
+/// Compresses equally long channels, e.g. the red and infrared traces of a pulse oximeter,
+/// into a single record.
+///
+/// Every channel is normalized and transformed on its own with the same `options`, then the
+/// coefficients of all channels share one deflate stream, so a single header and entropy
+/// coder state cover the whole recording. Decode with [`decompress_multichannel`]; the
+/// single-channel decoders reject the result. At most 255 channels are supported.
+pub fn compress_multichannel(
+    channels: &[&[f32]],
+    options: CompressionOptions,
+) -> Result<Vec<u8>, BiolepticError> {
+    if channels.is_empty() || channels.len() > u8::MAX as usize {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Channel count must be in 1..=255, but it was {}",
+            channels.len()
+        )));
+    }
+    let length = channels[0].len();
+    if let Some(mismatch) = channels.iter().find(|x| x.len() != length) {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "All channels must have {length} samples, but one had {}",
+            mismatch.len()
+        )));
+    }
+
+    let encoded = channels
+        .iter()
+        .map(|channel| encode_coefficients(channel, options))
+        .collect::<Result<Vec<EncodedCoefficients>, BiolepticError>>()?;
+
+    let mut payload = Vec::new();
+    let mut table = Vec::new();
+    let mut levels = 1u8;
+    for channel in encoded {
+        let mut coefficients = Vec::new();
+        if !channel.header.is_zero_signal() {
+            levels = levels.max(channel.header.levels);
+            coefficients = serialize_coefficients(
+                &channel.coefficients,
+                &channel.detail_lengths,
+                channel.header.coefficient_width(),
+                options.payload_encoding,
+            );
+        }
+        let record = write_stream(channel.header, &[], channel.extensions);
+        table.extend_from_slice(&(coefficients.len() as u32).to_le_bytes());
+        table.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        table.extend_from_slice(&record);
+        payload.extend_from_slice(&coefficients);
+    }
+
+    let compressed_data = entropy_encode(options.entropy, &payload, options.deflate_level)?;
+
+    // Normalization lives in the channel records, the outer header only frames them.
+    let mut header = BiolepticHeader::new(
+        DataType::Float32,
+        options.method,
+        levels,
+        options.scale,
+        length as u32,
+        0.,
+        0.,
+        0.,
+        compressed_data.len() as u32,
+    );
+    header.channels = channels.len() as u8;
+    header.quality_tag = options.quality_tag;
+    header.sample_rate = options.sample_rate.map_or(0, f32::to_bits);
+    if options.coefficient_width == CoefficientWidth::I32 {
+        header.flags |= FLAG_WIDE_COEFFICIENTS;
+    }
+    if options.dequant_mode == DequantMode::Midpoint {
+        header.flags |= FLAG_MIDPOINT_DEQUANT;
+    }
+    if options.threshold_mode == ThresholdMode::Soft {
+        header.flags |= FLAG_SOFT_THRESHOLD;
+    }
+    header.set_payload_encoding(options.payload_encoding);
+    header.set_entropy_coder(options.entropy);
+
+    let mut extensions = ExtensionWriter::default();
+    extensions.push(MULTICHANNEL_TAG, &table);
+    if let Some(reserved) = options.reserved {
+        extensions.push(RESERVED_TAG, &reserved);
+    }
+    Ok(write_stream(header, &compressed_data, extensions))
+}
+
+/// Decompresses every channel of a record written by [`compress_multichannel`], in order.
+///
+/// Single-channel streams decode to one channel.
+pub fn decompress_multichannel(bytes: &[u8]) -> Result<Vec<Vec<f32>>, BiolepticError> {
+    let mut decoded = Vec::new();
+    decode_channels(bytes, |_, signal| decoded.push(std::mem::take(signal)))?;
+    Ok(decoded)
+}
+
+/// Decompresses a record written by [`compress_multichannel`] into `out` in interleaved
+/// order, replacing its contents.
+///
+/// Sample `i` of channel `c` lands at `out[i * channels + c]`, so `out` holds
+/// `[c0[0], c1[0], .., c0[1], c1[1], ..]` with `signal_length * channels` samples, the
+/// frame layout most ML pipelines and audio APIs expect. Each channel is scattered into
+/// place as soon as it is decoded, without a planar copy of the whole recording.
+/// Single-channel streams decode as with [`crate::decompress_into`].
+pub fn decompress_multichannel_interleaved(
+    bytes: &[u8],
+    out: &mut Vec<f32>,
+) -> Result<(), BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    let channels = header.channels as usize;
+    let samples = (header.signal_length as usize)
+        .checked_mul(channels)
+        .ok_or(BiolepticError::InvalidHeader)?;
+    out.clear();
+    out.try_reserve_exact(samples)
+        .map_err(|_| BiolepticError::OutOfMemoryError(samples))?;
+    out.resize(samples, 0.);
+    decode_channels(bytes, |channel, signal| {
+        for (dst, &v) in out[channel..]
+            .iter_mut()
+            .step_by(channels)
+            .zip(signal.iter())
+        {
+            *dst = v;
+        }
+    })
+}
+
+/// Decodes the channels of `bytes` in order, handing each one to `sink` along with its
+/// index. The buffer may be taken by the sink, it is only reused when left in place.
+fn decode_channels(
+    bytes: &[u8],
+    mut sink: impl FnMut(usize, &mut Vec<f32>),
+) -> Result<(), BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    if header.channels == 1 {
+        let mut signal = decompress(bytes)?;
+        sink(0, &mut signal);
+        return Ok(());
+    }
+
+    let mut table = find_extension(bytes, &header, MULTICHANNEL_TAG)?
+        .ok_or(BiolepticError::InvalidHeaderExtension)?;
+    let mut channels = Vec::with_capacity(header.channels as usize);
+    let mut max_decoded_size = 0usize;
+    while !table.is_empty() {
+        if table.len() < 8 {
+            return Err(BiolepticError::InvalidHeaderExtension);
+        }
+        let coefficients_length = u32::from_le_bytes(table[0..4].try_into().unwrap()) as usize;
+        let record_length = u32::from_le_bytes(table[4..8].try_into().unwrap()) as usize;
+        let record = table
+            .get(8..8 + record_length)
+            .ok_or(BiolepticError::InvalidHeaderExtension)?;
+        let channel_header = BiolepticHeader::from_bytes(record)?;
+        if channel_header.channels != 1
+            || channel_header.compressed_size != 0
+            || channel_header.signal_length != header.signal_length
+        {
+            return Err(BiolepticError::InvalidHeaderExtension);
+        }
+        if !channel_header.is_zero_signal() {
+            let layout = channel_header.coefficient_layout()?;
+            max_decoded_size += max_payload_size(
+                layout.approximation_length,
+                &layout.detail_lengths,
+                channel_header.coefficient_width(),
+                channel_header.payload_encoding()?,
+            );
+        }
+        channels.push((coefficients_length, record, channel_header));
+        table = &table[8 + record_length..];
+    }
+    if channels.len() != header.channels as usize {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+
+    let payload_end = BIOLEPTIC_HEADER_SIZE + header.compressed_size as usize;
+    let compressed_data = bytes
+        .get(BIOLEPTIC_HEADER_SIZE..payload_end)
+        .ok_or_else(|| {
+            BiolepticError::DecompressionError(format!(
+                "Minimum data size is {payload_end}, but it was {}",
+                bytes.len()
+            ))
+        })?;
+    header.verify_payload(compressed_data)?;
+    let mut payload = Vec::new();
+    entropy_decode(
+        header.entropy_coder()?,
+        compressed_data,
+        max_decoded_size,
+        &mut payload,
+    )?;
+    let expected_size = channels.iter().map(|x| x.0).sum::<usize>();
+    if payload.len() != expected_size || expected_size > max_decoded_size {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Channels need {expected_size} payload bytes, but {} were decoded",
+            payload.len()
+        )));
+    }
+
+    let mut signal = Vec::new();
+    let mut offset = 0usize;
+    for (index, (coefficients_length, record, channel_header)) in channels.into_iter().enumerate() {
+        let coefficients = &payload[offset..offset + coefficients_length];
+        offset += coefficients_length;
+        let quantized = if channel_header.is_zero_signal() {
+            None
+        } else {
+            let layout = channel_header.coefficient_layout()?;
+            Some(deserialize_coefficients(
+                coefficients,
+                layout.approximation_length,
+                &layout.detail_lengths,
+                channel_header.coefficient_width(),
+                channel_header.payload_encoding()?,
+            )?)
+        };
+        decode_signal(
+            record,
+            &channel_header,
+            quantized.as_deref(),
+            1.,
+            0.,
+            &mut signal,
+        )?;
+        sink(index, &mut signal);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress;
+    use crate::compressor::tests::generate_ppg;
+
+    #[test]
+    fn test_multichannel_round_trip() {
+        let red = generate_ppg(5000, 120., 75.);
+        let infrared = red.iter().map(|&x| x * 0.8 + 150.).collect::<Vec<f32>>();
+        let flat = vec![0f32; 5000];
+        let options = CompressionOptions::default().with_taper(10);
+
+        let compressed = compress_multichannel(&[&red, &infrared, &flat], options).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.channels(), 3);
+
+        let decoded = decompress_multichannel(&compressed).unwrap();
+        assert_eq!(decoded.len(), 3);
+        for (channel, decoded) in [&red, &infrared, &flat].iter().zip(decoded.iter()) {
+            let expected = decompress(&compress(channel, options).unwrap()).unwrap();
+            assert_eq!(decoded, &expected);
+        }
+
+        let separate = compress(&red, options).unwrap().len()
+            + compress(&infrared, options).unwrap().len()
+            + compress(&flat, options).unwrap().len();
+        println!("multichannel={} separate={separate}", compressed.len());
+        assert!(compressed.len() < separate);
+
+        assert!(matches!(
+            decompress(&compressed),
+            Err(BiolepticError::DecompressionError(_))
+        ));
+        assert!(crate::Validator::validate(&compressed).unwrap().is_valid());
+
+        let single = compress(&red, options).unwrap();
+        assert_eq!(decompress_multichannel(&single).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_multichannel_interleaved() {
+        let red = generate_ppg(3000, 120., 75.);
+        let infrared = red.iter().map(|&x| x * 0.8 + 150.).collect::<Vec<f32>>();
+        let options = CompressionOptions::default();
+        let compressed = compress_multichannel(&[&red, &infrared], options).unwrap();
+
+        let planar = decompress_multichannel(&compressed).unwrap();
+        let transposed = (0..red.len())
+            .flat_map(|i| planar.iter().map(move |channel| channel[i]))
+            .collect::<Vec<f32>>();
+        let mut interleaved = vec![1.; 7];
+        decompress_multichannel_interleaved(&compressed, &mut interleaved).unwrap();
+        assert_eq!(
+            interleaved
+                .iter()
+                .map(|x| x.to_bits())
+                .collect::<Vec<u32>>(),
+            transposed.iter().map(|x| x.to_bits()).collect::<Vec<u32>>()
+        );
+
+        let single = compress(&red, options).unwrap();
+        decompress_multichannel_interleaved(&single, &mut interleaved).unwrap();
+        assert_eq!(interleaved, decompress(&single).unwrap());
+    }
+
+    #[test]
+    fn test_multichannel_rejects_mismatched_lengths() {
+        let a = generate_ppg(1000, 120., 75.);
+        let b = generate_ppg(999, 120., 75.);
+        let options = CompressionOptions::default();
+        assert!(compress_multichannel(&[&a, &b], options).is_err());
+        assert!(compress_multichannel(&[], options).is_err());
+    }
+}
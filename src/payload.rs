@@ -0,0 +1,486 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::BiolepticError;
+use crate::compressor::{CoefficientWidth, PayloadEncoding};
+use std::ops::Range;
+
+const LEVEL_DENSE: u8 = 0;
+const LEVEL_SPARSE: u8 = 1;
+
+fn push_coefficient(dst: &mut Vec<u8>, value: i32, width: CoefficientWidth) {
+    match width {
+        CoefficientWidth::I16 => dst.extend_from_slice(&(value as i16).to_le_bytes()),
+        CoefficientWidth::I32 => dst.extend_from_slice(&value.to_le_bytes()),
+    }
+}
+
+fn width_bytes(width: CoefficientWidth) -> usize {
+    match width {
+        CoefficientWidth::I16 => 2,
+        CoefficientWidth::I32 => 4,
+    }
+}
+
+/// Extra bytes allowed on top of the coefficient count, covering the samples added
+/// when a signal shorter than the filter is padded before the transform.
+const PAYLOAD_SLACK: usize = 4096;
+
+/// Upper bound of the serialized coefficient stream for the given level layout.
+///
+/// Sparse levels are only written when smaller than dense ones, so the dense size plus
+/// one mode byte per level bounds every encoding.
+/// Bitplanes carry one extra sign bit per coefficient and a leading plane count.
+/// A zigzag varint needs at most one byte more than the coefficient width, and so does a
+/// zero run shorter than 128, longer runs take fewer bytes than their dense zeros.
+pub(crate) fn max_payload_size(
+    approx_length: usize,
+    detail_lengths: &[usize],
+    width: CoefficientWidth,
+    encoding: PayloadEncoding,
+) -> usize {
+    let total = approx_length + detail_lengths.iter().sum::<usize>();
+    let body = match encoding {
+        PayloadEncoding::Dense | PayloadEncoding::SparseDetails => total
+            .saturating_mul(width_bytes(width))
+            .saturating_add(detail_lengths.len()),
+        PayloadEncoding::Bitplane => total
+            .saturating_mul(width_bytes(width) * 8 + 1)
+            .div_ceil(8)
+            .saturating_add(1),
+        PayloadEncoding::Varint | PayloadEncoding::ZeroRuns => {
+            total.saturating_mul(width_bytes(width) + 1)
+        }
+    };
+    body.saturating_add(PAYLOAD_SLACK)
+}
+
+fn push_leb128(dst: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        dst.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    dst.push(value as u8);
+}
+
+fn push_varint(dst: &mut Vec<u8>, value: i32) {
+    push_leb128(dst, ((value << 1) ^ (value >> 31)) as u32);
+}
+
+/// Coefficient ranges in bitplane transmission order: the approximation first, then the
+/// detail levels from coarsest to finest, so early planes refine the coarse shape first.
+fn bitplane_order(approx_length: usize, detail_lengths: &[usize]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::with_capacity(detail_lengths.len() + 1);
+    ranges.push(0..approx_length);
+    let mut end = approx_length + detail_lengths.iter().sum::<usize>();
+    for &length in detail_lengths.iter().rev() {
+        ranges.push(end - length..end);
+        end -= length;
+    }
+    ranges
+}
+
+/// Writes magnitudes plane by plane from the most significant one, emitting each
+/// coefficient's sign right after its first set bit.
+///
+/// Layout: a `u8` plane count followed by MSB-first packed bits.
+fn serialize_bitplanes(coefficients: &[i32], detail_lengths: &[usize]) -> Vec<u8> {
+    let approx_length = coefficients.len() - detail_lengths.iter().sum::<usize>();
+    let order = bitplane_order(approx_length, detail_lengths);
+    let max_magnitude = coefficients
+        .iter()
+        .map(|x| x.unsigned_abs())
+        .max()
+        .unwrap_or(0);
+    let planes = u32::BITS - max_magnitude.leading_zeros();
+
+    let mut writer = BitWriter {
+        bytes: vec![planes as u8],
+        current: 0,
+        filled: 0,
+    };
+    for plane in (0..planes).rev() {
+        for range in order.iter() {
+            for &x in &coefficients[range.clone()] {
+                let magnitude = x.unsigned_abs();
+                let bit = (magnitude >> plane) & 1 != 0;
+                writer.push(bit);
+                if bit && magnitude >> plane == 1 {
+                    writer.push(x < 0);
+                }
+            }
+        }
+    }
+    writer.finish()
+}
+
+/// Reverses [`serialize_bitplanes`], tolerating a stream cut at any byte.
+///
+/// Significant coefficients whose lower planes are missing are placed at the center of
+/// the remaining uncertainty interval.
+fn deserialize_bitplanes(
+    bytes: &[u8],
+    approx_length: usize,
+    detail_lengths: &[usize],
+) -> Result<Vec<i32>, BiolepticError> {
+    let total = approx_length + detail_lengths.iter().sum::<usize>();
+    let mut coefficients = Vec::new();
+    coefficients
+        .try_reserve_exact(total)
+        .map_err(|_| BiolepticError::OutOfMemoryError(total))?;
+    coefficients.resize(total, 0i32);
+    let Some((&planes, bits)) = bytes.split_first() else {
+        return Ok(coefficients);
+    };
+    if planes > 32 {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Bitplane count {planes} exceeds 32"
+        )));
+    }
+
+    let order = bitplane_order(approx_length, detail_lengths);
+    let mut magnitudes = vec![0u32; total];
+    // Lowest plane received for each coefficient, the bits below it are unknown.
+    let mut known_plane = vec![planes as u32; total];
+    let mut reader = BitReader {
+        bytes: bits,
+        position: 0,
+    };
+    'planes: for plane in (0..planes as u32).rev() {
+        for range in order.iter() {
+            for i in range.clone() {
+                let Some(bit) = reader.next() else {
+                    break 'planes;
+                };
+                if bit && magnitudes[i] == 0 {
+                    let Some(sign) = reader.next() else {
+                        break 'planes;
+                    };
+                    coefficients[i] = if sign { -1 } else { 1 };
+                }
+                magnitudes[i] |= (bit as u32) << plane;
+                known_plane[i] = plane;
+            }
+        }
+    }
+
+    for ((dst, &magnitude), &plane) in coefficients
+        .iter_mut()
+        .zip(magnitudes.iter())
+        .zip(known_plane.iter())
+    {
+        let magnitude = if magnitude != 0 && plane > 0 {
+            magnitude | (1 << (plane - 1))
+        } else {
+            magnitude
+        };
+        *dst *= magnitude.min(i32::MAX as u32) as i32;
+    }
+    Ok(coefficients)
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u8,
+    filled: u32,
+}
+
+impl BitWriter {
+    fn push(&mut self, bit: bool) {
+        self.current = (self.current << 1) | bit as u8;
+        self.filled += 1;
+        if self.filled == 8 {
+            self.bytes.push(self.current);
+            self.current = 0;
+            self.filled = 0;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.current << (8 - self.filled));
+        }
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn next(&mut self) -> Option<bool> {
+        let byte = self.bytes.get(self.position / 8)?;
+        let bit = (byte >> (7 - self.position % 8)) & 1 != 0;
+        self.position += 1;
+        Some(bit)
+    }
+}
+
+/// Serializes coefficients into the byte stream handed to the entropy coder.
+///
+/// `detail_lengths` lists the detail level sizes from finest to coarsest, the
+/// approximation occupies the remaining leading coefficients.
+pub(crate) fn serialize_coefficients(
+    coefficients: &[i32],
+    detail_lengths: &[usize],
+    width: CoefficientWidth,
+    encoding: PayloadEncoding,
+) -> Vec<u8> {
+    let w = width_bytes(width);
+    match encoding {
+        PayloadEncoding::Dense => {
+            let mut dst = Vec::with_capacity(coefficients.len() * w);
+            for &x in coefficients {
+                push_coefficient(&mut dst, x, width);
+            }
+            dst
+        }
+        PayloadEncoding::SparseDetails => {
+            let approx_length = coefficients.len() - detail_lengths.iter().sum::<usize>();
+            let mut dst = Vec::with_capacity(coefficients.len() * w);
+            for &x in &coefficients[..approx_length] {
+                push_coefficient(&mut dst, x, width);
+            }
+            let mut start = approx_length;
+            for &length in detail_lengths {
+                let level = &coefficients[start..start + length];
+                start += length;
+                let non_zero = level.iter().filter(|&&x| x != 0).count();
+                // Each sparse entry costs a u32 gap plus the value, pick whatever is smaller.
+                if 4 + non_zero * (4 + w) < length * w {
+                    dst.push(LEVEL_SPARSE);
+                    dst.extend_from_slice(&(non_zero as u32).to_le_bytes());
+                    let mut last = 0usize;
+                    for (i, &x) in level.iter().enumerate().filter(|(_, x)| **x != 0) {
+                        dst.extend_from_slice(&((i - last) as u32).to_le_bytes());
+                        push_coefficient(&mut dst, x, width);
+                        last = i;
+                    }
+                } else {
+                    dst.push(LEVEL_DENSE);
+                    for &x in level {
+                        push_coefficient(&mut dst, x, width);
+                    }
+                }
+            }
+            dst
+        }
+        PayloadEncoding::Bitplane => serialize_bitplanes(coefficients, detail_lengths),
+        PayloadEncoding::Varint => {
+            let mut dst = Vec::with_capacity(coefficients.len());
+            for &x in coefficients {
+                push_varint(&mut dst, x);
+            }
+            dst
+        }
+        PayloadEncoding::ZeroRuns => {
+            let mut dst = Vec::with_capacity(coefficients.len() * w);
+            let mut i = 0usize;
+            while i < coefficients.len() {
+                let x = coefficients[i];
+                push_coefficient(&mut dst, x, width);
+                i += 1;
+                if x == 0 {
+                    let run = coefficients[i..].iter().take_while(|&&x| x == 0).count();
+                    push_leb128(&mut dst, run as u32 + 1);
+                    i += run;
+                }
+            }
+            dst
+        }
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BiolepticError> {
+        if self.bytes.len() < n {
+            return Err(BiolepticError::DecompressionError(
+                "Coefficient payload is truncated".to_string(),
+            ));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    /// Fails when bytes are left after the last coefficient.
+    fn finish(&self) -> Result<(), BiolepticError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(BiolepticError::DecompressionError(format!(
+                "{} bytes trail the coefficient payload",
+                self.bytes.len()
+            )))
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, BiolepticError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, BiolepticError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Reads an LEB128 value of at most 5 bytes.
+    fn leb128(&mut self) -> Result<u32, BiolepticError> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BiolepticError::DecompressionError(
+            "Coefficient varint is longer than 5 bytes".to_string(),
+        ))
+    }
+
+    fn varint(&mut self) -> Result<i32, BiolepticError> {
+        let zigzag = self.leb128()?;
+        Ok((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32))
+    }
+
+    fn coefficient(&mut self, width: CoefficientWidth) -> Result<i32, BiolepticError> {
+        Ok(match width {
+            CoefficientWidth::I16 => i16::from_le_bytes(self.take(2)?.try_into().unwrap()) as i32,
+            CoefficientWidth::I32 => i32::from_le_bytes(self.take(4)?.try_into().unwrap()),
+        })
+    }
+}
+
+/// Reverses [`serialize_coefficients`].
+pub(crate) fn deserialize_coefficients(
+    bytes: &[u8],
+    approx_length: usize,
+    detail_lengths: &[usize],
+    width: CoefficientWidth,
+    encoding: PayloadEncoding,
+) -> Result<Vec<i32>, BiolepticError> {
+    match encoding {
+        PayloadEncoding::Dense => Ok(match width {
+            CoefficientWidth::I16 => bytes
+                .chunks_exact(2)
+                .map(|x| i16::from_le_bytes([x[0], x[1]]) as i32)
+                .collect(),
+            CoefficientWidth::I32 => bytes
+                .chunks_exact(4)
+                .map(|x| i32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+                .collect(),
+        }),
+        PayloadEncoding::SparseDetails => {
+            let total = approx_length + detail_lengths.iter().sum::<usize>();
+            let mut reader = ByteReader { bytes };
+            let mut coefficients = Vec::new();
+            coefficients
+                .try_reserve_exact(total)
+                .map_err(|_| BiolepticError::OutOfMemoryError(total))?;
+            for _ in 0..approx_length {
+                coefficients.push(reader.coefficient(width)?);
+            }
+            for &length in detail_lengths {
+                match reader.u8()? {
+                    LEVEL_DENSE => {
+                        for _ in 0..length {
+                            coefficients.push(reader.coefficient(width)?);
+                        }
+                    }
+                    LEVEL_SPARSE => {
+                        let start = coefficients.len();
+                        coefficients.resize(start + length, 0);
+                        let count = reader.u32()? as usize;
+                        let mut position = 0usize;
+                        for _ in 0..count {
+                            position += reader.u32()? as usize;
+                            let value = reader.coefficient(width)?;
+                            if position >= length {
+                                return Err(BiolepticError::DecompressionError(
+                                    "Sparse coefficient index is out of range".to_string(),
+                                ));
+                            }
+                            coefficients[start + position] = value;
+                        }
+                    }
+                    mode => {
+                        return Err(BiolepticError::DecompressionError(format!(
+                            "Unknown detail level encoding {mode}"
+                        )));
+                    }
+                }
+            }
+            reader.finish()?;
+            Ok(coefficients)
+        }
+        PayloadEncoding::Bitplane => deserialize_bitplanes(bytes, approx_length, detail_lengths),
+        PayloadEncoding::Varint => {
+            let total = approx_length + detail_lengths.iter().sum::<usize>();
+            let mut reader = ByteReader { bytes };
+            let mut coefficients = Vec::new();
+            coefficients
+                .try_reserve_exact(total)
+                .map_err(|_| BiolepticError::OutOfMemoryError(total))?;
+            for _ in 0..total {
+                coefficients.push(reader.varint()?);
+            }
+            reader.finish()?;
+            Ok(coefficients)
+        }
+        PayloadEncoding::ZeroRuns => {
+            let total = approx_length + detail_lengths.iter().sum::<usize>();
+            let mut reader = ByteReader { bytes };
+            let mut coefficients = Vec::new();
+            coefficients
+                .try_reserve_exact(total)
+                .map_err(|_| BiolepticError::OutOfMemoryError(total))?;
+            while coefficients.len() < total {
+                let x = reader.coefficient(width)?;
+                if x != 0 {
+                    coefficients.push(x);
+                    continue;
+                }
+                let run = reader.leb128()? as usize;
+                if run == 0 || run > total - coefficients.len() {
+                    return Err(BiolepticError::DecompressionError(format!(
+                        "Zero run of {run} is out of range"
+                    )));
+                }
+                coefficients.resize(coefficients.len() + run, 0);
+            }
+            reader.finish()?;
+            Ok(coefficients)
+        }
+    }
+}
@@ -0,0 +1,270 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::BiolepticError;
+
+/// First-difference pre-transform, see [`crate::PreTransform::Difference`].
+pub(crate) const DIFFERENCE_TAG: [u8; 4] = *b"dif1";
+
+/// Replaces `data` with its first difference in place, the first sample becomes zero.
+///
+/// Returns the initial value that has to be stored to integrate the signal back.
+pub(crate) fn difference(data: &mut [f32]) -> f32 {
+    let Some(&initial) = data.first() else {
+        return 0.;
+    };
+    let mut previous = initial;
+    for v in data.iter_mut() {
+        let current = *v;
+        *v = current - previous;
+        previous = current;
+    }
+    initial
+}
+
+/// Reverses [`difference`] by a running sum starting at `initial`.
+pub(crate) fn integrate(data: &mut [f32], initial: f32) {
+    // Accumulate in f64 so the running sum doesn't add its own rounding drift on long signals.
+    let mut acc = initial as f64;
+    for v in data.iter_mut() {
+        acc += *v as f64;
+        *v = acc as f32;
+    }
+}
+
+pub(crate) fn decode_initial_value(data: &[u8]) -> Result<f32, BiolepticError> {
+    let bytes: [u8; 4] = data
+        .try_into()
+        .map_err(|_| BiolepticError::InvalidHeaderExtension)?;
+    let initial = f32::from_le_bytes(bytes);
+    if !initial.is_finite() {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok(initial)
+}
+
+/// Tukey taper parameters, see [`crate::CompressionOptions::with_taper`].
+pub(crate) const TAPER_TAG: [u8; 4] = *b"tapr";
+
+/// Tukey window with tapered fraction `alpha`.
+fn taper_window(index: usize, length: usize, alpha: f32) -> f32 {
+    if length < 2 {
+        return 1.;
+    }
+    let x = index as f32 / (length - 1) as f32;
+    let edge = x.min(1. - x);
+    if edge < alpha * 0.5 {
+        0.5 * (1. - (2. * std::f32::consts::PI * edge / alpha).cos())
+    } else {
+        1.
+    }
+}
+
+/// Offset blended into sample `index` by the taper: the full edge offset at the edge,
+/// fading out along the Tukey window.
+fn taper_offset(index: usize, length: usize, alpha: f32, start: f32, end: f32) -> f32 {
+    let edge = if 2 * index < length { start } else { end };
+    (1. - taper_window(index, length, alpha)) * edge
+}
+
+/// Pulls both edges of `data` onto its mean with a Tukey blend, returns the offsets of
+/// the first and last sample from the mean.
+///
+/// The blend is additive, so undoing it leaves the reconstruction error at the edges
+/// unamplified.
+pub(crate) fn apply_taper(data: &mut [f32], alpha: f32) -> (f32, f32) {
+    let (Some(&first), Some(&last)) = (data.first(), data.last()) else {
+        return (0., 0.);
+    };
+    let center = (data.iter().map(|&x| x as f64).sum::<f64>() / data.len() as f64) as f32;
+    let (start, end) = (first - center, last - center);
+    let length = data.len();
+    for (i, v) in data.iter_mut().enumerate() {
+        *v -= taper_offset(i, length, alpha, start, end);
+    }
+    (start, end)
+}
+
+/// Reverses [`apply_taper`] by adding the edge offsets back, scaled by `gain`.
+pub(crate) fn remove_taper(data: &mut [f32], alpha: f32, start: f32, end: f32, gain: f32) {
+    let length = data.len();
+    for (i, v) in data.iter_mut().enumerate() {
+        *v += taper_offset(i, length, alpha, start, end) * gain;
+    }
+}
+
+/// Serializes the taper as `f32` alpha followed by the `f32` start and end offsets.
+pub(crate) fn encode_taper(alpha: f32, start: f32, end: f32) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(12);
+    encoded.extend_from_slice(&alpha.to_le_bytes());
+    encoded.extend_from_slice(&start.to_le_bytes());
+    encoded.extend_from_slice(&end.to_le_bytes());
+    encoded
+}
+
+pub(crate) fn decode_taper(data: &[u8]) -> Result<(f32, f32, f32), BiolepticError> {
+    if data.len() != 12 {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    let alpha = f32::from_le_bytes(data[0..4].try_into().unwrap());
+    let start = f32::from_le_bytes(data[4..8].try_into().unwrap());
+    let end = f32::from_le_bytes(data[8..12].try_into().unwrap());
+    if alpha.is_nan() || alpha <= 0. || alpha > 1. || !start.is_finite() || !end.is_finite() {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok((alpha, start, end))
+}
+
+/// Polynomial baseline, see [`crate::PreTransform::PolyDetrend`].
+pub(crate) const POLY_TAG: [u8; 4] = *b"poly";
+
+/// Maps sample `index` onto `[-1, 1]`, where low-order power bases stay well conditioned.
+fn poly_abscissa(index: usize, length: usize) -> f64 {
+    if length < 2 {
+        return 0.;
+    }
+    2. * index as f64 / (length - 1) as f64 - 1.
+}
+
+fn evaluate_polynomial(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0., |acc, &c| acc * x + c)
+}
+
+/// Least-squares fit of a polynomial of `degree` to `data`, lowest power first.
+///
+/// Solves the normal equations in `f64` on the abscissa mapped to `[-1, 1]`, which keeps
+/// the Gram matrix well conditioned up to cubic fits. The degree is lowered when there
+/// are not enough samples to determine it.
+pub(crate) fn fit_polynomial(data: &[f32], degree: usize) -> Vec<f64> {
+    let terms = degree.min(data.len().saturating_sub(1)) + 1;
+    let mut gram = vec![vec![0f64; terms + 1]; terms];
+    for (i, &v) in data.iter().enumerate() {
+        let x = poly_abscissa(i, data.len());
+        let mut powers = [1f64; 7];
+        for p in 1..2 * terms - 1 {
+            powers[p] = powers[p - 1] * x;
+        }
+        for (row, gram_row) in gram.iter_mut().enumerate() {
+            for (col, cell) in gram_row.iter_mut().take(terms).enumerate() {
+                *cell += powers[row + col];
+            }
+            gram_row[terms] += powers[row] * v as f64;
+        }
+    }
+
+    // Gaussian elimination with partial pivoting on the augmented matrix.
+    for col in 0..terms {
+        let pivot = (col..terms)
+            .max_by(|&a, &b| gram[a][col].abs().total_cmp(&gram[b][col].abs()))
+            .unwrap_or(col);
+        gram.swap(col, pivot);
+        if gram[col][col].abs() < f64::EPSILON {
+            continue;
+        }
+        let (upper, lower) = gram.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for row in lower.iter_mut() {
+            let factor = row[col] / pivot_row[col];
+            for (dst, &src) in row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                *dst -= factor * src;
+            }
+        }
+    }
+    let mut coefficients = vec![0f64; terms];
+    for row in (0..terms).rev() {
+        if gram[row][row].abs() < f64::EPSILON {
+            continue;
+        }
+        let tail = (row + 1..terms)
+            .map(|k| gram[row][k] * coefficients[k])
+            .sum::<f64>();
+        coefficients[row] = (gram[row][terms] - tail) / gram[row][row];
+    }
+    coefficients
+}
+
+/// Adds `scale` times the polynomial baseline to `data`, use a negative scale to subtract it.
+pub(crate) fn add_polynomial(data: &mut [f32], coefficients: &[f64], scale: f64) {
+    let length = data.len();
+    for (i, v) in data.iter_mut().enumerate() {
+        let baseline = evaluate_polynomial(coefficients, poly_abscissa(i, length));
+        *v = (*v as f64 + baseline * scale) as f32;
+    }
+}
+
+/// Serializes the fit as `f64` LE coefficients, lowest power first.
+pub(crate) fn encode_polynomial(coefficients: &[f64]) -> Vec<u8> {
+    coefficients.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub(crate) fn decode_polynomial(data: &[u8]) -> Result<Vec<f64>, BiolepticError> {
+    if data.is_empty() || data.len() > 32 || !data.len().is_multiple_of(8) {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    let coefficients = data
+        .chunks_exact(8)
+        .map(|x| f64::from_le_bytes(x.try_into().unwrap()))
+        .collect::<Vec<f64>>();
+    if coefficients.iter().any(|x| !x.is_finite()) {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok(coefficients)
+}
+
+/// Logarithmic companding, see [`crate::PreTransform::Log`].
+pub(crate) const LOG_TAG: [u8; 4] = *b"logc";
+
+/// Replaces every sample with `sign(x) * ln(1 + |x| * k)`, zero maps to zero.
+pub(crate) fn log_compand(data: &mut [f32], k: f32) {
+    let k = k as f64;
+    for v in data.iter_mut() {
+        let x = *v as f64;
+        *v = (x.abs() * k).ln_1p().copysign(x) as f32;
+    }
+}
+
+/// Reverses [`log_compand`] with `sign(y) * (exp(|y|) - 1) / k`.
+pub(crate) fn log_expand(data: &mut [f32], k: f32) {
+    let k = k as f64;
+    for v in data.iter_mut() {
+        let y = *v as f64;
+        *v = (y.abs().exp_m1() / k).copysign(y) as f32;
+    }
+}
+
+/// Reads back the companding factor, which is always a power of two.
+pub(crate) fn decode_log_factor(data: &[u8]) -> Result<f32, BiolepticError> {
+    let k = f32::from_le_bytes(
+        data.try_into()
+            .map_err(|_| BiolepticError::InvalidHeaderExtension)?,
+    );
+    if !k.is_finite() || k <= 0. || 2f32.powi(k.log2().round() as i32) != k {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok(k)
+}
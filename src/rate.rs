@@ -0,0 +1,542 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::compressor::{
+    CoefficientWidth, CompressionOptions, CutoffLevel, PayloadEncoding, QuantizationScale,
+    encode_coefficients, threshold, threshold_magnitude, write_stream,
+};
+use crate::decompressor::decode_quantized;
+use crate::entropy::entropy_encode;
+use crate::extension::{
+    ExtensionWriter, LEVEL_SCALES_TAG, RESIDUAL_TAG, apply_residual, decode_level_scales,
+    encode_residual, extension_section, find_extension,
+};
+use crate::header::FLAG_EXTENDED;
+use crate::metrics::prd;
+use crate::payload::serialize_coefficients;
+use crate::{
+    BIOLEPTIC_HEADER_SIZE, BiolepticError, BiolepticHeader, CompressionMethod, compress, decompress,
+};
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use std::io::Write;
+
+/// Scale whose payload is actually deflated to calibrate the entropy model.
+const CALIBRATION_SCALE: QuantizationScale = QuantizationScale::S9;
+
+/// Picks the finest quantization scale whose output is predicted to fit in `max_bytes`.
+///
+/// Runs a single forward transform and derives every scale's coefficients from it by
+/// shifting, then predicts each size from the zero-order entropy of the coefficients.
+/// Deflate also exploits repetition the entropy misses, so the model is calibrated by a
+/// single real compression at `S9`. The result is approximate, typically within one
+/// scale step of a brute-force search. Only `S6..=S12` are considered, the range of the
+/// default [`CoefficientWidth::I16`]; the coarsest scale is returned when nothing fits.
+pub fn scale_for_budget(
+    data: &[f32],
+    method: CompressionMethod,
+    max_bytes: usize,
+) -> Result<QuantizationScale, BiolepticError> {
+    let defaults = CompressionOptions::from_method(method);
+    let options = CompressionOptions {
+        scale: QuantizationScale::S20,
+        coefficient_width: CoefficientWidth::I32,
+        ..defaults
+    };
+    let encoded = encode_coefficients(data, options)?;
+    if encoded.header.is_zero_signal() {
+        return Ok(QuantizationScale::S12);
+    }
+    let approx_length = encoded.coefficients.len() - encoded.detail_lengths.iter().sum::<usize>();
+
+    // Truncation toward zero composes, so shifting the finest scale reproduces every
+    // coarser one exactly before thresholding.
+    let quantize = |scale: QuantizationScale| {
+        let divisor = 1i32 << (QuantizationScale::S20.as_u8() - scale.as_u8());
+        let mut coefficients = encoded
+            .coefficients
+            .iter()
+            .map(|&x| (x / divisor).clamp(i16::MIN as i32, i16::MAX as i32))
+            .collect::<Vec<i32>>();
+        threshold(
+            &mut coefficients[approx_length..],
+            threshold_magnitude(scale, defaults.cutoff_level),
+            defaults.threshold_mode,
+        );
+        coefficients
+    };
+
+    let calibration = quantize(CALIBRATION_SCALE);
+    let serialized = serialize_coefficients(
+        &calibration,
+        &encoded.detail_lengths,
+        CoefficientWidth::I16,
+        PayloadEncoding::Dense,
+    );
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&serialized)
+        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+    let deflated = encoder
+        .finish()
+        .map_err(|x| BiolepticError::UnderlyingCompressorError(x.to_string()))?;
+    let ratio = deflated.len() as f64 / entropy_bytes(&calibration).max(1.);
+
+    let mut chosen = QuantizationScale::S6;
+    for value in QuantizationScale::S6.as_u8()..=QuantizationScale::S12.as_u8() {
+        let scale = QuantizationScale::try_from(value)?;
+        let predicted = entropy_bytes(&quantize(scale)) * ratio + BIOLEPTIC_HEADER_SIZE as f64;
+        if predicted <= max_bytes as f64 {
+            chosen = scale;
+        }
+    }
+    Ok(chosen)
+}
+
+/// Compresses `data` into the smallest output whose [`prd`] stays within `max_prd` percent.
+///
+/// For every cutoff level the coarsest scale in `S6..=S12` meeting the bound is found by
+/// binary search, each probe being a full compress and decompress. Expect roughly ten
+/// encode/decode round trips, so this costs an order of magnitude more than a single
+/// [`compress`]. Fails when even `S12` with [`CutoffLevel::Low`] exceeds the bound, which
+/// includes signals with non-finite samples.
+pub fn compress_to_quality(
+    data: &[f32],
+    method: CompressionMethod,
+    max_prd: f64,
+) -> Result<Vec<u8>, BiolepticError> {
+    let probe = |scale: QuantizationScale, cutoff_level: CutoffLevel| {
+        let options = CompressionOptions {
+            scale,
+            cutoff_level,
+            ..CompressionOptions::from_method(method)
+        };
+        let compressed = compress(data, options)?;
+        let distortion = prd(data, &decompress(&compressed)?);
+        Ok::<_, BiolepticError>((distortion <= max_prd).then_some(compressed))
+    };
+
+    let mut best = probe(QuantizationScale::S12, CutoffLevel::Low)?.ok_or_else(|| {
+        BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "PRD of {max_prd}% is out of reach even at the finest scale"
+        ))
+    })?;
+    for cutoff_level in [CutoffLevel::Low, CutoffLevel::Medium, CutoffLevel::High] {
+        // Coarsening the scale raises the PRD, so the passing scales form an upper range.
+        let (mut lo, mut hi) = (
+            QuantizationScale::S6.as_u8(),
+            QuantizationScale::S12.as_u8() + 1,
+        );
+        let mut passing = None;
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match probe(QuantizationScale::try_from(mid)?, cutoff_level)? {
+                Some(compressed) => {
+                    passing = Some(compressed);
+                    hi = mid;
+                }
+                None => lo = mid + 1,
+            }
+        }
+        if let Some(compressed) = passing
+            && compressed.len() < best.len()
+        {
+            best = compressed;
+        }
+    }
+    Ok(best)
+}
+
+/// Compresses `data` with the least distortion whose output stays within `max_bps` bits
+/// per sample, for links with a fixed channel budget.
+///
+/// For every cutoff level the finest scale in `S6..=S12` that fits is found by binary
+/// search, then the candidate with the lowest [`prd`] wins. Fails when even `S6` with
+/// [`CutoffLevel::High`] exceeds the budget; the header alone costs `416 / len` bits per
+/// sample, so short signals have a floor.
+pub fn compress_to_bitrate(
+    data: &[f32],
+    method: CompressionMethod,
+    max_bps: f64,
+) -> Result<Vec<u8>, BiolepticError> {
+    let max_bytes = max_bps * data.len() as f64 / 8.;
+    let probe = |scale: QuantizationScale, cutoff_level: CutoffLevel| {
+        let options = CompressionOptions {
+            scale,
+            cutoff_level,
+            ..CompressionOptions::from_method(method)
+        };
+        let compressed = compress(data, options)?;
+        Ok::<_, BiolepticError>((compressed.len() as f64 <= max_bytes).then_some(compressed))
+    };
+
+    let mut best: Option<(f64, Vec<u8>)> = None;
+    for cutoff_level in [CutoffLevel::Low, CutoffLevel::Medium, CutoffLevel::High] {
+        // Refining the scale grows the output, so the fitting scales form a lower range.
+        let (mut lo, mut hi) = (
+            QuantizationScale::S6.as_u8(),
+            QuantizationScale::S12.as_u8() + 1,
+        );
+        let mut fitting = None;
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match probe(QuantizationScale::try_from(mid)?, cutoff_level)? {
+                Some(compressed) => {
+                    fitting = Some(compressed);
+                    lo = mid + 1;
+                }
+                None => hi = mid,
+            }
+        }
+        if let Some(compressed) = fitting {
+            let distortion = prd(data, &decompress(&compressed)?);
+            if best.as_ref().is_none_or(|x| distortion < x.0) {
+                best = Some((distortion, compressed));
+            }
+        }
+    }
+    best.map(|x| x.1).ok_or_else(|| {
+        BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "{max_bps} bits per sample are out of reach even at the coarsest scale"
+        ))
+    })
+}
+
+/// Compresses `data` so that no finite sample decodes further than `max_abs_err` from
+/// the original, e.g. to keep alarm thresholds trustworthy.
+///
+/// The signal is encoded as usual, then every sample still off by more than the bound
+/// gets a correction in multiples of `1.8 * max_abs_err`, stored deflated in the extension
+/// section and added back by [`decompress`]. Streams that already meet the bound are
+/// returned unchanged. The bound holds for [`decompress`], [`crate::decompress_scaled`]
+/// scales it by the gain. Fails when `max_abs_err` is not positive, or is too fine to
+/// resolve in `f32` at the signal's magnitude.
+pub fn compress_with_max_error(
+    data: &[f32],
+    method: CompressionMethod,
+    max_abs_err: f32,
+) -> Result<Vec<u8>, BiolepticError> {
+    if !max_abs_err.is_finite() || max_abs_err <= 0. {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Max absolute error must be positive and finite, but it was {max_abs_err}"
+        )));
+    }
+    let options = CompressionOptions::from_method(method);
+    let compressed = compress(data, options)?;
+    let mut decoded = decompress(&compressed)?;
+
+    // The margin below `2 * max_abs_err` absorbs the rounding of the corrected sample.
+    let step = 1.8 * max_abs_err;
+    let corrections = data
+        .iter()
+        .zip(decoded.iter())
+        .map(|(&x, &y)| {
+            let residual = x - y;
+            if !residual.is_finite() || residual.abs() <= max_abs_err {
+                0
+            } else {
+                (residual / step).round() as i32
+            }
+        })
+        .collect::<Vec<i32>>();
+    if corrections.iter().all(|&x| x == 0) {
+        return Ok(compressed);
+    }
+
+    apply_residual(&mut decoded, step, &corrections, 1.);
+    if let Some(worst) = data
+        .iter()
+        .zip(decoded.iter())
+        .map(|(&x, &y)| (x - y).abs())
+        .filter(|x| x.is_finite())
+        .find(|&x| x > max_abs_err)
+    {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Max absolute error {max_abs_err} is below the f32 resolution of the signal, \
+             a sample is still off by {worst}"
+        )));
+    }
+
+    let header = BiolepticHeader::from_bytes(&compressed)?;
+    let payload_end = BIOLEPTIC_HEADER_SIZE + header.compressed_size as usize;
+    let mut extensions = ExtensionWriter::from_entries(extension_section(&compressed, &header)?);
+    extensions.push(
+        RESIDUAL_TAG,
+        &encode_residual(step, &corrections, options.deflate_level)?,
+    );
+    Ok(write_stream(
+        header,
+        &compressed[BIOLEPTIC_HEADER_SIZE..payload_end],
+        extensions,
+    ))
+}
+
+/// Re-quantizes a compressed stream to the coarser `new_scale` without decoding it to
+/// samples, e.g. to shrink an archive under disk pressure.
+///
+/// The payload is inflated and every coefficient is shifted down to the new scale, then
+/// detail bands are re-thresholded with the default cutoff and the payload is deflated
+/// again at the default level. Truncation composes, so the result decodes like a stream
+/// compressed at `new_scale` directly, up to a cutoff coarser than the default and soft
+/// thresholding. Bands with a per-level scale already at or below `new_scale` are kept.
+/// Corrections of [`compress_with_max_error`] no longer apply and are dropped. Fails when
+/// `new_scale` is finer than the stream's scale, and on lossless and multichannel streams.
+pub fn transcode(bytes: &[u8], new_scale: QuantizationScale) -> Result<Vec<u8>, BiolepticError> {
+    let mut header = BiolepticHeader::from_bytes(bytes)?;
+    if new_scale.as_u8() > header.scale {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Can't transcode scale {} to the finer {}",
+            { header.scale },
+            new_scale.as_u8()
+        )));
+    }
+    if header.is_lossless() || header.channels != 1 {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "Only lossy single-channel streams can be transcoded".to_string(),
+        ));
+    }
+
+    let level_scales = find_extension(bytes, &header, LEVEL_SCALES_TAG)?;
+    let mut extensions = ExtensionWriter::default();
+    let mut section = extension_section(bytes, &header)?;
+    while !section.is_empty() {
+        if section.len() < 8 {
+            return Err(BiolepticError::InvalidHeaderExtension);
+        }
+        let tag: [u8; 4] = section[0..4].try_into().unwrap();
+        let length = u32::from_le_bytes(section[4..8].try_into().unwrap()) as usize;
+        let data = section
+            .get(8..8 + length)
+            .ok_or(BiolepticError::InvalidHeaderExtension)?;
+        if tag != RESIDUAL_TAG && tag != LEVEL_SCALES_TAG {
+            extensions.push(tag, data);
+        }
+        section = &section[8 + length..];
+    }
+
+    // The extension section is rebuilt, `write_stream` sets the flag again if it isn't empty.
+    header.flags &= !FLAG_EXTENDED;
+    if header.is_zero_signal() {
+        header.scale = new_scale.as_u8();
+        return Ok(write_stream(header, &[], extensions));
+    }
+
+    let levels = header.levels as usize;
+    let old_scales = match level_scales {
+        Some(scales) => decode_level_scales(scales, levels)?.to_vec(),
+        None => vec![header.scale; levels + 1],
+    };
+    let new_scales = old_scales
+        .iter()
+        .map(|&x| QuantizationScale::try_from(x.min(new_scale.as_u8())))
+        .collect::<Result<Vec<QuantizationScale>, BiolepticError>>()?;
+    if level_scales.is_some() {
+        extensions.push(
+            LEVEL_SCALES_TAG,
+            &new_scales.iter().map(|x| x.as_u8()).collect::<Vec<u8>>(),
+        );
+    }
+
+    let mut coefficients = decode_quantized(bytes, &header)?;
+    let layout = header.coefficient_layout()?;
+    let mut bands = vec![layout.approximation_length];
+    bands.extend_from_slice(&layout.detail_lengths);
+    if coefficients.len() != layout.total() {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Expected {} coefficients, but payload holds {}",
+            layout.total(),
+            coefficients.len()
+        )));
+    }
+
+    let cutoff_level = CompressionOptions::default().cutoff_level;
+    let mut start = 0usize;
+    for (band, &length) in bands.iter().enumerate() {
+        let divisor = 1i32 << (old_scales[band] - new_scales[band].as_u8());
+        let band_coefficients = &mut coefficients[start..start + length];
+        for x in band_coefficients.iter_mut() {
+            *x /= divisor;
+        }
+        if band > 0 {
+            threshold(
+                band_coefficients,
+                threshold_magnitude(new_scales[band], cutoff_level),
+                header.threshold_mode(),
+            );
+        }
+        start += length;
+    }
+
+    let payload = serialize_coefficients(
+        &coefficients,
+        &layout.detail_lengths,
+        header.coefficient_width(),
+        header.payload_encoding()?,
+    );
+    let compressed_data = entropy_encode(
+        header.entropy_coder()?,
+        &payload,
+        CompressionOptions::default().deflate_level,
+    )?;
+    header.scale = new_scale.as_u8();
+    header.compressed_size = compressed_data.len() as u32;
+    Ok(write_stream(header, &compressed_data, extensions))
+}
+
+/// Zero-order entropy of `i16` coefficients, in bytes.
+fn entropy_bytes(coefficients: &[i32]) -> f64 {
+    let mut histogram = vec![0u32; 1 << 16];
+    for &x in coefficients {
+        histogram[x as i16 as u16 as usize] += 1;
+    }
+    let total = coefficients.len() as f64;
+    let bits = histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| -(count as f64) * (count as f64 / total).log2())
+        .sum::<f64>();
+    bits / 8.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress;
+    use crate::compressor::tests::generate_ppg;
+
+    #[test]
+    fn test_scale_for_budget() {
+        let signal = generate_ppg(100000, 120., 75.);
+        for target in [
+            QuantizationScale::S7,
+            QuantizationScale::S9,
+            QuantizationScale::S11,
+        ] {
+            let options = CompressionOptions {
+                scale: target,
+                ..Default::default()
+            };
+            let max_bytes = compress(&signal, options).unwrap().len() * 21 / 20;
+            let scale = scale_for_budget(&signal, CompressionMethod::Cdf97, max_bytes).unwrap();
+            let actual = compress(
+                &signal,
+                CompressionOptions {
+                    scale,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .len();
+            println!("budget={max_bytes} scale={scale:?} actual={actual}");
+            assert!(
+                actual.abs_diff(max_bytes) * 5 <= max_bytes,
+                "budget {max_bytes}, got {actual} at {scale:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compress_to_quality() {
+        let signal = generate_ppg(20000, 120., 75.);
+        let strict = compress_to_quality(&signal, CompressionMethod::Cdf97, 0.5).unwrap();
+        let loose = compress_to_quality(&signal, CompressionMethod::Cdf97, 5.).unwrap();
+        assert!(prd(&signal, &decompress(&strict).unwrap()) <= 0.5);
+        assert!(prd(&signal, &decompress(&loose).unwrap()) <= 5.);
+        assert!(loose.len() < strict.len());
+        assert!(compress_to_quality(&signal, CompressionMethod::Cdf97, 1e-6).is_err());
+    }
+
+    #[test]
+    fn test_compress_to_bitrate() {
+        let signal = generate_ppg(20000, 120., 75.);
+        for max_bps in [0.5, 1., 2.] {
+            let compressed =
+                compress_to_bitrate(&signal, CompressionMethod::Cdf97, max_bps).unwrap();
+            let bps = (compressed.len() * 8) as f64 / signal.len() as f64;
+            println!("target={max_bps} actual={bps}");
+            assert!(bps <= max_bps);
+        }
+        let low = compress_to_bitrate(&signal, CompressionMethod::Cdf97, 0.5).unwrap();
+        let high = compress_to_bitrate(&signal, CompressionMethod::Cdf97, 2.).unwrap();
+        assert!(
+            prd(&signal, &decompress(&high).unwrap()) < prd(&signal, &decompress(&low).unwrap())
+        );
+        assert!(compress_to_bitrate(&signal, CompressionMethod::Cdf97, 0.01).is_err());
+    }
+
+    #[test]
+    fn test_compress_with_max_error() {
+        let signal = generate_ppg(20000, 120., 75.);
+        let lossy = compress(&signal, CompressionOptions::default()).unwrap();
+        let lossy_error = signal
+            .iter()
+            .zip(decompress(&lossy).unwrap().iter())
+            .fold(0f32, |acc, (&x, &y)| acc.max((x - y).abs()));
+
+        let max_abs_err = lossy_error / 8.;
+        let bounded =
+            compress_with_max_error(&signal, CompressionMethod::Cdf97, max_abs_err).unwrap();
+        let decoded = decompress(&bounded).unwrap();
+        for (&x, &y) in signal.iter().zip(decoded.iter()) {
+            assert!((x - y).abs() <= max_abs_err, "{x} vs {y}");
+        }
+        println!("lossy={} bounded={}", lossy.len(), bounded.len());
+        assert!(bounded.len() > lossy.len());
+        assert!(crate::Validator::validate(&bounded).unwrap().is_valid());
+
+        let loose = compress_with_max_error(&signal, CompressionMethod::Cdf97, lossy_error * 2.);
+        assert_eq!(loose.unwrap(), lossy);
+        assert!(compress_with_max_error(&signal, CompressionMethod::Cdf97, 0.).is_err());
+    }
+
+    #[test]
+    fn test_transcode_matches_direct_compression() {
+        let signal = generate_ppg(20000, 120., 75.);
+        let fine = compress(&signal, CompressionOptions::default()).unwrap();
+        let coarse_options = CompressionOptions {
+            scale: QuantizationScale::S8,
+            ..Default::default()
+        };
+
+        let transcoded = transcode(&fine, QuantizationScale::S8).unwrap();
+        let header = BiolepticHeader::from_bytes(&transcoded).unwrap();
+        assert_eq!(header.scale().unwrap(), QuantizationScale::S8);
+        assert!(transcoded.len() < fine.len());
+        assert_eq!(
+            decompress(&transcoded).unwrap(),
+            decompress(&compress(&signal, coarse_options).unwrap()).unwrap()
+        );
+
+        assert!(transcode(&transcoded, QuantizationScale::S11).is_err());
+        let flat = compress(&[0f32; 100], CompressionOptions::default()).unwrap();
+        assert_eq!(
+            decompress(&transcode(&flat, QuantizationScale::S6).unwrap()).unwrap(),
+            vec![0f32; 100]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::{BiolepticError, BiolepticHeader, decompress};
+
+/// Decompresses like [`decompress`] and resamples the signal from the sample rate stored in
+/// the header to `target_hz`, e.g. to align recordings from sensors with different native
+/// rates. Returns the resampled signal, its length is `signal_length * target_hz / source_hz`
+/// rounded to the nearest sample.
+///
+/// Resampling interpolates linearly between neighbouring samples and applies no
+/// anti-aliasing filter, so band-limit the signal first when downsampling by a large factor.
+/// Fails when the file was written without [`crate::CompressionOptions::with_sample_rate`],
+/// and on `Int16` streams, which [`decompress`] rejects.
+pub fn decompress_to_rate(bytes: &[u8], target_hz: f32) -> Result<Vec<f32>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    let source_hz = header.sample_rate().ok_or_else(|| {
+        BiolepticError::DecompressionError("Sample rate is not stored in the header".to_string())
+    })?;
+    if !target_hz.is_finite() || target_hz <= 0. {
+        return Err(BiolepticError::DecompressionError(format!(
+            "Target sample rate must be positive and finite, but it was {target_hz}"
+        )));
+    }
+    let signal = decompress(bytes)?;
+    Ok(resample_linear(&signal, source_hz, target_hz))
+}
+
+pub(crate) fn resample_linear(data: &[f32], source_hz: f32, target_hz: f32) -> Vec<f32> {
+    if data.is_empty() || source_hz == target_hz {
+        return data.to_vec();
+    }
+    let ratio = source_hz as f64 / target_hz as f64;
+    let length = ((data.len() as f64 / ratio).round() as usize).max(1);
+    let last = data.len() - 1;
+    (0..length)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = (position.floor() as usize).min(last);
+            let next = (index + 1).min(last);
+            let fraction = (position - index as f64).clamp(0., 1.) as f32;
+            data[index] + (data[next] - data[index]) * fraction
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::metrics::prd;
+    use crate::{CompressionOptions, compress};
+
+    #[test]
+    fn test_decompress_to_rate_halves_length() {
+        let signal = generate_ppg(4000, 100., 75.);
+        let options = CompressionOptions::default().with_sample_rate(100.);
+        let compressed = compress(&signal, options).unwrap();
+
+        let resampled = decompress_to_rate(&compressed, 50.).unwrap();
+        assert_eq!(resampled.len(), signal.len() / 2);
+
+        let reference = signal.iter().step_by(2).copied().collect::<Vec<f32>>();
+        let error = prd(&reference, &resampled);
+        println!("prd against decimation {error}");
+        assert!(error < 1.);
+
+        let upsampled = decompress_to_rate(&compressed, 250.).unwrap();
+        assert_eq!(upsampled.len(), 10000);
+    }
+
+    #[test]
+    fn test_decompress_to_rate_requires_rate() {
+        let signal = generate_ppg(1000, 100., 75.);
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        assert!(matches!(
+            decompress_to_rate(&compressed, 50.),
+            Err(BiolepticError::DecompressionError(_))
+        ));
+
+        let compressed = compress(
+            &signal,
+            CompressionOptions::default().with_sample_rate(100.),
+        )
+        .unwrap();
+        assert!(decompress_to_rate(&compressed, 0.).is_err());
+        assert!(decompress_to_rate(&compressed, f32::NAN).is_err());
+        let header = crate::BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert_eq!(header.sample_rate(), Some(100.));
+
+        // The raw bits of an integer rate make a subnormal float, not a rate.
+        for hz in [f32::from_bits(250), 0., -100., f32::INFINITY] {
+            let options = CompressionOptions::default().with_sample_rate(hz);
+            assert!(compress(&signal, options).is_err(), "{hz}");
+        }
+    }
+}
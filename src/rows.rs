@@ -0,0 +1,188 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::compressor::write_stream;
+use crate::decompressor::decompress;
+use crate::extension::{ExtensionWriter, RESERVED_TAG, find_extension};
+use crate::header::FLAG_ROWS;
+use crate::{
+    BIOLEPTIC_HEADER_SIZE, BiolepticError, BiolepticHeader, CompressionOptions, DataType, compress,
+};
+
+/// Row table of a row record: for every row, its `u32` LE sample count and the `u32` LE
+/// byte length of its compressed record in the payload.
+pub(crate) const ROWS_TAG: [u8; 4] = *b"rows";
+
+/// Compresses rows of possibly different lengths, e.g. the leads of a multi-lead ECG,
+/// into a single record.
+///
+/// Every row is compressed on its own with `options` like [`compress`] would, and the
+/// row records are stored back to back as the payload, their lengths and sample counts in
+/// the extension section. Unlike [`crate::compress_multichannel`] nothing is shared
+/// between rows, so they need not be equally long. Decode with [`decompress_rows`]; the
+/// single-signal decoders reject the result.
+pub fn compress_rows(
+    rows: &[Vec<f32>],
+    options: CompressionOptions,
+) -> Result<Vec<u8>, BiolepticError> {
+    if rows.is_empty() {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(
+            "Can't compress zero rows".to_string(),
+        ));
+    }
+    let total_length = rows.iter().map(|x| x.len()).sum::<usize>();
+    if total_length > u32::MAX as usize {
+        return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+            "Rows hold {total_length} samples, more than {} fit in a record",
+            u32::MAX
+        )));
+    }
+
+    let mut payload = Vec::new();
+    let mut table = Vec::with_capacity(rows.len() * 8);
+    for row in rows {
+        let record = compress(row, options)?;
+        table.extend_from_slice(&(row.len() as u32).to_le_bytes());
+        table.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&record);
+    }
+
+    // Every row record carries its own normalization, the outer header only frames them.
+    let mut header = BiolepticHeader::new(
+        DataType::Float32,
+        options.method,
+        1,
+        options.scale,
+        total_length as u32,
+        0.,
+        0.,
+        0.,
+        payload.len() as u32,
+    );
+    header.flags |= FLAG_ROWS;
+    header.quality_tag = options.quality_tag;
+    header.sample_rate = options.sample_rate.map_or(0, f32::to_bits);
+
+    let mut extensions = ExtensionWriter::default();
+    extensions.push(ROWS_TAG, &table);
+    if let Some(reserved) = options.reserved {
+        extensions.push(RESERVED_TAG, &reserved);
+    }
+    Ok(write_stream(header, &payload, extensions))
+}
+
+/// Decompresses every row of a record written by [`compress_rows`], in order.
+///
+/// Streams of a single signal decode to one row.
+pub fn decompress_rows(bytes: &[u8]) -> Result<Vec<Vec<f32>>, BiolepticError> {
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    if !header.is_rows() {
+        return Ok(vec![decompress(bytes)?]);
+    }
+
+    let table =
+        find_extension(bytes, &header, ROWS_TAG)?.ok_or(BiolepticError::InvalidHeaderExtension)?;
+    if table.is_empty() || table.len() % 8 != 0 {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    let payload_end = BIOLEPTIC_HEADER_SIZE + header.compressed_size as usize;
+    let payload = bytes
+        .get(BIOLEPTIC_HEADER_SIZE..payload_end)
+        .ok_or_else(|| {
+            BiolepticError::DecompressionError(format!(
+                "Minimum data size is {payload_end}, but it was {}",
+                bytes.len()
+            ))
+        })?;
+    header.verify_payload(payload)?;
+
+    let mut rows = Vec::with_capacity(table.len() / 8);
+    let mut offset = 0usize;
+    let mut total_length = 0usize;
+    for entry in table.chunks_exact(8) {
+        let row_length = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as usize;
+        let record_length = u32::from_le_bytes(entry[4..8].try_into().unwrap()) as usize;
+        let record = payload
+            .get(offset..offset + record_length)
+            .ok_or(BiolepticError::InvalidHeaderExtension)?;
+        let row = decompress(record)?;
+        if row.len() != row_length {
+            return Err(BiolepticError::DecompressionError(format!(
+                "Row should hold {row_length} samples, but {} were decoded",
+                row.len()
+            )));
+        }
+        offset += record_length;
+        total_length += row_length;
+        rows.push(row);
+    }
+    if offset != payload.len() || total_length != header.signal_length as usize {
+        return Err(BiolepticError::InvalidHeaderExtension);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+
+    #[test]
+    fn test_rows_round_trip() {
+        let rows = vec![
+            generate_ppg(5000, 120., 75.),
+            generate_ppg(3000, 250., 60.),
+            vec![0f32; 700],
+        ];
+        let options = CompressionOptions::default();
+
+        let compressed = compress_rows(&rows, options).unwrap();
+        let header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        assert!(header.is_rows());
+        assert_eq!(header.signal_length(), 8700);
+
+        let decoded = decompress_rows(&compressed).unwrap();
+        assert_eq!(decoded.len(), 3);
+        for (row, decoded) in rows.iter().zip(decoded.iter()) {
+            assert_eq!(
+                decoded,
+                &decompress(&compress(row, options).unwrap()).unwrap()
+            );
+        }
+
+        assert!(matches!(
+            decompress(&compressed),
+            Err(BiolepticError::DecompressionError(_))
+        ));
+        assert!(crate::Validator::validate(&compressed).unwrap().is_valid());
+
+        let single = compress(&rows[0], options).unwrap();
+        assert_eq!(decompress_rows(&single).unwrap().len(), 1);
+        assert!(compress_rows(&[], options).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::compressor::{CoefficientWidth, quantize};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Replaces every sample with `(x - offset) * scale`.
+///
+/// SSE2 is part of the x86_64 baseline, so it's selected at compile time there and other
+/// targets run the scalar loop. Every lane does exactly the scalar arithmetic, so the
+/// output is bit-identical either way.
+pub(crate) fn normalize(data: &mut [f32], offset: f32, scale: f32) {
+    // SAFETY: SSE2 is enabled on every x86_64 target.
+    #[cfg(target_arch = "x86_64")]
+    let done = unsafe { normalize_sse2(data, offset, scale) };
+    #[cfg(not(target_arch = "x86_64"))]
+    let done = 0;
+    normalize_scalar(&mut data[done..], offset, scale);
+}
+
+/// Quantizes every coefficient of `src` with [`quantize`], vectorized like [`normalize`].
+pub(crate) fn quantize_slice(src: &[f32], multiplier: f64, width: CoefficientWidth) -> Vec<i32> {
+    let mut dst = vec![0i32; src.len()];
+    // SAFETY: SSE2 is enabled on every x86_64 target.
+    #[cfg(target_arch = "x86_64")]
+    let done = unsafe { quantize_sse2(src, &mut dst, multiplier, width) };
+    #[cfg(not(target_arch = "x86_64"))]
+    let done = 0;
+    quantize_scalar(&src[done..], &mut dst[done..], multiplier, width);
+    dst
+}
+
+fn normalize_scalar(data: &mut [f32], offset: f32, scale: f32) {
+    for x in data.iter_mut() {
+        *x = (*x - offset) * scale;
+    }
+}
+
+fn quantize_scalar(src: &[f32], dst: &mut [i32], multiplier: f64, width: CoefficientWidth) {
+    for (dst, &x) in dst.iter_mut().zip(src.iter()) {
+        *dst = quantize(x, multiplier, width);
+    }
+}
+
+/// Normalizes whole blocks of 4 samples, returning how many samples were processed.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+fn normalize_sse2(data: &mut [f32], offset: f32, scale: f32) -> usize {
+    let v_offset = _mm_set1_ps(offset);
+    let v_scale = _mm_set1_ps(scale);
+    let len = data.len();
+    let mut chunks = data.chunks_exact_mut(4);
+    for chunk in &mut chunks {
+        // SAFETY: the chunk holds exactly 4 `f32`, unaligned access is used.
+        unsafe {
+            let x = _mm_loadu_ps(chunk.as_ptr());
+            let q = _mm_mul_ps(_mm_sub_ps(x, v_offset), v_scale);
+            _mm_storeu_ps(chunk.as_mut_ptr(), q);
+        }
+    }
+    len - chunks.into_remainder().len()
+}
+
+/// Quantizes whole blocks of 4 coefficients, returning how many were processed.
+///
+/// `minpd` returns its second operand when the first is NaN, which matches `f64::min`,
+/// and clamping to the `i32` range first makes `cvttpd2dq` agree with the saturating
+/// `as` cast for the wide width.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+fn quantize_sse2(src: &[f32], dst: &mut [i32], multiplier: f64, width: CoefficientWidth) -> usize {
+    let (q_min, q_max) = match width {
+        CoefficientWidth::I16 => (i16::MIN as f64, i16::MAX as f64),
+        CoefficientWidth::I32 => (i32::MIN as f64, i32::MAX as f64),
+    };
+    let v_multiplier = _mm_set1_pd(multiplier);
+    let v_min = _mm_set1_pd(q_min);
+    let v_max = _mm_set1_pd(q_max);
+    let quantize_pair = |x: __m128| {
+        let scaled = _mm_mul_pd(_mm_cvtps_pd(x), v_multiplier);
+        _mm_cvttpd_epi32(_mm_max_pd(_mm_min_pd(scaled, v_max), v_min))
+    };
+    let mut processed = 0usize;
+    for (src, dst) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+        // SAFETY: both chunks hold exactly 4 elements, unaligned access is used.
+        unsafe {
+            let x = _mm_loadu_ps(src.as_ptr());
+            let lo = quantize_pair(x);
+            let hi = quantize_pair(_mm_movehl_ps(x, x));
+            _mm_storeu_si128(dst.as_mut_ptr().cast(), _mm_unpacklo_epi64(lo, hi));
+        }
+        processed += 4;
+    }
+    processed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        let mut signal = generate_ppg(10007, 120., 75.);
+        signal.extend_from_slice(&[f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -0., 1e30]);
+
+        let mut scalar = signal.clone();
+        normalize_scalar(&mut scalar, 61.5, 0.013);
+        let mut vectorized = signal.clone();
+        normalize(&mut vectorized, 61.5, 0.013);
+        assert_eq!(
+            scalar.iter().map(|x| x.to_bits()).collect::<Vec<u32>>(),
+            vectorized.iter().map(|x| x.to_bits()).collect::<Vec<u32>>()
+        );
+
+        for width in [CoefficientWidth::I16, CoefficientWidth::I32] {
+            for multiplier in [64., 2048., 1048576.] {
+                let mut expected = vec![0i32; signal.len()];
+                quantize_scalar(&scalar, &mut expected, multiplier, width);
+                assert_eq!(quantize_slice(&scalar, multiplier, width), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,314 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::decompressor::decompress;
+use crate::header::FLAG_EXTENDED;
+use crate::{BIOLEPTIC_HEADER_SIZE, BiolepticError, BiolepticHeader, CompressionOptions, compress};
+use std::io::Read;
+
+/// Compresses an unbounded signal frame by frame as it arrives, e.g. from a device.
+///
+/// Every full frame of `frame_len` samples becomes an independent record, so memory stays
+/// bounded by one frame and the signal length is not limited. The records concatenate into
+/// a stream readable by [`StreamingDecompressor`], [`crate::decode_concatenated`] or
+/// [`crate::decompress_streaming`]. Each frame is transformed on its own, which costs some
+/// ratio and can show seams at frame boundaries, so prefer long frames.
+pub struct StreamingCompressor {
+    options: CompressionOptions,
+    frame_len: usize,
+    pending: Vec<f32>,
+}
+
+impl StreamingCompressor {
+    /// Creates a compressor emitting one record per `frame_len` samples.
+    pub fn new(options: CompressionOptions, frame_len: usize) -> Result<Self, BiolepticError> {
+        if frame_len == 0 || frame_len > i32::MAX as usize {
+            return Err(BiolepticError::UnsupportedCompressorConfiguration(format!(
+                "Frame length must be in 1..={}, but it was {frame_len}",
+                i32::MAX
+            )));
+        }
+        Ok(Self {
+            options,
+            frame_len,
+            pending: Vec::with_capacity(frame_len),
+        })
+    }
+
+    /// Buffers `samples` and returns the records of every frame they complete, concatenated,
+    /// or `None` while the current frame is still filling up.
+    pub fn push(&mut self, samples: &[f32]) -> Result<Option<Vec<u8>>, BiolepticError> {
+        let mut output: Option<Vec<u8>> = None;
+        let mut samples = samples;
+        while !samples.is_empty() {
+            let take = (self.frame_len - self.pending.len()).min(samples.len());
+            self.pending.extend_from_slice(&samples[..take]);
+            samples = &samples[take..];
+            if self.pending.len() == self.frame_len {
+                let record = compress(&self.pending, self.options)?;
+                self.pending.clear();
+                output.get_or_insert_with(Vec::new).extend(record);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Compresses the samples of the last, partial frame. Returns an empty vector when
+    /// the signal ended on a frame boundary.
+    pub fn finish(self) -> Result<Vec<u8>, BiolepticError> {
+        if self.pending.is_empty() {
+            return Ok(Vec::new());
+        }
+        compress(&self.pending, self.options)
+    }
+}
+
+/// Decodes the records of a [`StreamingCompressor`] from arbitrarily split chunks of bytes.
+///
+/// Bytes are buffered until a record is complete, so only the unfinished record is held.
+/// Records are decoded with [`decompress`], which rejects `Int16` ones.
+#[derive(Default)]
+pub struct StreamingDecompressor {
+    buffer: Vec<u8>,
+}
+
+impl StreamingDecompressor {
+    /// Creates a decompressor with an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `bytes` and returns the samples of every record they complete, in order.
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<f32>, BiolepticError> {
+        self.buffer.extend_from_slice(bytes);
+        let mut samples = Vec::new();
+        let mut consumed = 0usize;
+        while let Some(length) = complete_record_length(&self.buffer[consumed..])? {
+            samples.extend(decompress(&self.buffer[consumed..consumed + length])?);
+            consumed += length;
+        }
+        self.buffer.drain(..consumed);
+        Ok(samples)
+    }
+
+    /// Ends the stream, failing if it stopped in the middle of a record.
+    pub fn finish(self) -> Result<(), BiolepticError> {
+        if !self.buffer.is_empty() {
+            return Err(BiolepticError::DecompressionError(format!(
+                "Stream ended inside a record, {} bytes left over",
+                self.buffer.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Lazily decodes the records of a concatenated stream, e.g. a multi-gigabyte archive
+/// written by [`StreamingCompressor`], from any [`Read`].
+///
+/// Yields the samples of one record at a time, so only a single record is held in memory.
+/// Records are decoded with [`decompress`], which rejects `Int16` ones. A stream ending inside a record, or a read failure, is reported as the last item
+/// instead of silently ending the iteration. Wrap unbuffered sources such as files in a
+/// [`std::io::BufReader`].
+pub struct BiolepticReader<R: Read> {
+    reader: R,
+    record: Vec<u8>,
+    finished: bool,
+}
+
+impl<R: Read> BiolepticReader<R> {
+    /// Creates a reader decoding records from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            record: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the next record into `self.record`, returning `false` at a clean end of stream.
+    fn read_record(&mut self) -> Result<bool, BiolepticError> {
+        self.record.clear();
+        self.read_bytes(BIOLEPTIC_HEADER_SIZE)?;
+        if self.record.is_empty() {
+            return Ok(false);
+        }
+        self.expect_length(BIOLEPTIC_HEADER_SIZE)?;
+        let header = BiolepticHeader::from_bytes(&self.record)?;
+        let payload_end = BIOLEPTIC_HEADER_SIZE + header.compressed_size as usize;
+        self.read_bytes(header.compressed_size as usize)?;
+        self.expect_length(payload_end)?;
+        if header.flags & FLAG_EXTENDED != 0 {
+            self.read_bytes(4)?;
+            self.expect_length(payload_end + 4)?;
+            let length = u32::from_le_bytes(self.record[payload_end..].try_into().unwrap());
+            self.read_bytes(length as usize)?;
+            self.expect_length(payload_end + 4 + length as usize)?;
+        }
+        Ok(true)
+    }
+
+    /// Appends up to `count` bytes, fewer only when the stream ends.
+    fn read_bytes(&mut self, count: usize) -> Result<(), BiolepticError> {
+        // Growing with the data instead of reserving `count` keeps a corrupt length from
+        // allocating gigabytes up front.
+        (&mut self.reader)
+            .take(count as u64)
+            .read_to_end(&mut self.record)
+            .map_err(|x| BiolepticError::DecompressionError(x.to_string()))?;
+        Ok(())
+    }
+
+    fn expect_length(&self, length: usize) -> Result<(), BiolepticError> {
+        if self.record.len() < length {
+            return Err(BiolepticError::DecompressionError(format!(
+                "Stream ended inside a record, expected {length} bytes but got {}",
+                self.record.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for BiolepticReader<R> {
+    type Item = Result<Vec<f32>, BiolepticError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let decoded = match self.read_record() {
+            Ok(false) => None,
+            Ok(true) => Some(decompress(&self.record)),
+            Err(e) => Some(Err(e)),
+        };
+        // Nothing after a broken record can be located, so stop here.
+        self.finished = !matches!(decoded, Some(Ok(_)));
+        decoded
+    }
+}
+
+/// Returns the length of the record at the start of `bytes`, or `None` if it isn't complete yet.
+fn complete_record_length(bytes: &[u8]) -> Result<Option<usize>, BiolepticError> {
+    if bytes.len() < BIOLEPTIC_HEADER_SIZE {
+        return Ok(None);
+    }
+    let header = BiolepticHeader::from_bytes(bytes)?;
+    let payload_end = BIOLEPTIC_HEADER_SIZE + header.compressed_size as usize;
+    if header.flags & FLAG_EXTENDED == 0 {
+        return Ok((bytes.len() >= payload_end).then_some(payload_end));
+    }
+    let Some(length_bytes) = bytes.get(payload_end..payload_end + 4) else {
+        return Ok(None);
+    };
+    let record_end =
+        payload_end + 4 + u32::from_le_bytes(length_bytes.try_into().unwrap()) as usize;
+    Ok((bytes.len() >= record_end).then_some(record_end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::decode_concatenated;
+
+    #[test]
+    fn test_streaming_round_trip() {
+        let signal = generate_ppg(10500, 120., 75.);
+        let options = CompressionOptions::default().with_envelope(64);
+        let mut compressor = StreamingCompressor::new(options, 2000).unwrap();
+        let mut stream = Vec::new();
+        for chunk in signal.chunks(700) {
+            if let Some(records) = compressor.push(chunk).unwrap() {
+                stream.extend(records);
+            }
+        }
+        stream.extend(compressor.finish().unwrap());
+
+        let expected = signal
+            .chunks(2000)
+            .flat_map(|frame| decompress(&compress(frame, options).unwrap()).unwrap())
+            .collect::<Vec<f32>>();
+        assert_eq!(decode_concatenated(&stream).count(), 6);
+
+        let mut decompressor = StreamingDecompressor::new();
+        let mut decoded = Vec::new();
+        for chunk in stream.chunks(333) {
+            decoded.extend(decompressor.push(chunk).unwrap());
+        }
+        decompressor.finish().unwrap();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_streaming_edge_cases() {
+        assert!(StreamingCompressor::new(CompressionOptions::default(), 0).is_err());
+
+        let mut compressor = StreamingCompressor::new(CompressionOptions::default(), 100).unwrap();
+        let records = compressor.push(&generate_ppg(250, 120., 75.)).unwrap();
+        let records = records.unwrap();
+        assert_eq!(decode_concatenated(&records).count(), 2);
+        assert!(!compressor.finish().unwrap().is_empty());
+
+        let mut decompressor = StreamingDecompressor::new();
+        assert!(decompressor.push(&records[..records.len() - 1]).is_ok());
+        assert!(decompressor.finish().is_err());
+    }
+
+    #[test]
+    fn test_reader() {
+        let signal = generate_ppg(5500, 120., 75.);
+        let options = CompressionOptions::default().with_envelope(64);
+        let mut compressor = StreamingCompressor::new(options, 2000).unwrap();
+        let mut stream = compressor.push(&signal).unwrap().unwrap();
+        stream.extend(compressor.finish().unwrap());
+
+        let expected = decode_concatenated(&stream)
+            .collect::<Result<Vec<Vec<f32>>, BiolepticError>>()
+            .unwrap();
+        let decoded = BiolepticReader::new(stream.as_slice())
+            .collect::<Result<Vec<Vec<f32>>, BiolepticError>>()
+            .unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded, expected);
+        assert_eq!(BiolepticReader::new([0u8; 0].as_slice()).count(), 0);
+
+        for cut in [1, 10, BIOLEPTIC_HEADER_SIZE + 3, 100] {
+            let truncated = &stream[..stream.len() - cut];
+            let items = BiolepticReader::new(truncated).collect::<Vec<_>>();
+            assert_eq!(items.len(), 3, "cut {cut}");
+            assert!(items[..2].iter().all(|x| x.is_ok()));
+            assert!(items[2].is_err());
+        }
+    }
+}
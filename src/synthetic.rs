@@ -0,0 +1,123 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+
+/// Generates a deterministic PPG-like signal, e.g. as a reproducible benchmark fixture.
+///
+/// Every beat is modeled as a systolic peak, a dicrotic notch and a diastolic peak, on top
+/// of a slow respiratory baseline and a small pseudo-random noise. Values are in raw ADC
+/// units of roughly `0..3500`; the same arguments always give the same samples.
+pub fn synthetic_ppg(samples: usize, sample_rate: f32, heart_rate_bpm: f32) -> Vec<f32> {
+    let rr_interval = 60.0 / heart_rate_bpm;
+    let mut signal = vec![0.0f32; samples];
+
+    for (i, dst) in signal.iter_mut().enumerate() {
+        let t = i as f32 / sample_rate;
+        let phase = (t / rr_interval).fract();
+
+        // systolic rise — fast gaussian peak at ~25% of cycle
+        let systolic = 1.0 * gaussian(phase, 0.25, 0.06);
+
+        // dicrotic notch — small dip at ~45% of cycle
+        let notch = -0.08 * gaussian(phase, 0.45, 0.02);
+
+        // diastolic peak — smaller secondary bump at ~55% of cycle
+        let diastolic = 0.15 * gaussian(phase, 0.55, 0.04);
+
+        // slow baseline variation simulating respiration (~0.3 Hz)
+        let baseline = 0.03 * (2.0 * std::f32::consts::PI * 0.3 * t).sin();
+
+        // noise
+        let noise = 0.005 * pseudo_noise(i);
+
+        *dst = (systolic + notch + diastolic + baseline + noise) * 3500.0;
+    }
+
+    signal
+}
+
+/// Generates a deterministic ECG-like signal in millivolts, e.g. as a reproducible
+/// benchmark fixture.
+///
+/// Every beat is the sum of gaussian P, Q, R, S and T waves with an R peak of about 1 mV,
+/// on top of a slow baseline wander and a small pseudo-random noise.
+pub fn synthetic_ecg(samples: usize, sample_rate: f32, heart_rate_bpm: f32) -> Vec<f32> {
+    let rr_interval = 60.0 / heart_rate_bpm;
+    let mut signal = vec![0.0f32; samples];
+
+    for (i, dst) in signal.iter_mut().enumerate() {
+        let t = i as f32 / sample_rate;
+        let phase = (t / rr_interval).fract();
+
+        let p = 0.12 * gaussian(phase, 0.2, 0.025);
+        let q = -0.1 * gaussian(phase, 0.37, 0.008);
+        let r = 1.0 * gaussian(phase, 0.4, 0.01);
+        let s = -0.2 * gaussian(phase, 0.43, 0.008);
+        let t_wave = 0.3 * gaussian(phase, 0.65, 0.04);
+
+        // baseline wander from breathing and electrode motion (~0.2 Hz)
+        let baseline = 0.05 * (2.0 * std::f32::consts::PI * 0.2 * t).sin();
+
+        let noise = 0.01 * pseudo_noise(i);
+
+        *dst = p + q + r + s + t_wave + baseline + noise;
+    }
+
+    signal
+}
+
+#[inline]
+pub(crate) fn gaussian(x: f32, mean: f32, std: f32) -> f32 {
+    (-(x - mean).powi(2) / (2.0 * std.powi(2))).exp()
+}
+
+/// Deterministic pseudo-noise via LCG, avoids rand dependency
+#[inline]
+pub(crate) fn pseudo_noise(i: usize) -> f32 {
+    let x = (i as u32).wrapping_mul(1664525).wrapping_add(1013904223);
+    // map to [-1, 1]
+    (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionOptions, compress, decompress, prd};
+
+    #[test]
+    fn test_synthetic_ecg_round_trip() {
+        let signal = synthetic_ecg(10000, 360., 72.);
+        assert_eq!(signal, synthetic_ecg(10000, 360., 72.));
+        let peak = signal.iter().fold(f32::MIN, |a, &b| a.max(b));
+        assert!((0.9..1.2).contains(&peak), "peak {peak}");
+
+        let compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        let decoded = decompress(&compressed).unwrap();
+        assert!(prd(&signal, &decoded) < 1.);
+    }
+}
@@ -0,0 +1,368 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+use crate::decompressor::{decode_quantized, decode_samples};
+use crate::delta::decode_delta_residual;
+use crate::extension::{find_extension, record_length};
+use crate::header::{FLAG_WIDE_COEFFICIENTS, check_header_crc, check_version};
+use crate::multichannel::decompress_multichannel;
+use crate::rows::decompress_rows;
+use crate::{
+    BIOLEPTIC_HEADER_SIZE, BIOLEPTIC_MAGIC, BiolepticError, BiolepticHeader, CompressionMethod,
+    DataType, QuantizationScale,
+};
+
+/// A single failed check of a [`ValidationReport`].
+#[derive(Debug)]
+pub struct ValidationIssue {
+    /// Stable name of the check, e.g. `"version"`.
+    pub check: &'static str,
+    /// What the check found.
+    pub error: BiolepticError,
+}
+
+/// Outcome of every check [`Validator::validate`] ran on a file.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Checks that passed.
+    pub passed: Vec<&'static str>,
+    /// Checks that failed, in the order they ran.
+    pub failed: Vec<ValidationIssue>,
+    /// Checks that couldn't run because the header fields they depend on are invalid.
+    pub skipped: Vec<&'static str>,
+    /// Wavelet of the stream, `None` when the header doesn't parse.
+    pub method: Option<CompressionMethod>,
+    /// DWT levels of the stream, `None` when the header doesn't parse.
+    pub levels: Option<u8>,
+    /// Quantization scale of the stream, `None` when the header doesn't parse.
+    pub scale: Option<QuantizationScale>,
+    /// Whether the buffer ends exactly after the `compressed_size` bytes of payload and
+    /// the extension section. Trailing bytes aren't an error, they may be further records.
+    pub exact_length: bool,
+}
+
+impl ValidationReport {
+    /// Returns true when no check failed or was skipped.
+    pub fn is_valid(&self) -> bool {
+        self.failed.is_empty() && self.skipped.is_empty()
+    }
+
+    /// Returns true when `check` failed.
+    pub fn has_failed(&self, check: &str) -> bool {
+        self.failed.iter().any(|x| x.check == check)
+    }
+
+    fn record(&mut self, check: &'static str, result: Result<(), BiolepticError>) {
+        match result {
+            Ok(()) => self.passed.push(check),
+            Err(error) => self.failed.push(ValidationIssue { check, error }),
+        }
+    }
+}
+
+/// Runs every consistency check on a file and reports all findings at once, instead of
+/// stopping at the first error like [`crate::decompress`], e.g. for repair or forensics
+/// tools.
+///
+/// Header fields are checked one by one: magic, version, data type, compression method,
+/// scale and level ranges, normalization values, header checksum and payload encoding. The
+/// payload size and the extension section are checked against the buffer, and the
+/// payload is inflated to confirm its coefficient count matches the level layout. Finally
+/// the signal is reconstructed and its length compared to `signal_length`. The payload
+/// checks need a parsable header and are skipped otherwise.
+pub struct Validator;
+
+impl Validator {
+    /// Validates `bytes`, failing only when they are too short to hold a header.
+    pub fn validate(bytes: &[u8]) -> Result<ValidationReport, BiolepticError> {
+        if bytes.len() < BIOLEPTIC_HEADER_SIZE {
+            return Err(BiolepticError::InvalidHeader);
+        }
+        let mut report = ValidationReport::default();
+        let u16_at = |at: usize| u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap());
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        let magic: [u8; 4] = bytes[0..4].try_into().unwrap();
+        report.record(
+            "magic",
+            if magic == BIOLEPTIC_MAGIC {
+                Ok(())
+            } else {
+                Err(BiolepticError::InvalidMagic(magic))
+            },
+        );
+        let version = u16_at(4);
+        report.record("version", check_version(version));
+        report.record("data_type", DataType::try_from(u16_at(6)).map(|_| ()));
+        report.record(
+            "compression_method",
+            CompressionMethod::try_from(u32_at(8)).map(|_| ()),
+        );
+
+        let levels = bytes[12];
+        report.record(
+            "levels",
+            if (1..=10).contains(&levels) {
+                Ok(())
+            } else {
+                Err(BiolepticError::DecompressionError(format!(
+                    "Supported levels only [1, 10] but it was {levels}"
+                )))
+            },
+        );
+        let scale = bytes[13];
+        let max_scale = if u16_at(14) & FLAG_WIDE_COEFFICIENTS != 0 {
+            QuantizationScale::S20
+        } else {
+            QuantizationScale::S12
+        };
+        report.record(
+            "scale",
+            QuantizationScale::try_from(scale).and_then(|x| {
+                if x <= max_scale {
+                    Ok(())
+                } else {
+                    Err(BiolepticError::InvalidQuantizationScale(scale))
+                }
+            }),
+        );
+
+        let (min, max, mean) = (
+            f32::from_bits(u32_at(20)),
+            f32::from_bits(u32_at(24)),
+            f32::from_bits(u32_at(28)),
+        );
+        report.record(
+            "range",
+            if min.is_finite() && max.is_finite() && mean.is_finite() && min <= max {
+                Ok(())
+            } else {
+                Err(BiolepticError::InvalidHeader)
+            },
+        );
+
+        report.record("header_checksum", check_header_crc(bytes));
+
+        let compressed_size = u32_at(32) as usize;
+        report.record(
+            "compressed_size",
+            if BIOLEPTIC_HEADER_SIZE + compressed_size <= bytes.len() {
+                Ok(())
+            } else {
+                Err(BiolepticError::DecompressionError(format!(
+                    "Payload needs {} bytes, but only {} are present",
+                    BIOLEPTIC_HEADER_SIZE + compressed_size,
+                    bytes.len()
+                )))
+            },
+        );
+
+        let header = match BiolepticHeader::from_bytes(bytes) {
+            Ok(header) => header,
+            Err(_) => {
+                report.skipped.extend([
+                    "payload_encoding",
+                    "extensions",
+                    "coefficients",
+                    "reconstruction",
+                ]);
+                return Ok(report);
+            }
+        };
+        report.method = header.compression_method().ok();
+        report.levels = Some(header.levels);
+        report.scale = header.scale().ok();
+        report.exact_length = record_length(bytes, &header).is_ok_and(|x| x == bytes.len());
+        report.record("payload_encoding", header.payload_encoding().map(|_| ()));
+        // An unused tag walks the whole section, surfacing any malformed entry.
+        report.record(
+            "extensions",
+            find_extension(bytes, &header, [0; 4]).map(|_| ()),
+        );
+
+        if report.has_failed("levels") || report.has_failed("scale") {
+            report.skipped.extend(["coefficients", "reconstruction"]);
+            return Ok(report);
+        }
+        let signal_length = header.signal_length as usize;
+        let check_length = |decoded: usize| {
+            if decoded == signal_length {
+                Ok(())
+            } else {
+                Err(BiolepticError::DecompressionError(format!(
+                    "Reconstructed {decoded} samples, but the header declares {signal_length}"
+                )))
+            }
+        };
+        if header.channels > 1 || header.is_rows() {
+            // Channel layouts live in the channel records, decoding checks them all.
+            let decoded = if header.is_rows() {
+                // Rows differ in length, only their total matches the header.
+                decompress_rows(bytes).map(|x| vec![x.concat()])
+            } else {
+                decompress_multichannel(bytes)
+            };
+            match decoded {
+                Ok(channels) => {
+                    report.passed.push("coefficients");
+                    report.record(
+                        "reconstruction",
+                        channels.iter().try_for_each(|x| check_length(x.len())),
+                    );
+                }
+                Err(error) => {
+                    report.record("coefficients", Err(error));
+                    report.skipped.push("reconstruction");
+                }
+            }
+            return Ok(report);
+        }
+        if header.is_zero_signal() {
+            report.skipped.push("coefficients");
+        } else {
+            let coefficients = header.coefficient_layout().and_then(|layout| {
+                let decoded = decode_quantized(bytes, &header)?.len();
+                if decoded == layout.total() {
+                    Ok(())
+                } else {
+                    Err(BiolepticError::DecompressionError(format!(
+                        "Payload holds {decoded} coefficients, but the layout needs {}",
+                        layout.total()
+                    )))
+                }
+            });
+            report.record("coefficients", coefficients);
+            if report.has_failed("coefficients") {
+                report.skipped.push("reconstruction");
+                return Ok(report);
+            }
+        }
+        // Delta streams need their reference to decode, the residual alone checks framing.
+        let reconstruction = if header.is_delta() {
+            decode_delta_residual(bytes)
+        } else {
+            // Any data type is checked, Int16 streams included.
+            let mut signal = Vec::new();
+            decode_samples(bytes, &header, None, 1., 0., &mut signal).map(|_| signal)
+        };
+        report.record(
+            "reconstruction",
+            reconstruction.and_then(|x| check_length(x.len())),
+        );
+
+        Ok(report)
+    }
+}
+
+/// Validates `bytes` with every check of [`Validator::validate`], e.g. for integrity sweeps
+/// over stored files without the original signal.
+pub fn validate(bytes: &[u8]) -> Result<ValidationReport, BiolepticError> {
+    Validator::validate(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::tests::generate_ppg;
+    use crate::{CompressionOptions, compress, compress_i16};
+
+    #[test]
+    fn test_valid_file_passes_everything() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let compressed =
+            compress(&signal, CompressionOptions::default().with_envelope(64)).unwrap();
+        let report = Validator::validate(&compressed).unwrap();
+        assert!(report.is_valid(), "{report:?}");
+        assert!(report.passed.contains(&"coefficients"));
+        assert!(report.passed.contains(&"reconstruction"));
+        assert_eq!(report.method, Some(CompressionMethod::Cdf97));
+        assert_eq!(report.scale, Some(QuantizationScale::S11));
+        assert!(report.levels.is_some());
+        assert!(report.exact_length);
+
+        let mut padded = compressed.clone();
+        padded.extend_from_slice(&[0; 8]);
+        let report = validate(&padded).unwrap();
+        assert!(report.is_valid(), "{report:?}");
+        assert!(!report.exact_length);
+    }
+
+    #[test]
+    fn test_int16_file_passes() {
+        let samples = generate_ppg(5000, 120., 75.)
+            .iter()
+            .map(|&x| (x * 1000.) as i16)
+            .collect::<Vec<i16>>();
+        for options in [
+            CompressionOptions::default(),
+            CompressionOptions {
+                lossless: true,
+                method: CompressionMethod::Cdf53,
+                ..Default::default()
+            },
+        ] {
+            let compressed = compress_i16(&samples, options).unwrap();
+            let report = validate(&compressed).unwrap();
+            assert!(report.is_valid(), "{report:?}");
+            assert!(report.passed.contains(&"reconstruction"));
+        }
+    }
+
+    #[test]
+    fn test_reports_every_corruption() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let mut compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        compressed[4] = 0xEE;
+        compressed[13] = 30;
+        compressed.truncate(compressed.len() - 10);
+
+        let report = Validator::validate(&compressed).unwrap();
+        for check in ["version", "scale", "compressed_size", "header_checksum"] {
+            assert!(report.has_failed(check), "{check} not reported: {report:?}");
+        }
+        assert_eq!(report.failed.len(), 4);
+        assert!(report.passed.contains(&"magic"));
+        assert!(report.skipped.contains(&"coefficients"));
+
+        assert!(Validator::validate(&compressed[..10]).is_err());
+    }
+
+    #[test]
+    fn test_reports_payload_corruption() {
+        let signal = generate_ppg(5000, 120., 75.);
+        let mut compressed = compress(&signal, CompressionOptions::default()).unwrap();
+        // Claim only half of the deflate stream, which can't inflate to a full layout.
+        let mut header = BiolepticHeader::from_bytes(&compressed).unwrap();
+        header.compressed_size /= 2;
+        compressed[..BIOLEPTIC_HEADER_SIZE].copy_from_slice(&header.to_bytes());
+        let report = Validator::validate(&compressed).unwrap();
+        assert!(report.has_failed("coefficients"), "{report:?}");
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.skipped.contains(&"reconstruction"));
+    }
+}
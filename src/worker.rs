@@ -0,0 +1,87 @@
+/*
+ * // Copyright (c) Radzivon Bartoshyk 2/2026. All rights reserved.
+ * //
+ * // Redistribution and use in source and binary forms, with or without modification,
+ * // are permitted provided that the following conditions are met:
+ * //
+ * // 1.  Redistributions of source code must retain the above copyright notice, this
+ * // list of conditions and the following disclaimer.
+ * //
+ * // 2.  Redistributions in binary form must reproduce the above copyright notice,
+ * // this list of conditions and the following disclaimer in the documentation
+ * // and/or other materials provided with the distribution.
+ * //
+ * // 3.  Neither the name of the copyright holder nor the names of its
+ * // contributors may be used to endorse or promote products derived from
+ * // this software without specific prior written permission.
+ * //
+ * // THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+ * // AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+ * // IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+ * // DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+ * // FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+ * // DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+ * // SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+ * // CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+ * // OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+ * // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+ */
+/// Builds the `f32` DWT executor matching a [`crate::CompressionMethod`] and
+/// [`crate::BorderMode`].
+///
+/// Shared by the encoder and the decoder so both always agree on the transform.
+macro_rules! make_dwt_worker {
+    ($method:expr, $border:expr) => {{
+        let border = match $border {
+            $crate::BorderMode::Wrap => osclet::BorderMode::Wrap,
+            $crate::BorderMode::Reflect => osclet::BorderMode::Reflect,
+            $crate::BorderMode::Clamp => osclet::BorderMode::Clamp,
+            $crate::BorderMode::Zeros => osclet::BorderMode::Zeros,
+        };
+        match $method {
+            $crate::CompressionMethod::Cdf53 => osclet::Osclet::make_cdf53_f32(),
+            $crate::CompressionMethod::Cdf97 => osclet::Osclet::make_cdf97_f32(),
+            $crate::CompressionMethod::Db4 => {
+                osclet::Osclet::make_daubechies_f32(osclet::DaubechiesFamily::Db4, border)
+            }
+            $crate::CompressionMethod::Sym4 => {
+                osclet::Osclet::make_symlet_f32(osclet::SymletFamily::Sym4, border)
+            }
+            $crate::CompressionMethod::Db2 => {
+                osclet::Osclet::make_daubechies_f32(osclet::DaubechiesFamily::Db2, border)
+            }
+            $crate::CompressionMethod::Db6 => {
+                osclet::Osclet::make_daubechies_f32(osclet::DaubechiesFamily::Db6, border)
+            }
+            $crate::CompressionMethod::Db8 => {
+                osclet::Osclet::make_daubechies_f32(osclet::DaubechiesFamily::Db8, border)
+            }
+            $crate::CompressionMethod::Coif1 => {
+                osclet::Osclet::make_coiflet_f32(osclet::CoifletFamily::Coif1, border)
+            }
+            $crate::CompressionMethod::Coif2 => {
+                osclet::Osclet::make_coiflet_f32(osclet::CoifletFamily::Coif2, border)
+            }
+            $crate::CompressionMethod::Coif3 => {
+                osclet::Osclet::make_coiflet_f32(osclet::CoifletFamily::Coif3, border)
+            }
+            $crate::CompressionMethod::Sym6 => {
+                osclet::Osclet::make_symlet_f32(osclet::SymletFamily::Sym6, border)
+            }
+            $crate::CompressionMethod::Sym8 => {
+                osclet::Osclet::make_symlet_f32(osclet::SymletFamily::Sym8, border)
+            }
+            $crate::CompressionMethod::Bior22 => {
+                osclet::Osclet::make_biorthogonal_f32(osclet::BiorthogonalFamily::Bior2_2, border)
+            }
+            $crate::CompressionMethod::Bior44 => {
+                osclet::Osclet::make_biorthogonal_f32(osclet::BiorthogonalFamily::Bior4_4, border)
+            }
+            $crate::CompressionMethod::Bior68 => {
+                osclet::Osclet::make_biorthogonal_f32(osclet::BiorthogonalFamily::Bior6_8, border)
+            }
+        }
+    }};
+}
+
+pub(crate) use make_dwt_worker;